@@ -1,24 +1,166 @@
 use axum::{routing, Router};
 
-pub fn get_app() -> Router {
+use crate::state::AppState;
+
+pub fn get_app(state: AppState) -> Router {
     Router::new()
         .route("/", routing::get(http::root))
         .route("/me", routing::get(http::get_subject))
+        .route("/orders", routing::get(http::get_orders))
+        .route("/trades", routing::get(http::get_trades))
+        .route("/transfers", routing::post(http::post_transfer))
+        .route("/statements", routing::get(http::get_statement))
         .route("/sse", routing::get(sse::root))
         .route("/ws", routing::get(ws::root))
+        .with_state(state)
 }
 
 mod http {
-    use chrono::Utc;
+    use axum::{
+        extract::{Query, State},
+        http::{header, HeaderMap},
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use chrono::{DateTime, Utc};
+    use database::{CursorSigner, DatabaseHandle, Filter, Page, PageRequest};
+    use models::{OrderRaw, TradeRaw};
+    use serde::Deserialize;
+    use transfer::{
+        internal::{
+            models::{Recipient, Transfer, TransferLimits, TransferRequest},
+            service::InternalTransfers,
+        },
+        limits::{
+            models::{LimitPolicy, ReferencePrices},
+            service::WithdrawalLimits,
+        },
+        statement::service::Statements,
+    };
+    use uuid::Uuid;
 
-    use crate::auth::jwt::models::Claims;
+    use crate::{errors::ApiError, user::UserId};
 
     pub async fn root() -> String {
         format!("Hello from server! Time: {}\n", Utc::now())
     }
 
-    pub async fn get_subject(claims: Claims) -> String {
-        claims.sub
+    pub async fn get_subject(UserId(id): UserId) -> String {
+        id.to_string()
+    }
+
+    pub async fn get_orders(
+        UserId(id): UserId,
+        State(database): State<DatabaseHandle>,
+        State(cursors): State<CursorSigner>,
+        Query(request): Query<PageRequest>,
+    ) -> Result<Json<Page<OrderRaw>>, ApiError> {
+        let page = database
+            .repository::<OrderRaw>()
+            .page(&Filter::new().eq("user_id", id), &request, &cursors)
+            .await?;
+        Ok(Json(page))
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct TradesQuery {
+        base_asset_id: Uuid,
+        quote_asset_id: Uuid,
+    }
+
+    /// Trade history of a market; the page is read from the same query string.
+    pub async fn get_trades(
+        State(database): State<DatabaseHandle>,
+        State(cursors): State<CursorSigner>,
+        Query(market): Query<TradesQuery>,
+        Query(request): Query<PageRequest>,
+    ) -> Result<Json<Page<TradeRaw>>, ApiError> {
+        let filter = Filter::new()
+            .eq("base_asset_id", market.base_asset_id)
+            .eq("quote_asset_id", market.quote_asset_id);
+        let page = database
+            .repository::<TradeRaw>()
+            .page(&filter, &request, &cursors)
+            .await?;
+        Ok(Json(page))
+    }
+
+    /// Header carrying the key that makes a retried transfer request a no-op.
+    const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+    #[derive(Debug, Deserialize)]
+    pub struct TransferBody {
+        recipient: Recipient,
+        asset_id: Uuid,
+        amount: i64,
+        #[serde(default)]
+        memo: Option<String>,
+    }
+
+    pub async fn post_transfer(
+        UserId(id): UserId,
+        State(database): State<DatabaseHandle>,
+        State(limits): State<TransferLimits>,
+        State(policy): State<LimitPolicy>,
+        headers: HeaderMap,
+        Json(body): Json<TransferBody>,
+    ) -> Result<Json<Transfer>, ApiError> {
+        let idempotency_key = headers
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let security = WithdrawalLimits::new(database.client(), policy, ReferencePrices::new());
+        let transfer = InternalTransfers::new(database.client(), limits, security)
+            .transfer(&TransferRequest {
+                sender_id: id,
+                recipient: body.recipient,
+                asset_id: body.asset_id,
+                amount: body.amount,
+                memo: body.memo,
+                idempotency_key,
+            })
+            .await?;
+        Ok(Json(transfer))
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum StatementFormat {
+        #[default]
+        Json,
+        Csv,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct StatementQuery {
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+        #[serde(default)]
+        format: StatementFormat,
+    }
+
+    pub async fn get_statement(
+        UserId(id): UserId,
+        State(database): State<DatabaseHandle>,
+        Query(query): Query<StatementQuery>,
+    ) -> Result<Response, ApiError> {
+        let statement = Statements::new(database.client())
+            .generate(id, query.from, query.until)
+            .await?;
+        Ok(match query.format {
+            StatementFormat::Json => Json(statement).into_response(),
+            StatementFormat::Csv => (
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"statement.csv\"",
+                    ),
+                ],
+                statement.to_csv(),
+            )
+                .into_response(),
+        })
     }
 }
 
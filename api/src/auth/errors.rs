@@ -14,12 +14,14 @@ struct AuthErrorResponse {
 pub enum AuthError {
     WrongCredentials,
     InvalidToken,
+    Unavailable,
 }
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
             AuthError::WrongCredentials => (StatusCode::UNAUTHORIZED, "Wrong credentials"),
             AuthError::InvalidToken => (StatusCode::BAD_REQUEST, "Invalid token"),
+            AuthError::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable"),
         };
         let body = Json(AuthErrorResponse {
             error: error_message.to_string(),
@@ -13,7 +13,7 @@ use axum::{
 };
 use chrono::Utc;
 
-use self::{models::Claims, traits::JwtEncodeDecode};
+use self::{models::Claims, traits::JwtDecode};
 
 use super::errors::AuthError;
 
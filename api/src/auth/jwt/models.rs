@@ -1,10 +1,8 @@
-use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
-};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, TokenData, Validation};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
-use super::traits::JwtEncodeDecode;
+use super::traits::JwtDecode;
 
 pub static KEYS: Lazy<Keys> = Lazy::new(|| {
     let secret =
@@ -13,13 +11,11 @@ pub static KEYS: Lazy<Keys> = Lazy::new(|| {
 });
 pub struct Keys {
     pub decoding: DecodingKey,
-    pub encoding: EncodingKey,
 }
 impl Keys {
     fn new(secret: &[u8]) -> Self {
         Self {
             decoding: DecodingKey::from_secret(secret),
-            encoding: EncodingKey::from_secret(secret),
         }
     }
 }
@@ -29,11 +25,8 @@ pub struct Claims {
     pub sub: String,
     pub exp: usize,
 }
-impl JwtEncodeDecode<Self> for Claims {
+impl JwtDecode<Self> for Claims {
     fn decode(token: &str) -> jsonwebtoken::errors::Result<TokenData<Self>> {
         decode::<Claims>(token, &KEYS.decoding, &Validation::new(Algorithm::HS256))
     }
-    fn encode(&self) -> jsonwebtoken::errors::Result<String> {
-        encode(&Header::new(Algorithm::HS256), self, &KEYS.encoding)
-    }
 }
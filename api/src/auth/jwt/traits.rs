@@ -1,6 +1,5 @@
 use jsonwebtoken::TokenData;
 
-pub trait JwtEncodeDecode<T> {
+pub trait JwtDecode<T> {
     fn decode(token: &str) -> jsonwebtoken::errors::Result<TokenData<T>>;
-    fn encode(&self) -> jsonwebtoken::errors::Result<String>;
 }
@@ -1,11 +1,80 @@
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use database::DatabaseError;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use transfer::{
+    internal::errors::TransferError, ledger::errors::LedgerError, statement::errors::StatementError,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error("parse address error")]
     AddressParse(#[from] std::net::AddrParseError),
 
+    #[error("database error")]
+    Database(#[from] database::DatabaseError),
+
     #[error("axum server error")]
     Hyper(#[from] hyper::Error),
 
+    #[error(transparent)]
+    Transfer(#[from] TransferError),
+
+    #[error(transparent)]
+    Statement(#[from] StatementError),
+
     #[error("tracing setup error")]
     Tracing(#[from] tracing::subscriber::SetGlobalDefaultError),
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ApiErrorResponse {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::Database(
+                DatabaseError::InvalidCursor
+                | DatabaseError::InvalidField(_)
+                | DatabaseError::UnsupportedFilter(_),
+            ) => StatusCode::BAD_REQUEST,
+            ApiError::Database(DatabaseError::NotFound) => StatusCode::NOT_FOUND,
+            // Retries on a contended audit chain ran out; the request can be repeated.
+            ApiError::Database(DatabaseError::Conflict)
+            | ApiError::Transfer(
+                TransferError::Database(DatabaseError::Conflict)
+                | TransferError::Ledger(LedgerError::Database(DatabaseError::Conflict)),
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Transfer(
+                TransferError::InvalidAmount
+                | TransferError::SelfTransfer
+                | TransferError::MemoTooLong(_),
+            ) => StatusCode::BAD_REQUEST,
+            ApiError::Transfer(TransferError::RecipientNotFound | TransferError::AssetNotFound) => {
+                StatusCode::NOT_FOUND
+            }
+            ApiError::Transfer(TransferError::IdempotencyConflict) => StatusCode::CONFLICT,
+            ApiError::Transfer(
+                TransferError::InsufficientBalance
+                | TransferError::LimitExceeded(_)
+                | TransferError::CoolingOff(_),
+            ) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Statement(StatementError::InvalidPeriod | StatementError::PeriodTooLong) => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => {
+                tracing::error!("{:?}", self);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let body = Json(ApiErrorResponse {
+            error: self.to_string(),
+        });
+        (status, body).into_response()
+    }
+}
@@ -1,13 +1,26 @@
+use std::time::Duration;
+
+use database::{CursorSigner, DatabaseConfig, DatabaseHandle};
 use once_cell::sync::Lazy;
+use transfer::limits::models::LimitPolicy;
 
 mod app;
 mod auth;
 mod errors;
+mod migrations;
 mod shutdown;
+mod state;
 mod user;
 
 static API_BIND: Lazy<String> =
     Lazy::new(|| std::env::var("KSOX_SERVER_API_BIND").expect("KSOX_SERVER_API_BIND must be set"));
+static TRANSFER_LIMITS: Lazy<String> =
+    Lazy::new(|| std::env::var("KSOX_SERVER_TRANSFER_LIMITS").unwrap_or_default());
+/// Tier of users without one; the API only applies the policy's cooling-off period.
+const DEFAULT_LIMIT_TIER: &str = "basic";
+static CURSOR_SECRET: Lazy<String> = Lazy::new(|| {
+    std::env::var("KSOX_SERVER_CURSOR_SECRET").expect("KSOX_SERVER_CURSOR_SECRET must be set")
+});
 
 #[tokio::main]
 async fn main() -> Result<(), errors::ApiError> {
@@ -22,7 +35,17 @@ async fn main() -> Result<(), errors::ApiError> {
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
-    let app = app::get_app();
+    let database = DatabaseHandle::connect(DatabaseConfig::from_env()?).await?;
+    migrations::run_on_startup(&database).await?;
+    database.spawn_health_check();
+
+    let app = app::get_app(state::AppState {
+        database,
+        cursors: CursorSigner::new(CURSOR_SECRET.as_bytes()),
+        transfer_limits: TRANSFER_LIMITS.parse()?,
+        limit_policy: LimitPolicy::new(DEFAULT_LIMIT_TIER),
+        users: user::KnownUsers::new(Duration::from_secs(60)),
+    });
 
     let addr = API_BIND.parse()?;
     tracing::info!("🚀 server starting at {}", addr);
@@ -0,0 +1,17 @@
+use database::{DatabaseError, DatabaseHandle, Migrator};
+use once_cell::sync::Lazy;
+
+static MIGRATE_ON_STARTUP: Lazy<bool> = Lazy::new(|| {
+    std::env::var("KSOX_SERVER_MIGRATE_ON_STARTUP").is_ok_and(|value| value == "true")
+});
+
+/// Applies the embedded migrations when `KSOX_SERVER_MIGRATE_ON_STARTUP=true`.
+pub async fn run_on_startup(database: &DatabaseHandle) -> Result<(), DatabaseError> {
+    if !*MIGRATE_ON_STARTUP {
+        return Ok(());
+    }
+    for version in Migrator::embedded().run(&database.client(), false).await? {
+        tracing::info!("applied migration {}", version);
+    }
+    Ok(())
+}
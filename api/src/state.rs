@@ -0,0 +1,15 @@
+use axum::extract::FromRef;
+use database::{CursorSigner, DatabaseHandle};
+use transfer::{internal::models::TransferLimits, limits::models::LimitPolicy};
+
+use crate::user::KnownUsers;
+
+/// State shared by all handlers; extractors pick their part of it through `FromRef`.
+#[derive(Debug, Clone, FromRef)]
+pub struct AppState {
+    pub database: DatabaseHandle,
+    pub cursors: CursorSigner,
+    pub transfer_limits: TransferLimits,
+    pub limit_policy: LimitPolicy,
+    pub users: KnownUsers,
+}
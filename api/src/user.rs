@@ -1,57 +1,147 @@
+use std::{sync::Arc, time::Duration};
+
 use axum::{
     async_trait,
     extract::{FromRef, FromRequestParts},
-    headers::{authorization::Bearer, Authorization},
     http::request::Parts,
-    RequestPartsExt, TypedHeader,
+    RequestPartsExt,
 };
-use jsonwebtoken::{decode, Validation};
-use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use surrealdb::{
-    engine::remote::ws::{Client, Ws},
-    sql::Uuid,
-    Surreal,
+use database::{
+    user_key, Actor, AuditCategory, AuditEvent, Cache, DatabaseError, DatabaseHandle, MemoryStore,
 };
+use models::UserRaw;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::auth::{
-    errors::AuthError,
-    jwt::models::{Claims, KEYS},
-};
+use crate::auth::{errors::AuthError, jwt::models::Claims};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserId(pub Uuid);
 
-static _SURREALDB_URL: Lazy<String> = Lazy::new(|| {
-    std::env::var("KSOX_SERVER_SURREALDB_URL").expect("KSOX_SERVER_SURREALDB_URL must be set")
-});
+/// Users recently seen to exist, so authenticating a request does not query the database
+/// every time. A deleted user keeps access for at most the cache's time to live.
+///
+/// Every authentication is recorded in the audit log, whether the user came from the cache
+/// or the database.
+#[derive(Debug, Clone)]
+pub struct KnownUsers(Arc<Cache<MemoryStore>>);
+impl KnownUsers {
+    pub fn new(ttl: Duration) -> Self {
+        Self(Arc::new(Cache::new(MemoryStore::new(), ttl)))
+    }
 
-pub async fn _get_surrealdb() -> surrealdb::Result<Surreal<Client>> {
-    Surreal::new::<Ws>(_SURREALDB_URL.to_string()).await
+    /// Looks the user up in the cache and falls back to the database, caching only users that
+    /// exist so new users are let in as soon as they are created. The outcome is audited
+    /// before it is returned.
+    async fn authenticate(
+        &self,
+        database: &DatabaseHandle,
+        id: Uuid,
+    ) -> Result<Option<UserRaw>, DatabaseError> {
+        let key = user_key(id);
+        let user = match self.0.get(&key).await? {
+            Some(user) => Some(user),
+            None => {
+                let user = database.repository::<UserRaw>().get(&id).await?;
+                if let Some(user) = &user {
+                    self.0.set(&key, user).await?;
+                }
+                user
+            }
+        };
+        let action = match user {
+            Some(_) => "auth.accept",
+            None => "auth.reject",
+        };
+        database
+            .audit_log()
+            .append(AuditEvent::new(
+                Actor::User(id),
+                AuditCategory::Auth,
+                action,
+            ))
+            .await?;
+        Ok(user)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UserId(Uuid);
-
 #[async_trait]
 impl<S> FromRequestParts<S> for UserId
 where
-    Surreal<Client>: FromRef<S>,
+    DatabaseHandle: FromRef<S>,
+    KnownUsers: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AuthError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the token from the authorization header
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
+        let claims = parts.extract::<Claims>().await?;
+        let id = Uuid::parse_str(&claims.sub).map_err(|_| AuthError::InvalidToken)?;
+
+        // The user must still exist
+        let user = KnownUsers::from_ref(state)
+            .authenticate(&DatabaseHandle::from_ref(state), id)
             .await
-            .map_err(|_| AuthError::InvalidToken)?;
-        // Decode the user data
+            .map_err(|err| {
+                tracing::error!("{:?}", err);
+                AuthError::Unavailable
+            })?;
+        match user {
+            Some(user) => Ok(UserId(user.id)),
+            None => Err(AuthError::WrongCredentials),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use database::{DatabaseConfig, DatabaseHandle, Migrator};
+    use models::UserRaw;
+    use uuid::Uuid;
 
-        let _token_data = decode::<Claims>(bearer.token(), &KEYS.decoding, &Validation::default())
-            .map_err(|_| AuthError::InvalidToken)?;
+    use super::KnownUsers;
 
-        let db = Surreal::<Client>::from_ref(state);
-        let _groups = db.query("").bind(("table", "users"));
+    #[tokio::test]
+    async fn every_authentication_is_audited() {
+        let database = DatabaseHandle::connect(DatabaseConfig::new("mem://", "test", "test"))
+            .await
+            .unwrap();
+        Migrator::embedded()
+            .run(&database.client(), false)
+            .await
+            .unwrap();
+        let user = UserRaw {
+            id: Uuid::new_v4(),
+            handle: None,
+        };
+        database
+            .repository::<UserRaw>()
+            .create(&user)
+            .await
+            .unwrap();
+        let users = KnownUsers::new(Duration::from_secs(60));
 
-        Ok(UserId(Uuid::new()))
+        for _ in 0..2 {
+            assert_eq!(
+                users.authenticate(&database, user.id).await.unwrap(),
+                Some(user.clone())
+            );
+        }
+        assert_eq!(
+            users.authenticate(&database, Uuid::new_v4()).await.unwrap(),
+            None
+        );
+
+        let actions: Vec<_> = database
+            .audit_log()
+            .list(0, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event.action)
+            .collect();
+        assert_eq!(actions, ["auth.accept", "auth.accept", "auth.reject"]);
     }
 }
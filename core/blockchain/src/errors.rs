@@ -12,6 +12,9 @@ pub enum EvmNetworkError {
     #[error("transaction error")]
     Transaction,
 
+    #[error("unexpected call result")]
+    CallResult,
+
     #[error("surrealdb error")]
     Surrealdb(#[from] surrealdb::Error),
 }
@@ -2,6 +2,10 @@ mod errors;
 mod traits;
 mod types;
 
+pub use errors::EvmNetworkError;
+pub use traits::{EvmNetworkApi, EvmNetworkChecks, SurrealdbNamedModel};
+pub use types::EvmNetwork;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
@@ -34,6 +34,16 @@ pub trait EvmNetworkApi {
         self,
         transaction_hash: TxHash,
     ) -> Result<Transaction, EvmNetworkError>;
+    /// Native coin balance of `address` at the latest block, in wei.
+    async fn get_balance(self, address: Address) -> Result<U256, EvmNetworkError>;
+    /// Balance of `holder` in the ERC-20 contract at `token`, in its smallest unit.
+    async fn get_token_balance(
+        self,
+        token: Address,
+        holder: Address,
+    ) -> Result<U256, EvmNetworkError>;
+    /// Number of decimals of the ERC-20 contract at `token`.
+    async fn get_token_decimals(self, token: Address) -> Result<u32, EvmNetworkError>;
 }
 
 #[async_trait]
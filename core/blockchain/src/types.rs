@@ -1,10 +1,13 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use ethers::{
-    providers::{Middleware, Provider},
-    types::{Address, Transaction, TxHash, U256},
+    providers::{Http, Middleware, Provider},
+    types::{Address, Bytes, Transaction, TransactionRequest, TxHash, U256},
 };
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
-use url::Url;
+use url::{ParseError, Url};
 
 use crate::{
     errors::EvmNetworkError,
@@ -15,11 +18,23 @@ use crate::{
 pub struct EvmNetwork {
     name: String,
     rpc_url: Url,
+    /// Built on first use and shared by all clones of the network.
+    #[serde(skip)]
+    provider: Arc<OnceCell<Provider<Http>>>,
 }
 #[allow(dead_code)]
 impl EvmNetwork {
     pub fn new(name: String, rpc_url: Url) -> Self {
-        Self { name, rpc_url }
+        Self {
+            name,
+            rpc_url,
+            provider: Arc::default(),
+        }
+    }
+
+    fn provider(&self) -> Result<&Provider<Http>, ParseError> {
+        self.provider
+            .get_or_try_init(|| Provider::try_from(self.rpc_url.as_str()))
     }
 }
 
@@ -32,19 +47,59 @@ impl EvmNetworkApi for EvmNetwork {
         self.rpc_url
     }
     async fn get_chain_id(self) -> Result<U256, EvmNetworkError> {
-        let provider = Provider::try_from(self.rpc_url.as_str())?;
-        Ok(provider.get_chainid().await?)
+        Ok(self.provider()?.get_chainid().await?)
     }
     async fn get_transaction(
         self,
         transaction_hash: TxHash,
     ) -> Result<Transaction, EvmNetworkError> {
-        let provider = Provider::try_from(self.rpc_url.as_str())?;
-        Ok(provider
+        Ok(self
+            .provider()?
             .get_transaction(transaction_hash)
             .await?
             .ok_or_else(|| EvmNetworkError::Transaction)?)
     }
+    async fn get_balance(self, address: Address) -> Result<U256, EvmNetworkError> {
+        Ok(self.provider()?.get_balance(address, None).await?)
+    }
+    async fn get_token_balance(
+        self,
+        token: Address,
+        holder: Address,
+    ) -> Result<U256, EvmNetworkError> {
+        let call = TransactionRequest::new()
+            .to(token)
+            .data(balance_of_calldata(holder));
+        let output = self.provider()?.call(&call.into(), None).await?;
+        if output.len() < 32 {
+            return Err(EvmNetworkError::CallResult);
+        }
+        Ok(U256::from_big_endian(&output[..32]))
+    }
+    async fn get_token_decimals(self, token: Address) -> Result<u32, EvmNetworkError> {
+        let call = TransactionRequest::new()
+            .to(token)
+            .data(Bytes::from(DECIMALS.to_vec()));
+        let output = self.provider()?.call(&call.into(), None).await?;
+        if output.len() < 32 {
+            return Err(EvmNetworkError::CallResult);
+        }
+        let decimals = U256::from_big_endian(&output[..32]);
+        u32::try_from(decimals).map_err(|_| EvmNetworkError::CallResult)
+    }
+}
+
+/// Selector of ERC-20 `balanceOf(address)`.
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Selector of ERC-20 `decimals()`.
+const DECIMALS: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+fn balance_of_calldata(holder: Address) -> Bytes {
+    let mut data = BALANCE_OF.to_vec();
+    data.extend_from_slice(&[0; 12]);
+    data.extend_from_slice(holder.as_bytes());
+    data.into()
 }
 
 #[async_trait]
@@ -0,0 +1,303 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use crate::{
+    errors::DatabaseError,
+    record::{canonical, sha256_hex, Record},
+    transaction::{execute_once, RetryPolicy, CONFLICT},
+};
+
+pub const AUDIT_LOG_TABLE: &str = "audit_log";
+
+/// Previous hash of the first entry in the chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const VERIFY_BATCH_SIZE: usize = 1000;
+
+/// Who performed an audited action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "lowercase")]
+pub enum Actor {
+    User(Uuid),
+    Admin(Uuid),
+    System,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditCategory {
+    Balance,
+    Withdrawal,
+    Admin,
+    Auth,
+}
+
+/// An action to be recorded, e.g. `AuditEvent::new(Actor::User(id), AuditCategory::Auth, "login")`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub actor: Actor,
+    pub category: AuditCategory,
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<IpAddr>,
+}
+impl AuditEvent {
+    pub fn new(actor: Actor, category: AuditCategory, action: impl Into<String>) -> Self {
+        Self {
+            actor,
+            category,
+            action: action.into(),
+            target: None,
+            before: None,
+            after: None,
+            request_id: None,
+            ip: None,
+        }
+    }
+
+    /// The affected record, e.g. `balances:<uuid>`.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// State of the target before and after the action.
+    pub fn change(mut self, before: Option<Value>, after: Option<Value>) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Targets the record changed from `before` to `after`, either of which is `None` if
+    /// the record was created or removed.
+    pub fn record<M: Record>(
+        self,
+        before: Option<&M>,
+        after: Option<&M>,
+    ) -> Result<Self, DatabaseError> {
+        let id = before.or(after).map(Record::id).unwrap_or_default();
+        Ok(self.target(format!("{}:{id}", M::TABLE)).change(
+            before.map(serde_json::to_value).transpose()?,
+            after.map(serde_json::to_value).transpose()?,
+        ))
+    }
+
+    pub fn request(mut self, request_id: impl Into<String>, ip: Option<IpAddr>) -> Self {
+        self.request_id = Some(request_id.into());
+        self.ip = ip;
+        self
+    }
+}
+
+/// A persisted [`AuditEvent`], linked to its predecessor by `previous_hash`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub created_at: DateTime<Utc>,
+    pub previous_hash: String,
+    pub hash: String,
+}
+impl AuditEntry {
+    fn new(sequence: u64, event: AuditEvent, previous_hash: String) -> Result<Self, DatabaseError> {
+        let mut entry = Self {
+            sequence,
+            event,
+            created_at: Utc::now(),
+            previous_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+        Ok(entry)
+    }
+
+    /// SHA-256 over the entry without its own hash, serialized as JSON with sorted keys.
+    pub fn compute_hash(&self) -> Result<String, DatabaseError> {
+        let mut content = serde_json::to_value(self)?;
+        if let Some(object) = content.as_object_mut() {
+            object.remove("hash");
+        }
+        Ok(sha256_hex(&serde_json::to_vec(&canonical(content))?))
+    }
+}
+
+/// The last entry of a verified chain.
+///
+/// Keeping the head outside of the database also reveals entries removed from the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditHead {
+    pub length: u64,
+    pub hash: String,
+}
+
+/// Append-only, hash-chained log of privileged and financial actions.
+///
+/// Each entry is stored under its sequence number, so concurrent appends collide on
+/// the record id and the loser retries on top of the new head.
+#[derive(Debug)]
+pub struct AuditLog<C: Connection> {
+    db: Surreal<C>,
+    retry: RetryPolicy,
+}
+impl<C: Connection> Clone for AuditLog<C> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            retry: self.retry.clone(),
+        }
+    }
+}
+impl<C: Connection> AuditLog<C> {
+    pub fn new(db: Surreal<C>) -> Self {
+        Self {
+            db,
+            retry: RetryPolicy {
+                max_attempts: 10,
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub async fn append(&self, event: AuditEvent) -> Result<AuditEntry, DatabaseError> {
+        let mut entries = self.commit("", &[], &[event]).await?;
+        entries.pop().ok_or(DatabaseError::NotFound)
+    }
+
+    /// Runs `query`, a list of statements without `BEGIN`/`COMMIT`, and appends `events`
+    /// after it in one transaction, so the change and its audit trail persist together.
+    ///
+    /// The events are chained onto the head read before every attempt; the transaction
+    /// throws [`CONFLICT`] and is retried if another append moved the head meanwhile.
+    /// `query` must not use parameters starting with `audit`.
+    pub async fn commit(
+        &self,
+        query: &str,
+        bindings: &[(String, Value)],
+        events: &[AuditEvent],
+    ) -> Result<Vec<AuditEntry>, DatabaseError> {
+        self.retry
+            .run(|| async {
+                let entries = self.chain(events).await?;
+                let mut query = format!("BEGIN TRANSACTION;\n{query}\n");
+                let mut bindings = bindings.to_vec();
+                if let Some(first) = entries.first() {
+                    bindings.extend([
+                        ("audit".to_string(), Value::from(AUDIT_LOG_TABLE)),
+                        ("audit_head".to_string(), Value::from(first.sequence)),
+                        ("audit_entries".to_string(), serde_json::to_value(&entries)?),
+                    ]);
+                    query.push_str(&format!(
+                        "IF (SELECT VALUE id FROM type::thing($audit, $audit_head)) {{ THROW '{CONFLICT}' }};\n\
+                         FOR $audit_entry IN $audit_entries {{ \
+                         CREATE type::thing($audit, $audit_entry.sequence) CONTENT $audit_entry RETURN NONE; }};\n"
+                    ));
+                }
+                query.push_str("COMMIT TRANSACTION;");
+                execute_once(&self.db, &query, &bindings).await?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    /// Chains `events` onto the current head.
+    async fn chain(&self, events: &[AuditEvent]) -> Result<Vec<AuditEntry>, DatabaseError> {
+        if events.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (sequence, mut previous_hash) = match self.last().await? {
+            Some(last) => (last.sequence + 1, last.hash),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let mut entries = Vec::with_capacity(events.len());
+        for (offset, event) in (0..).zip(events) {
+            let entry = AuditEntry::new(sequence + offset, event.clone(), previous_hash)?;
+            previous_hash = entry.hash.clone();
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    pub async fn last(&self) -> Result<Option<AuditEntry>, DatabaseError> {
+        let rows: Vec<Value> = self
+            .db
+            .query("SELECT * OMIT id FROM type::table($table) ORDER BY sequence DESC LIMIT 1")
+            .bind(("table", AUDIT_LOG_TABLE))
+            .await?
+            .take(0)?;
+        entries(rows).map(|mut entries| entries.pop())
+    }
+
+    /// Entries in chain order, starting at `sequence`.
+    pub async fn list(
+        &self,
+        sequence: u64,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, DatabaseError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT * OMIT id FROM type::table($table) WHERE sequence >= $sequence \
+                 ORDER BY sequence ASC LIMIT $limit",
+            )
+            .bind(("table", AUDIT_LOG_TABLE))
+            .bind(("sequence", sequence))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
+        entries(rows)
+    }
+
+    /// Walks the whole chain, failing with `AuditChainBroken` at the first entry that
+    /// was altered, inserted or removed.
+    pub async fn verify(&self) -> Result<AuditHead, DatabaseError> {
+        let mut head = AuditHead {
+            length: 0,
+            hash: GENESIS_HASH.to_string(),
+        };
+        loop {
+            let batch = self.list(head.length, VERIFY_BATCH_SIZE).await?;
+            let done = batch.len() < VERIFY_BATCH_SIZE;
+            for entry in batch {
+                if entry.sequence != head.length
+                    || entry.previous_hash != head.hash
+                    || entry.hash != entry.compute_hash()?
+                {
+                    return Err(DatabaseError::AuditChainBroken(head.length));
+                }
+                head = AuditHead {
+                    length: entry.sequence + 1,
+                    hash: entry.hash,
+                };
+            }
+            if done {
+                return Ok(head);
+            }
+        }
+    }
+}
+
+fn entries(rows: Vec<Value>) -> Result<Vec<AuditEntry>, DatabaseError> {
+    Ok(rows
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()?)
+}
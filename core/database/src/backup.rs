@@ -0,0 +1,437 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Seek, SeekFrom, Write},
+};
+
+use chrono::{DateTime, Utc};
+use models::{AssetRaw, BalanceRaw, MarketRaw, OrderRaw, TradeRaw, UserRaw};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use crate::{
+    errors::DatabaseError,
+    record::{canonical, hex, Record, SELECT_FIELDS},
+};
+
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Table of `blockchain::EvmNetwork`.
+const EVM_NETWORKS_TABLE: &str = "evm-network";
+/// Tables of the ledger of the `transfer` crate.
+const JOURNAL_ENTRIES_TABLE: &str = "journal_entries";
+pub(crate) const LEDGER_BALANCES_TABLE: &str = "ledger_balances";
+
+/// Tables that can be exported, in an order that imports referenced records first.
+pub const EXPORT_TABLES: [&str; 9] = [
+    UserRaw::TABLE,
+    AssetRaw::TABLE,
+    EVM_NETWORKS_TABLE,
+    MarketRaw::TABLE,
+    BalanceRaw::TABLE,
+    JOURNAL_ENTRIES_TABLE,
+    LEDGER_BALANCES_TABLE,
+    OrderRaw::TABLE,
+    TradeRaw::TABLE,
+];
+
+const BATCH_SIZE: usize = 1000;
+
+/// One line of an export: a header, then the records and a checksum of every table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line {
+    Header {
+        version: u32,
+        tables: Vec<String>,
+        anonymized: bool,
+        created_at: DateTime<Utc>,
+    },
+    Record {
+        table: String,
+        record: Value,
+    },
+    Checksum(TableChecksum),
+}
+
+/// Number of records of a table and the SHA-256 over their JSON lines with sorted keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableChecksum {
+    pub table: String,
+    pub count: u64,
+    pub sha256: String,
+}
+
+struct Checksum {
+    count: u64,
+    context: digest::Context,
+}
+impl Checksum {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            context: digest::Context::new(&digest::SHA256),
+        }
+    }
+
+    fn add(&mut self, record: &Value) -> Result<(), DatabaseError> {
+        self.context.update(&serde_json::to_vec(record)?);
+        self.context.update(b"\n");
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self, table: &str) -> TableChecksum {
+        TableChecksum {
+            table: table.to_string(),
+            count: self.count,
+            sha256: hex(self.context.finish().as_ref()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub tables: Vec<String>,
+    /// Replaces every user id with a random one, consistently across all tables, and
+    /// drops user handles.
+    pub anonymize: bool,
+}
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            tables: EXPORT_TABLES
+                .iter()
+                .map(|table| table.to_string())
+                .collect(),
+            anonymize: false,
+        }
+    }
+}
+
+/// Pseudonymous user ids handed out during one export.
+#[derive(Debug, Default)]
+struct Anonymizer {
+    user_ids: HashMap<String, String>,
+}
+impl Anonymizer {
+    fn apply(&mut self, table: &str, record: &mut Value) {
+        match table {
+            UserRaw::TABLE => {
+                if let Some(record) = record.as_object_mut() {
+                    record.remove("handle");
+                }
+                self.user_id(record.get_mut("id"));
+            }
+            // Ledger accounts of users are named `user:<uuid>`
+            JOURNAL_ENTRIES_TABLE => {
+                if let Some(Value::Array(postings)) = record.get_mut("postings") {
+                    for posting in postings {
+                        self.account(posting.get_mut("account"));
+                    }
+                }
+                if let Some(Value::Array(accounts)) = record.get_mut("accounts") {
+                    accounts
+                        .iter_mut()
+                        .for_each(|account| self.account(Some(account)));
+                }
+            }
+            LEDGER_BALANCES_TABLE => {
+                self.account(record.get_mut("account"));
+                self.account(record.get_mut("id").and_then(|id| id.get_mut(0)));
+            }
+            _ => self.user_id(record.get_mut("user_id")),
+        }
+    }
+
+    fn user_id(&mut self, value: Option<&mut Value>) {
+        if let Some(Value::String(user_id)) = value {
+            *user_id = self.pseudonym(user_id);
+        }
+    }
+
+    fn account(&mut self, value: Option<&mut Value>) {
+        if let Some(Value::String(account)) = value {
+            if let Some(user_id) = account.strip_prefix("user:") {
+                *account = format!("user:{}", self.pseudonym(user_id));
+            }
+        }
+    }
+
+    fn pseudonym(&mut self, user_id: &str) -> String {
+        self.user_ids
+            .entry(user_id.to_string())
+            .or_insert_with(|| Uuid::new_v4().to_string())
+            .clone()
+    }
+}
+
+fn validate(table: &str) -> Result<(), DatabaseError> {
+    if EXPORT_TABLES.contains(&table) {
+        Ok(())
+    } else {
+        Err(DatabaseError::Backup(format!("unsupported table: {table}")))
+    }
+}
+
+fn write_line(writer: &mut impl Write, line: &Line) -> Result<(), DatabaseError> {
+    serde_json::to_writer(&mut *writer, line)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Writes the selected tables as newline-delimited JSON, independent of the SurrealDB version.
+///
+/// Every table is read in batches ordered by record id, seeking past the last record of
+/// the previous batch, so memory use does not grow with the database. The export is not a
+/// snapshot: records written while it runs may or may not be included, so writes should be
+/// stopped for a consistent backup.
+pub async fn export<C: Connection>(
+    db: &Surreal<C>,
+    options: &ExportOptions,
+    mut writer: impl Write,
+) -> Result<Vec<TableChecksum>, DatabaseError> {
+    options
+        .tables
+        .iter()
+        .try_for_each(|table| validate(table))?;
+    write_line(
+        &mut writer,
+        &Line::Header {
+            version: EXPORT_FORMAT_VERSION,
+            tables: options.tables.clone(),
+            anonymized: options.anonymize,
+            created_at: Utc::now(),
+        },
+    )?;
+
+    let mut anonymizer = Anonymizer::default();
+    let mut checksums = Vec::new();
+    for table in &options.tables {
+        let mut checksum = Checksum::new();
+        let mut after = None;
+        loop {
+            let rows = read_batch(db, table, after.as_ref()).await?;
+            let done = rows.len() < BATCH_SIZE;
+            after = rows.last().and_then(|record| record.get("id")).cloned();
+            for mut record in rows {
+                if options.anonymize {
+                    anonymizer.apply(table, &mut record);
+                }
+                let record = canonical(record);
+                checksum.add(&record)?;
+                write_line(
+                    &mut writer,
+                    &Line::Record {
+                        table: table.clone(),
+                        record,
+                    },
+                )?;
+            }
+            if done {
+                break;
+            }
+        }
+        let checksum = checksum.finish(table);
+        write_line(&mut writer, &Line::Checksum(checksum.clone()))?;
+        checksums.push(checksum);
+    }
+    writer.flush()?;
+    Ok(checksums)
+}
+
+/// Reads up to `BATCH_SIZE` records of `table` in id order, following the record id
+/// `after` if given.
+async fn read_batch<C: Connection>(
+    db: &Surreal<C>,
+    table: &str,
+    after: Option<&Value>,
+) -> Result<Vec<Value>, DatabaseError> {
+    let condition = match after {
+        Some(_) => " WHERE id > type::thing($table, $after)",
+        None => "",
+    };
+    let rows = db
+        .query(format!(
+            "SELECT {SELECT_FIELDS} FROM type::table($table){condition} ORDER BY id LIMIT {BATCH_SIZE}"
+        ))
+        .bind(("table", table))
+        .bind(("after", after.cloned().unwrap_or(Value::Null)))
+        .await?
+        .take(0)?;
+    Ok(rows)
+}
+
+/// Verifies an export and creates its records, failing with `AlreadyExists` if any of
+/// them is already present with other content.
+///
+/// The export is read twice: nothing is written unless every checksum matches, then the
+/// records are created in batches, each in its own transaction. The import is therefore
+/// not atomic, but it can be resumed: running it again after a failure skips the records
+/// an earlier run created. The schema is expected to be migrated already.
+pub async fn import<C: Connection, R: BufRead + Seek>(
+    db: &Surreal<C>,
+    mut reader: R,
+) -> Result<Vec<TableChecksum>, DatabaseError> {
+    let checksums = verify(&mut reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut current: Option<String> = None;
+    for line in reader.lines().skip(1) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)? {
+            Line::Record { table, record } => {
+                if current.as_ref().is_some_and(|current| current != &table) {
+                    write_batch(db, current.as_deref().unwrap_or_default(), &mut batch).await?;
+                }
+                batch.push(canonical(record));
+                if batch.len() == BATCH_SIZE {
+                    write_batch(db, &table, &mut batch).await?;
+                }
+                current = Some(table);
+            }
+            _ => {
+                if let Some(table) = current.take() {
+                    write_batch(db, &table, &mut batch).await?;
+                }
+            }
+        }
+    }
+    if let Some(table) = current {
+        write_batch(db, &table, &mut batch).await?;
+    }
+    Ok(checksums)
+}
+
+/// Reads an export to its end, checking its header and the checksum of every table
+/// without keeping any record.
+fn verify(reader: &mut impl BufRead) -> Result<Vec<TableChecksum>, DatabaseError> {
+    let mut lines = reader.lines();
+    let tables = match lines.next().transpose()? {
+        Some(line) => match serde_json::from_str(&line)? {
+            Line::Header {
+                version: EXPORT_FORMAT_VERSION,
+                tables,
+                ..
+            } => tables,
+            Line::Header { version, .. } => {
+                return Err(DatabaseError::Backup(format!(
+                    "unsupported export version: {version}"
+                )))
+            }
+            _ => return Err(DatabaseError::Backup("missing header".to_string())),
+        },
+        None => return Err(DatabaseError::Backup("empty export".to_string())),
+    };
+    tables.iter().try_for_each(|table| validate(table))?;
+
+    let mut running: HashMap<String, Checksum> = HashMap::new();
+    let mut checksums = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)? {
+            Line::Record { table, record } if tables.contains(&table) => {
+                running
+                    .entry(table)
+                    .or_insert_with(Checksum::new)
+                    .add(&canonical(record))?;
+            }
+            Line::Checksum(expected) => {
+                let actual = running
+                    .remove(&expected.table)
+                    .unwrap_or_else(Checksum::new)
+                    .finish(&expected.table);
+                if actual != expected {
+                    return Err(DatabaseError::Backup(format!(
+                        "checksum mismatch for table {}",
+                        expected.table
+                    )));
+                }
+                checksums.push(actual);
+            }
+            Line::Record { table, .. } => {
+                return Err(DatabaseError::Backup(format!(
+                    "record of undeclared table: {table}"
+                )))
+            }
+            Line::Header { .. } => {
+                return Err(DatabaseError::Backup("unexpected header".to_string()))
+            }
+        }
+    }
+    for table in &tables {
+        if !checksums.iter().any(|checksum| &checksum.table == table) {
+            return Err(DatabaseError::Backup(format!(
+                "missing checksum for table {table}"
+            )));
+        }
+    }
+    Ok(checksums)
+}
+
+/// Creates the records of `batch` in `table` in one transaction and empties it.
+///
+/// Records that already exist with the same content were created by an earlier run and
+/// are skipped; a record that exists with other content fails with `AlreadyExists`.
+async fn write_batch<C: Connection>(
+    db: &Surreal<C>,
+    table: &str,
+    batch: &mut Vec<Value>,
+) -> Result<(), DatabaseError> {
+    let ids: Vec<Value> = batch
+        .iter()
+        .map(|record| record.get("id").cloned().unwrap_or(Value::Null))
+        .collect();
+    let existing: Vec<Value> = db
+        .query(format!(
+            "SELECT {SELECT_FIELDS} FROM (SELECT VALUE type::thing($table, $this) FROM $ids)"
+        ))
+        .bind(("table", table))
+        .bind(("ids", ids))
+        .await?
+        .take(0)?;
+    let existing: HashMap<String, Value> = existing
+        .into_iter()
+        .map(|record| {
+            let record = canonical(record);
+            (record["id"].to_string(), record)
+        })
+        .collect();
+
+    let mut rows = Vec::with_capacity(batch.len());
+    for mut record in batch.drain(..) {
+        if let Some(present) = existing.get(&record["id"].to_string()) {
+            if present != &record {
+                return Err(DatabaseError::AlreadyExists);
+            }
+            continue;
+        }
+        let id = record
+            .as_object_mut()
+            .and_then(|object| object.remove("id"))
+            .unwrap_or(Value::Null);
+        rows.push(serde_json::json!({ "id": id, "content": record }));
+    }
+    if rows.is_empty() {
+        return Ok(());
+    }
+    db.query(
+        "BEGIN TRANSACTION; \
+         FOR $row IN $rows { CREATE type::thing($table, $row.id) CONTENT $row.content RETURN NONE; }; \
+         COMMIT TRANSACTION;",
+    )
+    .bind(("table", table))
+    .bind(("rows", rows))
+    .await?
+    .check()?;
+    Ok(())
+}
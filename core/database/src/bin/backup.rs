@@ -0,0 +1,82 @@
+//! Exports tables of the database configured through the environment to newline-delimited
+//! JSON, or imports such an export after applying the embedded migrations.
+//!
+//! ```sh
+//! cargo run -p database --bin backup -- export [--tables users,assets] [--anonymize] <file>
+//! cargo run -p database --bin backup -- import <file>
+//! ```
+//!
+//! Exports include users, assets, EVM networks, markets, balances, the ledger, orders and
+//! trades by default.
+//!
+//! An import that fails midway leaves the records written so far; running it again with the
+//! same file resumes it.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use database::{
+    export, import, DatabaseConfig, DatabaseError, DatabaseHandle, ExportOptions, Migrator,
+};
+
+const USAGE: &str =
+    "usage: backup export [--tables <table,...>] [--anonymize] <file> | backup import <file>";
+
+/// Prints the usage text and exits with status 2.
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(2)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), DatabaseError> {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next() {
+        Some(command) if command == "export" || command == "import" => command,
+        _ => usage(),
+    };
+    let mut options = ExportOptions::default();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tables" if command == "export" => {
+                options.tables = args
+                    .next()
+                    .unwrap_or_else(|| usage())
+                    .split(',')
+                    .map(str::to_string)
+                    .collect()
+            }
+            "--anonymize" if command == "export" => options.anonymize = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else { usage() };
+
+    let database = DatabaseHandle::connect(DatabaseConfig::from_env()?).await?;
+    let checksums = match command.as_str() {
+        "export" => {
+            export(
+                &database.client(),
+                &options,
+                BufWriter::new(File::create(&path)?),
+            )
+            .await?
+        }
+        "import" => {
+            Migrator::embedded().run(&database.client(), false).await?;
+            import(&database.client(), BufReader::new(File::open(&path)?)).await?
+        }
+        _ => usage(),
+    };
+    for checksum in checksums {
+        println!(
+            "{command}ed {} records of {} (sha256 {})",
+            checksum.count, checksum.table, checksum.sha256
+        );
+    }
+    Ok(())
+}
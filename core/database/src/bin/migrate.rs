@@ -0,0 +1,49 @@
+//! Applies SurrealQL migrations to the database configured through the environment.
+//!
+//! ```sh
+//! cargo run -p database --bin migrate -- [--dry-run] [--dir <path>]
+//! ```
+//!
+//! Without `--dir` the migrations embedded in the crate are used.
+
+use database::{DatabaseConfig, DatabaseError, DatabaseHandle, Migrator};
+
+const USAGE: &str = "usage: migrate [--dry-run] [--dir <path>]";
+
+/// Prints the usage text and exits with status 2.
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(2)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), DatabaseError> {
+    let mut dry_run = false;
+    let mut dir = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--dir" => dir = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    let migrator = match dir {
+        Some(dir) => Migrator::from_dir(dir)?,
+        None => Migrator::embedded(),
+    };
+
+    let database = DatabaseHandle::connect(DatabaseConfig::from_env()?).await?;
+    let versions = migrator.run(&database.client(), dry_run).await?;
+    if versions.is_empty() {
+        println!("database is up to date");
+    }
+    for version in versions {
+        println!(
+            "{} migration {version}",
+            if dry_run { "would apply" } else { "applied" }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::errors::DatabaseError;
+
+/// Key of a cached balance.
+pub fn balance_key(user_id: Uuid, asset_id: Uuid) -> String {
+    format!("balance:{user_id}:{asset_id}")
+}
+
+/// Key of a cached user.
+pub fn user_key(user_id: Uuid) -> String {
+    format!("user:{user_id}")
+}
+
+/// Key of a cached ticker of a market.
+pub fn ticker_key(base_asset_id: Uuid, quote_asset_id: Uuid) -> String {
+    format!("ticker:{base_asset_id}:{quote_asset_id}")
+}
+
+/// Byte storage with per-entry expiry behind a [`Cache`].
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DatabaseError>;
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), DatabaseError>;
+    async fn delete(&self, key: &str) -> Result<(), DatabaseError>;
+}
+
+/// Cache store shared by all replicas, reconnecting automatically.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+}
+impl RedisStore {
+    pub async fn connect(url: &str) -> Result<Self, DatabaseError> {
+        let connection = redis::Client::open(url)?.get_connection_manager().await?;
+        Ok(Self { connection })
+    }
+}
+#[async_trait]
+impl CacheStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DatabaseError> {
+        Ok(self.connection.clone().get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), DatabaseError> {
+        let ttl = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+        Ok(self.connection.clone().pset_ex(key, value, ttl).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), DatabaseError> {
+        Ok(self.connection.clone().del(key).await?)
+    }
+}
+
+/// In-process stand-in for [`RedisStore`], local to a single replica.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+}
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+#[async_trait]
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DatabaseError> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((_, expires_at)) if *expires_at <= Instant::now() => {
+                entries.remove(key);
+                Ok(None)
+            }
+            entry => Ok(entry.map(|(value, _)| value.clone())),
+        }
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), DatabaseError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (value, Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), DatabaseError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Typed read-through cache storing values as JSON.
+#[derive(Debug, Clone)]
+pub struct Cache<S: CacheStore> {
+    store: S,
+    ttl: Duration,
+}
+impl<S: CacheStore> Cache<S> {
+    /// Creates a cache whose entries expire after `ttl` unless set with another one.
+    pub fn new(store: S, ttl: Duration) -> Self {
+        Self { store, ttl }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, DatabaseError> {
+        match self.store.get(key).await? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), DatabaseError> {
+        self.set_with_ttl(key, value, self.ttl).await
+    }
+
+    pub async fn set_with_ttl<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<(), DatabaseError> {
+        self.store.set(key, serde_json::to_vec(value)?, ttl).await
+    }
+
+    pub async fn invalidate(&self, key: &str) -> Result<(), DatabaseError> {
+        self.store.delete(key).await
+    }
+
+    /// Returns the cached value or loads, caches and returns it on a miss.
+    pub async fn get_or_load<T, F, Fut>(&self, key: &str, load: F) -> Result<T, DatabaseError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, DatabaseError>>,
+    {
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+        let value = load().await?;
+        self.set(key, &value).await?;
+        Ok(value)
+    }
+}
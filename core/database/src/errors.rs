@@ -0,0 +1,89 @@
+#[derive(Debug, thiserror::Error)]
+pub enum DatabaseError {
+    #[error("surrealdb error")]
+    Surrealdb(Box<surrealdb::Error>),
+
+    #[error("redis error")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("serialization error")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("record not found")]
+    NotFound,
+
+    #[error("record already exists")]
+    AlreadyExists,
+
+    #[error("record was modified concurrently")]
+    Conflict,
+
+    #[error("table {0} is read-only")]
+    ReadOnly(&'static str),
+
+    #[error("record is still referenced by {0}")]
+    Referenced(&'static str),
+
+    #[error("insufficient balance")]
+    InsufficientBalance,
+
+    #[error("audit log chain broken at entry {0}")]
+    AuditChainBroken(u64),
+
+    #[error("invalid cursor")]
+    InvalidCursor,
+
+    #[error("unsupported filter: {0}")]
+    UnsupportedFilter(&'static str),
+
+    #[error("invalid field name: {0}")]
+    InvalidField(String),
+
+    #[error("configuration error: {0}")]
+    Config(String),
+
+    #[error("migration error: {0}")]
+    Migration(String),
+
+    #[error("backup error: {0}")]
+    Backup(String),
+
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+}
+
+impl DatabaseError {
+    /// Restores a serialization error that was captured as a message by a builder.
+    pub(crate) fn serialization(message: String) -> Self {
+        Self::Serialization(<serde_json::Error as serde::ser::Error>::custom(message))
+    }
+
+    /// Whether the operation may succeed when retried, e.g. after a transaction conflict.
+    ///
+    /// A `Conflict` only resolves if the retried operation re-reads the record first.
+    pub fn is_retryable(&self) -> bool {
+        use surrealdb::error::Db;
+        match self {
+            DatabaseError::Conflict => true,
+            DatabaseError::Surrealdb(err) => match err.as_ref() {
+                surrealdb::Error::Db(Db::TxFailure | Db::TxConditionNotMet) => true,
+                surrealdb::Error::Db(Db::Tx(message)) => {
+                    let message = message.to_lowercase();
+                    message.contains("conflict") || message.contains("retry")
+                }
+                _ => false,
+            },
+            DatabaseError::Redis(err) => err.is_connection_dropped() || err.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+impl From<surrealdb::Error> for DatabaseError {
+    fn from(err: surrealdb::Error) -> Self {
+        match err {
+            surrealdb::Error::Db(surrealdb::error::Db::RecordExists { .. }) => Self::AlreadyExists,
+            err => Self::Surrealdb(Box::new(err)),
+        }
+    }
+}
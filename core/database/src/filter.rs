@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::DatabaseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+impl Operator {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Lte => "<=",
+            Operator::Gt => ">",
+            Operator::Gte => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: &'static str,
+    operator: Operator,
+    value: Result<Value, String>,
+}
+
+/// Conditions, ordering and bounds of a listing query.
+///
+/// Values are always sent as bound parameters; field names must be plain identifiers.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    conditions: Vec<Condition>,
+    order_by: Vec<(&'static str, Direction)>,
+    limit: Option<usize>,
+    start: Option<usize>,
+}
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn condition<T: Serialize>(
+        mut self,
+        field: &'static str,
+        operator: Operator,
+        value: T,
+    ) -> Self {
+        self.conditions.push(Condition {
+            field,
+            operator,
+            value: serde_json::to_value(value).map_err(|err| err.to_string()),
+        });
+        self
+    }
+
+    pub fn eq<T: Serialize>(self, field: &'static str, value: T) -> Self {
+        self.condition(field, Operator::Eq, value)
+    }
+
+    pub fn order_by(mut self, field: &'static str, direction: Direction) -> Self {
+        self.order_by.push((field, direction));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn start(mut self, start: usize) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Renders the clauses following `FROM` together with their parameter bindings.
+    pub(crate) fn render(&self) -> Result<(String, Vec<(String, Value)>), DatabaseError> {
+        let (mut clauses, bindings) = self.bound_where_clause("filter")?;
+        for (index, (field, direction)) in self.order_by.iter().enumerate() {
+            validate_field(field)?;
+            clauses.push_str(if index == 0 { " ORDER BY " } else { ", " });
+            clauses.push_str(&format!("{field} {}", direction.as_str()));
+        }
+        if let Some(limit) = self.limit {
+            clauses.push_str(&format!(" LIMIT {limit}"));
+        }
+        if let Some(start) = self.start {
+            clauses.push_str(&format!(" START {start}"));
+        }
+        Ok((clauses, bindings))
+    }
+
+    /// Renders only the conditions, for queries that order and bound the results themselves.
+    pub(crate) fn render_conditions(
+        &self,
+        context: &'static str,
+    ) -> Result<(String, Vec<(String, Value)>), DatabaseError> {
+        self.ensure_unordered(context)?;
+        self.bound_where_clause("filter")
+    }
+
+    /// Renders only the conditions with parameters named after `prefix`, so several
+    /// filters can be bound in one query.
+    pub(crate) fn render_prefixed(
+        &self,
+        prefix: &str,
+        context: &'static str,
+    ) -> Result<(String, Vec<(String, Value)>), DatabaseError> {
+        self.ensure_unordered(context)?;
+        self.bound_where_clause(prefix)
+    }
+
+    /// Renders the conditions with inlined values for a `LIVE SELECT`, which does not
+    /// keep the parameters of the query that registered it.
+    pub(crate) fn render_live(&self) -> Result<String, DatabaseError> {
+        self.ensure_unordered("live queries cannot be ordered or bounded")?;
+        self.where_clause(|_, value| literal(&value))
+    }
+
+    fn ensure_unordered(&self, context: &'static str) -> Result<(), DatabaseError> {
+        if !self.order_by.is_empty() || self.limit.is_some() || self.start.is_some() {
+            return Err(DatabaseError::UnsupportedFilter(context));
+        }
+        Ok(())
+    }
+
+    fn bound_where_clause(
+        &self,
+        prefix: &str,
+    ) -> Result<(String, Vec<(String, Value)>), DatabaseError> {
+        let mut bindings = Vec::with_capacity(self.conditions.len());
+        let clauses = self.where_clause(|index, value| {
+            let param = format!("{prefix}_{index}");
+            bindings.push((param.clone(), value));
+            format!("${param}")
+        })?;
+        Ok((clauses, bindings))
+    }
+
+    fn where_clause(
+        &self,
+        mut value: impl FnMut(usize, Value) -> String,
+    ) -> Result<String, DatabaseError> {
+        let mut clauses = String::new();
+        for (index, condition) in self.conditions.iter().enumerate() {
+            validate_field(condition.field)?;
+            let rendered = value(
+                index,
+                condition
+                    .value
+                    .clone()
+                    .map_err(DatabaseError::serialization)?,
+            );
+            clauses.push_str(if index == 0 { " WHERE " } else { " AND " });
+            clauses.push_str(&format!(
+                "{} {} {rendered}",
+                condition.field,
+                condition.operator.as_str()
+            ));
+        }
+        Ok(clauses)
+    }
+}
+
+/// SurrealQL literal of a JSON value. Strings are cast explicitly, since the parser
+/// would otherwise turn uuid and datetime shaped strings into those types.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(_) => format!("<string> {value}"),
+        _ => value.to_string(),
+    }
+}
+
+pub(crate) fn validate_field(field: &str) -> Result<(), DatabaseError> {
+    let mut chars = field.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(DatabaseError::InvalidField(field.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use surrealdb::{
+    engine::any::{self, Any},
+    opt::auth::Root,
+    Surreal,
+};
+use tokio::task::JoinHandle;
+
+use crate::{audit::AuditLog, errors::DatabaseError, record::Record, repository::Repository};
+
+/// Where and how to connect to SurrealDB.
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub namespace: String,
+    pub database: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub connect_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub health_check_interval: Duration,
+}
+impl DatabaseConfig {
+    pub fn new(
+        url: impl Into<String>,
+        namespace: impl Into<String>,
+        database: impl Into<String>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            namespace: namespace.into(),
+            database: database.into(),
+            username: None,
+            password: None,
+            connect_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            health_check_interval: Duration::from_secs(15),
+        }
+    }
+
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Reads the `KSOX_SERVER_SURREALDB_*` variables; credentials are optional.
+    pub fn from_env() -> Result<Self, DatabaseError> {
+        let config = Self::new(
+            env("KSOX_SERVER_SURREALDB_URL")?,
+            env("KSOX_SERVER_SURREALDB_NAMESPACE")?,
+            env("KSOX_SERVER_SURREALDB_DATABASE")?,
+        );
+        Ok(
+            match (
+                env("KSOX_SERVER_SURREALDB_USER"),
+                env("KSOX_SERVER_SURREALDB_PASS"),
+            ) {
+                (Ok(username), Ok(password)) => config.with_credentials(username, password),
+                _ => config,
+            },
+        )
+    }
+}
+
+fn env(name: &str) -> Result<String, DatabaseError> {
+    std::env::var(name).map_err(|_| DatabaseError::Config(format!("{name} must be set")))
+}
+
+/// Shared handle to SurrealDB that is cheap to clone.
+///
+/// Not a pool: a SurrealDB client multiplexes all requests over one connection, so the
+/// handle holds a single client and replaces it for every clone when it becomes unhealthy.
+#[derive(Debug, Clone)]
+pub struct DatabaseHandle {
+    config: Arc<DatabaseConfig>,
+    client: Arc<RwLock<Surreal<Any>>>,
+}
+impl DatabaseHandle {
+    pub async fn connect(config: DatabaseConfig) -> Result<Self, DatabaseError> {
+        let client = open(&config).await?;
+        Ok(Self {
+            config: Arc::new(config),
+            client: Arc::new(RwLock::new(client)),
+        })
+    }
+
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// The current client, signed in with the namespace and database selected.
+    pub fn client(&self) -> Surreal<Any> {
+        self.client.read().unwrap().clone()
+    }
+
+    pub fn repository<M: Record>(&self) -> Repository<Any, M> {
+        Repository::new(self.client())
+    }
+
+    pub fn audit_log(&self) -> AuditLog<Any> {
+        AuditLog::new(self.client())
+    }
+
+    pub async fn health_check(&self) -> Result<(), DatabaseError> {
+        Ok(self.client().health().await?)
+    }
+
+    /// Opens a new connection and swaps it in once it is ready.
+    pub async fn reconnect(&self) -> Result<(), DatabaseError> {
+        let client = open(&self.config).await?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+
+    /// Reconnects if the health check fails.
+    pub async fn ensure_healthy(&self) -> Result<(), DatabaseError> {
+        if self.health_check().await.is_ok() {
+            return Ok(());
+        }
+        self.reconnect().await
+    }
+
+    /// Checks the connection every `health_check_interval` for as long as the task runs.
+    pub fn spawn_health_check(&self) -> JoinHandle<()> {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(handle.config.health_check_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(err) = handle.ensure_healthy().await {
+                    tracing::error!("surrealdb is unreachable: {:?}", err);
+                }
+            }
+        })
+    }
+}
+
+/// Connects with exponential backoff between failed attempts.
+async fn open(config: &DatabaseConfig) -> Result<Surreal<Any>, DatabaseError> {
+    let mut backoff = config.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        match open_once(config).await {
+            Err(err) if attempt < config.connect_attempts => {
+                tracing::warn!(
+                    "connecting to surrealdb failed, attempt {}: {:?}",
+                    attempt,
+                    err
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn open_once(config: &DatabaseConfig) -> Result<Surreal<Any>, DatabaseError> {
+    let client = any::connect(config.url.as_str()).await?;
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        client.signin(Root { username, password }).await?;
+    }
+    client
+        .use_ns(config.namespace.as_str())
+        .use_db(config.database.as_str())
+        .await?;
+    client.health().await?;
+    Ok(client)
+}
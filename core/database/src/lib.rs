@@ -1,14 +1,46 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
-}
+mod audit;
+mod backup;
+mod cache;
+mod errors;
+mod filter;
+mod handle;
+mod live;
+mod migrations;
+mod outbox;
+mod pagination;
+mod pubsub;
+mod record;
+mod registry;
+mod repository;
+mod transaction;
 
 #[cfg(test)]
-mod tests {
-    use super::*;
+mod tests;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use audit::{
+    Actor, AuditCategory, AuditEntry, AuditEvent, AuditHead, AuditLog, AUDIT_LOG_TABLE,
+    GENESIS_HASH,
+};
+pub use backup::{
+    export, import, ExportOptions, TableChecksum, EXPORT_FORMAT_VERSION, EXPORT_TABLES,
+};
+pub use cache::{balance_key, ticker_key, user_key, Cache, CacheStore, MemoryStore, RedisStore};
+pub use errors::DatabaseError;
+pub use filter::{Direction, Filter, Operator};
+pub use handle::{DatabaseConfig, DatabaseHandle};
+pub use live::Change;
+pub use migrations::{Migration, Migrator, MIGRATIONS_TABLE};
+pub use outbox::{OutboxMessage, OutboxRelay, OUTBOX_TABLE, TRADES_CHANNEL};
+pub use pagination::{
+    CursorSigner, Page, PageRequest, Paginated, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+pub use pubsub::{Broker, EventBus, MemoryBroker, RedisBroker};
+pub use record::{from_rows, to_content, Record};
+pub use registry::{Registry, RegistryChange, ASSETS_KEY, MARKETS_KEY};
+pub use repository::{
+    AssetRepository, BalanceRepository, MarketRepository, OrderRepository, Repository,
+    TradeRepository, UserRepository,
+};
+pub use transaction::{
+    RetryPolicy, UnitOfWork, CONFLICT, INSUFFICIENT_BALANCE, NOT_FOUND, REFERENCED,
+};
@@ -0,0 +1,120 @@
+use std::{future::Future, time::Duration};
+
+use futures::{stream, Stream, StreamExt};
+use serde_json::Value;
+use surrealdb::{method::QueryStream, sql, Action, Connection, Notification, Surreal};
+
+use crate::{errors::DatabaseError, record::Record};
+
+const RESUBSCRIBE_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(5);
+
+/// A change to a record matched by a live query.
+///
+/// Deletions carry the record as it was before it was deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<M> {
+    Created(M),
+    Updated(M),
+    Deleted(M),
+}
+impl<M> Change<M> {
+    pub fn record(&self) -> &M {
+        match self {
+            Change::Created(record) | Change::Updated(record) | Change::Deleted(record) => record,
+        }
+    }
+
+    pub fn into_record(self) -> M {
+        match self {
+            Change::Created(record) | Change::Updated(record) | Change::Deleted(record) => record,
+        }
+    }
+}
+
+/// Registers `query` and streams its notifications as typed changes.
+///
+/// The first subscription is made eagerly so an invalid query fails here. Whenever the
+/// notification stream ends, e.g. because the connection dropped, the query is registered
+/// again; changes made while unsubscribed are not replayed.
+pub(crate) async fn watch<C: Connection, M: Record>(
+    db: Surreal<C>,
+    query: String,
+) -> Result<impl Stream<Item = Result<Change<M>, DatabaseError>>, DatabaseError> {
+    let notifications = subscribe(&db, &query).await?;
+    let notifications = resubscribing(notifications, move || {
+        let db = db.clone();
+        let query = query.clone();
+        async move { subscribe(&db, &query).await }
+    });
+    Ok(notifications.filter_map(|notification| async move { change::<M>(notification) }))
+}
+
+async fn subscribe<C: Connection>(
+    db: &Surreal<C>,
+    query: &str,
+) -> Result<QueryStream<sql::Value>, DatabaseError> {
+    Ok(db.query(query).await?.stream::<sql::Value>(0)?)
+}
+
+/// Forwards the items of `current` and of every stream that replaces it once it ends,
+/// retrying failed subscriptions with exponential backoff. Never ends by itself.
+pub(crate) fn resubscribing<S, F, Fut>(current: S, subscribe: F) -> impl Stream<Item = S::Item>
+where
+    S: Stream + Unpin,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<S, DatabaseError>>,
+{
+    stream::unfold(
+        (Some(current), subscribe),
+        |(mut current, mut subscribe)| async move {
+            let mut backoff = RESUBSCRIBE_BACKOFF;
+            loop {
+                if let Some(stream) = current.as_mut() {
+                    match stream.next().await {
+                        Some(item) => return Some((item, (current, subscribe))),
+                        None => current = None,
+                    }
+                }
+                match subscribe().await {
+                    Ok(stream) => current = Some(stream),
+                    Err(_) => {
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_RESUBSCRIBE_BACKOFF);
+                    }
+                }
+            }
+        },
+    )
+}
+
+fn change<M: Record>(
+    notification: Notification<sql::Value>,
+) -> Option<Result<Change<M>, DatabaseError>> {
+    let mut data = notification.data.into_json();
+    // Deletions are sent without the projection, so the id is still a `table:⟨uuid⟩` thing.
+    if let Some(Value::String(id)) = data.get_mut("id") {
+        if let Some(key) = record_key(M::TABLE, id) {
+            *id = key.to_string();
+        }
+    }
+    let record = match serde_json::from_value(data) {
+        Ok(record) => record,
+        Err(err) => return Some(Err(err.into())),
+    };
+    Some(Ok(match notification.action {
+        Action::Create => Change::Created(record),
+        Action::Update => Change::Updated(record),
+        Action::Delete => Change::Deleted(record),
+        _ => return None,
+    }))
+}
+
+fn record_key<'a>(table: &str, thing: &'a str) -> Option<&'a str> {
+    let key = thing.strip_prefix(table)?.strip_prefix(':')?;
+    Some(
+        key.strip_prefix('⟨')
+            .and_then(|key| key.strip_suffix('⟩'))
+            .unwrap_or(key),
+    )
+}
@@ -0,0 +1,223 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+use surrealdb::{Connection, Surreal};
+
+use crate::errors::DatabaseError;
+
+/// Bookkeeping table with one record per applied migration version.
+pub const MIGRATIONS_TABLE: &str = "migrations";
+
+const EMBEDDED: &[(&str, &str)] = &[
+    (
+        "V0001__create_tables.surql",
+        include_str!("../migrations/V0001__create_tables.surql"),
+    ),
+    (
+        "V0002__add_versions.surql",
+        include_str!("../migrations/V0002__add_versions.surql"),
+    ),
+    (
+        "V0003__add_created_at.surql",
+        include_str!("../migrations/V0003__add_created_at.surql"),
+    ),
+    (
+        "V0004__create_audit_log.surql",
+        include_str!("../migrations/V0004__create_audit_log.surql"),
+    ),
+    (
+        "V0005__create_outbox.surql",
+        include_str!("../migrations/V0005__create_outbox.surql"),
+    ),
+    (
+        "V0006__create_markets.surql",
+        include_str!("../migrations/V0006__create_markets.surql"),
+    ),
+    (
+        "V0007__create_ledger.surql",
+        include_str!("../migrations/V0007__create_ledger.surql"),
+    ),
+    (
+        "V0008__add_user_handles.surql",
+        include_str!("../migrations/V0008__add_user_handles.surql"),
+    ),
+    (
+        "V0009__create_withdrawals.surql",
+        include_str!("../migrations/V0009__create_withdrawals.surql"),
+    ),
+    (
+        "V0010__create_deposits.surql",
+        include_str!("../migrations/V0010__create_deposits.surql"),
+    ),
+    (
+        "V0011__create_withdrawal_limits.surql",
+        include_str!("../migrations/V0011__create_withdrawal_limits.surql"),
+    ),
+    (
+        "V0012__create_withdrawal_approvals.surql",
+        include_str!("../migrations/V0012__create_withdrawal_approvals.surql"),
+    ),
+    (
+        "V0013__create_reconciliation_reports.surql",
+        include_str!("../migrations/V0013__create_reconciliation_reports.surql"),
+    ),
+    (
+        "V0014__index_journal_accounts.surql",
+        include_str!("../migrations/V0014__index_journal_accounts.surql"),
+    ),
+    (
+        "V0015__add_stalled_withdrawals.surql",
+        include_str!("../migrations/V0015__add_stalled_withdrawals.surql"),
+    ),
+    (
+        "V0016__add_security_event_addresses.surql",
+        include_str!("../migrations/V0016__add_security_event_addresses.surql"),
+    ),
+    (
+        "V0017__index_trades_by_market.surql",
+        include_str!("../migrations/V0017__index_trades_by_market.surql"),
+    ),
+    (
+        "V0018__scale_reconciliation_holdings.surql",
+        include_str!("../migrations/V0018__scale_reconciliation_holdings.surql"),
+    ),
+];
+
+/// A SurrealQL script identified by a file name of the form `V<version>__<name>.surql`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u32,
+    pub name: String,
+    pub sql: String,
+}
+impl Migration {
+    pub fn from_file(file_name: &str, sql: String) -> Result<Self, DatabaseError> {
+        let invalid =
+            || DatabaseError::Migration(format!("invalid migration file name: {file_name}"));
+        let (version, name) = file_name
+            .strip_suffix(".surql")
+            .and_then(|stem| stem.strip_prefix('V'))
+            .and_then(|stem| stem.split_once("__"))
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            version: version.parse().map_err(|_| invalid())?,
+            name: name.to_string(),
+            sql,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AppliedMigration {
+    version: u32,
+}
+
+/// Applies migrations in version order, each one together with its bookkeeping
+/// record in a single transaction.
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+}
+impl Migrator {
+    pub fn new(mut migrations: Vec<Migration>) -> Result<Self, DatabaseError> {
+        migrations.sort_by_key(|migration| migration.version);
+        if let Some(pair) = migrations
+            .windows(2)
+            .find(|pair| pair[0].version == pair[1].version)
+        {
+            return Err(DatabaseError::Migration(format!(
+                "duplicate migration version: {}",
+                pair[0].version
+            )));
+        }
+        Ok(Self { migrations })
+    }
+
+    /// Migrations shipped with this crate in `core/database/migrations`.
+    pub fn embedded() -> Self {
+        let migrations = EMBEDDED
+            .iter()
+            .map(|(file_name, sql)| Migration::from_file(file_name, sql.to_string()))
+            .collect::<Result<_, _>>()
+            .expect("embedded migrations must be valid");
+        Self::new(migrations).expect("embedded migrations must be valid")
+    }
+
+    /// Loads every `.surql` file of a directory.
+    pub fn from_dir(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let mut migrations = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if file_name.ends_with(".surql") {
+                migrations.push(Migration::from_file(file_name, fs::read_to_string(&path)?)?);
+            }
+        }
+        Self::new(migrations)
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    pub async fn applied<C: Connection>(&self, db: &Surreal<C>) -> Result<Vec<u32>, DatabaseError> {
+        let applied: Vec<AppliedMigration> = db
+            .query("SELECT version FROM type::table($table) ORDER BY version")
+            .bind(("table", MIGRATIONS_TABLE))
+            .await?
+            .take(0)?;
+        Ok(applied
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect())
+    }
+
+    pub async fn pending<C: Connection>(
+        &self,
+        db: &Surreal<C>,
+    ) -> Result<Vec<&Migration>, DatabaseError> {
+        let applied = self.applied(db).await?;
+        Ok(self
+            .migrations
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .collect())
+    }
+
+    /// Applies all pending migrations and returns their versions.
+    ///
+    /// With `dry_run` nothing is executed and the versions that would be applied are returned.
+    pub async fn run<C: Connection>(
+        &self,
+        db: &Surreal<C>,
+        dry_run: bool,
+    ) -> Result<Vec<u32>, DatabaseError> {
+        let pending = self.pending(db).await?;
+        let versions = pending.iter().map(|migration| migration.version).collect();
+        if dry_run {
+            return Ok(versions);
+        }
+        for migration in pending {
+            db.query("BEGIN TRANSACTION")
+                .query(&migration.sql)
+                .query(
+                    "CREATE type::thing($table, $version) SET version = $version, name = $name, applied_at = time::now()",
+                )
+                .query("COMMIT TRANSACTION")
+                .bind(("table", MIGRATIONS_TABLE))
+                .bind(("version", migration.version))
+                .bind(("name", migration.name.as_str()))
+                .await?
+                .check()
+                .map_err(|err| {
+                    DatabaseError::Migration(format!(
+                        "migration {} failed: {err}",
+                        migration.version
+                    ))
+                })?;
+        }
+        Ok(versions)
+    }
+}
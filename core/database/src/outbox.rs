@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use surrealdb::{Connection, Surreal};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{errors::DatabaseError, pubsub::Broker};
+
+pub const OUTBOX_TABLE: &str = "outbox";
+
+/// Channel that settled trades are published on.
+pub const TRADES_CHANNEL: &str = "trades";
+
+/// An event committed together with the domain changes it describes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OutboxMessage {
+    pub id: Uuid,
+    pub channel: String,
+    pub payload: Value,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// Publishes outbox messages to a [`Broker`] in the order they were committed.
+///
+/// Delivery is at-least-once: a message is marked delivered only after it has been
+/// published, so a crash in between publishes it again and consumers must tolerate
+/// duplicates. A message that fails to publish holds back the ones after it.
+#[derive(Debug, Clone)]
+pub struct OutboxRelay<C: Connection, B: Broker> {
+    db: Surreal<C>,
+    broker: B,
+    batch_size: usize,
+    poll_interval: Duration,
+}
+impl<C: Connection, B: Broker + 'static> OutboxRelay<C, B> {
+    pub fn new(db: Surreal<C>, broker: B) -> Self {
+        Self {
+            db,
+            broker,
+            batch_size: 100,
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Oldest messages that have not been delivered yet.
+    pub async fn pending(&self, limit: usize) -> Result<Vec<OutboxMessage>, DatabaseError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT *, meta::id(id) AS id FROM type::table($table) \
+                 WHERE delivered_at = NONE ORDER BY created_at ASC LIMIT $limit",
+            )
+            .bind(("table", OUTBOX_TABLE))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
+        Ok(rows
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Publishes one batch of pending messages and returns how many were delivered.
+    pub async fn relay_once(&self) -> Result<usize, DatabaseError> {
+        let messages = self.pending(self.batch_size).await?;
+        for (delivered, message) in messages.iter().enumerate() {
+            let published = match serde_json::to_vec(&message.payload) {
+                Ok(payload) => self.broker.publish(&message.channel, payload).await,
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = published {
+                self.update(&message.id, "attempts += 1").await?;
+                tracing::warn!(
+                    "publishing outbox message {} failed, attempt {}: {:?}",
+                    message.id,
+                    message.attempts + 1,
+                    err
+                );
+                return Ok(delivered);
+            }
+            self.update(&message.id, "delivered_at = time::now()")
+                .await?;
+        }
+        Ok(messages.len())
+    }
+
+    async fn update(&self, id: &Uuid, set: &str) -> Result<(), DatabaseError> {
+        self.db
+            .query(format!(
+                "UPDATE type::thing($table, $id) SET {set} RETURN NONE"
+            ))
+            .bind(("table", OUTBOX_TABLE))
+            .bind(("id", id.to_string()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Removes messages delivered before `before`.
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<(), DatabaseError> {
+        self.db
+            .query(
+                "DELETE type::table($table) WHERE delivered_at != NONE \
+                 AND delivered_at < <datetime> $before",
+            )
+            .bind(("table", OUTBOX_TABLE))
+            .bind(("before", before))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Relays messages for as long as the task runs, draining full batches without
+    /// waiting for the next poll.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                loop {
+                    match self.relay_once().await {
+                        Ok(delivered) if delivered == self.batch_size => continue,
+                        Ok(_) => break,
+                        Err(err) => {
+                            tracing::error!("relaying the outbox failed: {:?}", err);
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
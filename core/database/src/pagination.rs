@@ -0,0 +1,147 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use models::{OrderRaw, TradeRaw};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{errors::DatabaseError, filter::Direction, record::Record};
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Records listed page by page in a stable order of creation time, then id.
+pub trait Paginated: Record {
+    fn created_at(&self) -> DateTime<Utc>;
+}
+
+impl Paginated for OrderRaw {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl Paginated for TradeRaw {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+/// Which page to fetch, usually taken from the query string of a list endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PageRequest {
+    /// Token of a previous page; its order takes precedence over `order`.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_size")]
+    pub limit: usize,
+    #[serde(default = "default_order")]
+    pub order: Direction,
+}
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            cursor: None,
+            limit: DEFAULT_PAGE_SIZE,
+            order: default_order(),
+        }
+    }
+}
+impl PageRequest {
+    pub fn first(limit: usize, order: Direction) -> Self {
+        Self {
+            cursor: None,
+            limit,
+            order,
+        }
+    }
+
+    pub fn at(cursor: impl Into<String>, limit: usize) -> Self {
+        Self {
+            cursor: Some(cursor.into()),
+            ..Self::first(limit, default_order())
+        }
+    }
+}
+
+fn default_page_size() -> usize {
+    DEFAULT_PAGE_SIZE
+}
+
+fn default_order() -> Direction {
+    Direction::Desc
+}
+
+/// A page of records with the cursors of its neighbours, if there are any.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Page<M> {
+    pub items: Vec<M>,
+    pub next: Option<String>,
+    pub previous: Option<String>,
+}
+
+/// Position of a page boundary, handed out to clients only as a signed token.
+///
+/// It is only valid for the table and filter of the page it was taken from, so a client
+/// cannot reuse it to seek through records another filter would have hidden.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Cursor {
+    #[serde(rename = "t")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "i")]
+    pub id: Uuid,
+    #[serde(rename = "o")]
+    pub order: Direction,
+    /// Whether the page lies before the position instead of after it.
+    #[serde(rename = "b")]
+    pub backward: bool,
+    /// Digest of the table and the conditions of the filter.
+    #[serde(rename = "f")]
+    pub scope: String,
+}
+impl Cursor {
+    pub fn at<M: Paginated>(record: &M, order: Direction, backward: bool, scope: &str) -> Self {
+        Self {
+            created_at: record.created_at(),
+            id: record.id(),
+            order,
+            backward,
+            scope: scope.to_string(),
+        }
+    }
+}
+
+/// Signs cursors so clients cannot forge positions.
+#[derive(Debug, Clone)]
+pub struct CursorSigner {
+    key: hmac::Key,
+}
+impl CursorSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+        }
+    }
+
+    pub(crate) fn sign(&self, cursor: &Cursor) -> Result<String, DatabaseError> {
+        let payload = serde_json::to_vec(cursor)?;
+        let tag = hmac::sign(&self.key, &payload);
+        Ok(format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(tag.as_ref())
+        ))
+    }
+
+    pub(crate) fn verify(&self, token: &str) -> Result<Cursor, DatabaseError> {
+        let (payload, tag) = token.split_once('.').ok_or(DatabaseError::InvalidCursor)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| DatabaseError::InvalidCursor)?;
+        let tag = URL_SAFE_NO_PAD
+            .decode(tag)
+            .map_err(|_| DatabaseError::InvalidCursor)?;
+        hmac::verify(&self.key, &payload, &tag).map_err(|_| DatabaseError::InvalidCursor)?;
+        serde_json::from_slice(&payload).map_err(|_| DatabaseError::InvalidCursor)
+    }
+}
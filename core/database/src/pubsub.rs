@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::{errors::DatabaseError, live::resubscribing};
+
+const MEMORY_CHANNEL_CAPACITY: usize = 1024;
+
+/// Transport of an [`EventBus`].
+#[async_trait]
+pub trait Broker: Send + Sync {
+    async fn publish(&self, channel: &str, payload: Vec<u8>) -> Result<(), DatabaseError>;
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError>;
+}
+
+/// Lets an [`EventBus`] and an [`OutboxRelay`](crate::OutboxRelay) share one broker.
+#[async_trait]
+impl<B: Broker + ?Sized> Broker for Arc<B> {
+    async fn publish(&self, channel: &str, payload: Vec<u8>) -> Result<(), DatabaseError> {
+        self.as_ref().publish(channel, payload).await
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError> {
+        self.as_ref().subscribe(channel).await
+    }
+}
+
+/// Broker fanning messages out to every replica subscribed to a Redis channel.
+#[derive(Clone)]
+pub struct RedisBroker {
+    client: redis::Client,
+    connection: ConnectionManager,
+}
+impl RedisBroker {
+    pub async fn connect(url: &str) -> Result<Self, DatabaseError> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_connection_manager().await?;
+        Ok(Self { client, connection })
+    }
+}
+
+async fn redis_subscribe(
+    client: &redis::Client,
+    channel: &str,
+) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    Ok(pubsub
+        .into_on_message()
+        .map(|message| message.get_payload_bytes().to_vec())
+        .boxed())
+}
+
+#[async_trait]
+impl Broker for RedisBroker {
+    async fn publish(&self, channel: &str, payload: Vec<u8>) -> Result<(), DatabaseError> {
+        Ok(self.connection.clone().publish(channel, payload).await?)
+    }
+
+    /// Subscribes on a dedicated connection, which is reopened whenever it drops.
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError> {
+        let messages = redis_subscribe(&self.client, channel).await?;
+        let (client, channel) = (self.client.clone(), channel.to_string());
+        Ok(resubscribing(messages, move || {
+            let (client, channel) = (client.clone(), channel.clone());
+            async move { redis_subscribe(&client, &channel).await }
+        })
+        .boxed())
+    }
+}
+
+/// In-process stand-in for [`RedisBroker`], delivering only within this replica.
+///
+/// Subscribers that fall too far behind skip the messages they missed.
+#[derive(Debug, Default)]
+pub struct MemoryBroker {
+    channels: Mutex<HashMap<String, broadcast::Sender<Vec<u8>>>>,
+}
+impl MemoryBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sender(&self, channel: &str) -> broadcast::Sender<Vec<u8>> {
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(MEMORY_CHANNEL_CAPACITY).0)
+            .clone()
+    }
+}
+#[async_trait]
+impl Broker for MemoryBroker {
+    async fn publish(&self, channel: &str, payload: Vec<u8>) -> Result<(), DatabaseError> {
+        // Publishing without subscribers is not an error, just like in Redis.
+        let _ = self.sender(channel).send(payload);
+        Ok(())
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError> {
+        Ok(BroadcastStream::new(self.sender(channel).subscribe())
+            .filter_map(|message| async move { message.ok() })
+            .boxed())
+    }
+}
+
+/// Typed events shared between `api` replicas, encoded as JSON.
+#[derive(Debug, Clone)]
+pub struct EventBus<B: Broker> {
+    broker: B,
+}
+impl<B: Broker> EventBus<B> {
+    pub fn new(broker: B) -> Self {
+        Self { broker }
+    }
+
+    pub async fn publish<T: Serialize>(
+        &self,
+        channel: &str,
+        event: &T,
+    ) -> Result<(), DatabaseError> {
+        self.broker
+            .publish(channel, serde_json::to_vec(event)?)
+            .await
+    }
+
+    pub async fn subscribe<T: DeserializeOwned>(
+        &self,
+        channel: &str,
+    ) -> Result<impl Stream<Item = Result<T, DatabaseError>>, DatabaseError> {
+        Ok(self
+            .broker
+            .subscribe(channel)
+            .await?
+            .map(|payload| Ok(serde_json::from_slice(&payload)?)))
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use models::{AssetRaw, BalanceRaw, MarketRaw, OrderRaw, TradeRaw, UserRaw};
+use ring::digest;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::errors::DatabaseError;
+
+/// A model persisted in its own SurrealDB table under the record id `table:⟨uuid⟩`.
+pub trait Record: Serialize + DeserializeOwned + Send + Sync {
+    const TABLE: &'static str;
+    fn id(&self) -> Uuid;
+
+    /// Whether [`Repository`](crate::Repository) and [`UnitOfWork`](crate::UnitOfWork)
+    /// must refuse to write the table because something else maintains it.
+    const READ_ONLY: bool = false;
+
+    /// Version the record was read at, for records updated under optimistic concurrency.
+    ///
+    /// Such records keep the version in a `version` field that every persisted update
+    /// increments; an update of a record read at an older version fails with `Conflict`.
+    fn version(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Record for UserRaw {
+    const TABLE: &'static str = "users";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl Record for AssetRaw {
+    const TABLE: &'static str = "assets";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl Record for MarketRaw {
+    const TABLE: &'static str = "markets";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Balances are a projection of the ledger in the `transfer` crate, which alone writes
+/// them, so they stay in step with its journal.
+impl Record for BalanceRaw {
+    const TABLE: &'static str = "balances";
+    const READ_ONLY: bool = true;
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
+
+impl Record for OrderRaw {
+    const TABLE: &'static str = "orders";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
+
+impl Record for TradeRaw {
+    const TABLE: &'static str = "trades";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Projection that returns the record key in place of the `table:⟨uuid⟩` thing.
+pub(crate) const SELECT_FIELDS: &str = "*, meta::id(id) AS id";
+
+/// Serializes a record into `CONTENT` for SurrealDB.
+///
+/// Goes through JSON so uuids are stored as strings, and drops `id` which SurrealDB
+/// takes from the record id instead.
+pub fn to_content<M: Record>(record: &M) -> Result<Value, DatabaseError> {
+    let mut content = serde_json::to_value(record)?;
+    if let Some(object) = content.as_object_mut() {
+        object.remove("id");
+    }
+    Ok(content)
+}
+
+/// Deserializes the rows of a query result.
+pub fn from_rows<T: DeserializeOwned>(rows: Vec<Value>) -> Result<Vec<T>, DatabaseError> {
+    Ok(rows
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()?)
+}
+
+/// Orders object keys so a hash does not depend on how a value was stored.
+pub(crate) fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, canonical(value)))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical).collect()),
+        value => value,
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, bytes).as_ref())
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use futures::{stream, Stream, StreamExt};
+use models::{AssetRaw, MarketRaw};
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use crate::{
+    audit::{Actor, AuditCategory, AuditEvent},
+    backup::LEDGER_BALANCES_TABLE,
+    cache::{Cache, CacheStore},
+    errors::DatabaseError,
+    filter::{Direction, Filter, Operator},
+    live::Change,
+    record::Record,
+    repository::{AssetRepository, MarketRepository},
+    transaction::{RetryPolicy, UnitOfWork},
+};
+
+/// Cache key of the list of all assets.
+pub const ASSETS_KEY: &str = "registry:assets";
+
+/// Cache key of the list of all markets.
+pub const MARKETS_KEY: &str = "registry:markets";
+
+/// Tables of the `transfer` crate that refer to assets while their records are open.
+const WITHDRAWALS_TABLE: &str = "withdrawals";
+const CLOSED_WITHDRAWAL_STATUSES: [&str; 3] = ["confirmed", "failed", "cancelled"];
+const DEPOSITS_TABLE: &str = "deposits";
+const PENDING_DEPOSIT_STATUS: &str = "pending";
+
+/// A listing that was added, changed or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryChange {
+    Asset(Change<AssetRaw>),
+    Market(Change<MarketRaw>),
+}
+
+/// Listed assets and markets, read through a cache and changed by admins.
+///
+/// Every change is committed together with its entry in the audit log. Services that
+/// keep listings in memory follow [`Registry::changes`] instead of polling.
+pub struct Registry<C: Connection, S: CacheStore> {
+    assets: AssetRepository<C>,
+    markets: MarketRepository<C>,
+    retry: RetryPolicy,
+    cache: Arc<Cache<S>>,
+}
+impl<C: Connection, S: CacheStore> Clone for Registry<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            assets: self.assets.clone(),
+            markets: self.markets.clone(),
+            retry: self.retry.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+impl<C: Connection, S: CacheStore + 'static> Registry<C, S> {
+    pub fn new(db: Surreal<C>, cache: Cache<S>) -> Self {
+        Self {
+            assets: AssetRepository::new(db.clone()),
+            markets: MarketRepository::new(db),
+            // Admin changes contend with every other audited write for the head of the log
+            retry: RetryPolicy {
+                max_attempts: 10,
+                ..RetryPolicy::default()
+            },
+            cache: Arc::new(cache),
+        }
+    }
+
+    pub async fn assets(&self) -> Result<Vec<AssetRaw>, DatabaseError> {
+        self.cache
+            .get_or_load(ASSETS_KEY, || async {
+                self.assets
+                    .list(&Filter::new().order_by("symbol", Direction::Asc))
+                    .await
+            })
+            .await
+    }
+
+    pub async fn asset(&self, id: Uuid) -> Result<Option<AssetRaw>, DatabaseError> {
+        Ok(self
+            .assets()
+            .await?
+            .into_iter()
+            .find(|asset| asset.id == id))
+    }
+
+    pub async fn markets(&self) -> Result<Vec<MarketRaw>, DatabaseError> {
+        self.cache
+            .get_or_load(MARKETS_KEY, || async {
+                self.markets.list(&Filter::new()).await
+            })
+            .await
+    }
+
+    pub async fn market(&self, id: Uuid) -> Result<Option<MarketRaw>, DatabaseError> {
+        Ok(self
+            .markets()
+            .await?
+            .into_iter()
+            .find(|market| market.id == id))
+    }
+
+    pub async fn market_for(
+        &self,
+        base_asset_id: Uuid,
+        quote_asset_id: Uuid,
+    ) -> Result<Option<MarketRaw>, DatabaseError> {
+        Ok(self.markets().await?.into_iter().find(|market| {
+            market.base_asset_id == base_asset_id && market.quote_asset_id == quote_asset_id
+        }))
+    }
+
+    pub async fn create_asset(
+        &self,
+        admin_id: Uuid,
+        asset: &AssetRaw,
+    ) -> Result<AssetRaw, DatabaseError> {
+        let event = audit(admin_id, "asset.create", None, Some(asset))?;
+        self.commit(UnitOfWork::new().insert(asset).audit(event))
+            .await?;
+        self.cache.invalidate(ASSETS_KEY).await?;
+        Ok(asset.clone())
+    }
+
+    pub async fn update_asset(
+        &self,
+        admin_id: Uuid,
+        asset: &AssetRaw,
+    ) -> Result<AssetRaw, DatabaseError> {
+        let before = self.assets.get(&asset.id).await?;
+        let event = audit(admin_id, "asset.update", before.as_ref(), Some(asset))?;
+        self.commit(UnitOfWork::new().update(asset).audit(event))
+            .await?;
+        self.cache.invalidate(ASSETS_KEY).await?;
+        Ok(asset.clone())
+    }
+
+    /// Removes an asset, failing with `Referenced` while a market trades it, a ledger
+    /// account holds some of it or a withdrawal or deposit of it is open.
+    ///
+    /// The references are checked in the transaction that removes the asset, so none can
+    /// be added in between.
+    pub async fn delete_asset(
+        &self,
+        admin_id: Uuid,
+        id: Uuid,
+    ) -> Result<Option<AssetRaw>, DatabaseError> {
+        let Some(asset) = self.assets.get(&id).await? else {
+            return Ok(None);
+        };
+        let open_withdrawals = CLOSED_WITHDRAWAL_STATUSES
+            .iter()
+            .fold(Filter::new().eq("asset_id", id), |filter, status| {
+                filter.condition("status", Operator::Ne, status)
+            });
+        let event = audit(admin_id, "asset.delete", Some(&asset), None)?;
+        let work = UnitOfWork::new()
+            .unreferenced(MarketRaw::TABLE, &Filter::new().eq("base_asset_id", id))
+            .unreferenced(MarketRaw::TABLE, &Filter::new().eq("quote_asset_id", id))
+            .unreferenced(
+                LEDGER_BALANCES_TABLE,
+                &Filter::new()
+                    .eq("asset_id", id)
+                    .condition("balance", Operator::Ne, 0),
+            )
+            .unreferenced(WITHDRAWALS_TABLE, &open_withdrawals)
+            .unreferenced(
+                DEPOSITS_TABLE,
+                &Filter::new()
+                    .eq("asset_id", id)
+                    .eq("status", PENDING_DEPOSIT_STATUS),
+            )
+            .delete::<AssetRaw>(id)
+            .audit(event);
+        let deleted = self.delete(work, asset).await?;
+        self.cache.invalidate(ASSETS_KEY).await?;
+        Ok(deleted)
+    }
+
+    /// Lists a market, failing with `NotFound` unless both of its assets are listed and
+    /// with `AlreadyExists` if the pair is listed already.
+    pub async fn create_market(
+        &self,
+        admin_id: Uuid,
+        market: &MarketRaw,
+    ) -> Result<MarketRaw, DatabaseError> {
+        for asset_id in [market.base_asset_id, market.quote_asset_id] {
+            self.assets
+                .get(&asset_id)
+                .await?
+                .ok_or(DatabaseError::NotFound)?;
+        }
+        let pair = Filter::new()
+            .eq("base_asset_id", market.base_asset_id)
+            .eq("quote_asset_id", market.quote_asset_id);
+        if !self.markets.list(&pair).await?.is_empty() {
+            return Err(DatabaseError::AlreadyExists);
+        }
+        let event = audit(admin_id, "market.create", None, Some(market))?;
+        self.commit(UnitOfWork::new().insert(market).audit(event))
+            .await?;
+        self.cache.invalidate(MARKETS_KEY).await?;
+        Ok(market.clone())
+    }
+
+    pub async fn update_market(
+        &self,
+        admin_id: Uuid,
+        market: &MarketRaw,
+    ) -> Result<MarketRaw, DatabaseError> {
+        let before = self.markets.get(&market.id).await?;
+        let event = audit(admin_id, "market.update", before.as_ref(), Some(market))?;
+        self.commit(UnitOfWork::new().update(market).audit(event))
+            .await?;
+        self.cache.invalidate(MARKETS_KEY).await?;
+        Ok(market.clone())
+    }
+
+    pub async fn delete_market(
+        &self,
+        admin_id: Uuid,
+        id: Uuid,
+    ) -> Result<Option<MarketRaw>, DatabaseError> {
+        let Some(market) = self.markets.get(&id).await? else {
+            return Ok(None);
+        };
+        let event = audit(admin_id, "market.delete", Some(&market), None)?;
+        let deleted = self
+            .delete(
+                UnitOfWork::new().delete::<MarketRaw>(id).audit(event),
+                market,
+            )
+            .await?;
+        self.cache.invalidate(MARKETS_KEY).await?;
+        Ok(deleted)
+    }
+
+    async fn commit(&self, work: UnitOfWork) -> Result<(), DatabaseError> {
+        work.commit(self.assets.db(), &self.retry).await
+    }
+
+    /// Commits a deletion of `record`, which yields `None` if it was deleted meanwhile.
+    async fn delete<M>(&self, work: UnitOfWork, record: M) -> Result<Option<M>, DatabaseError> {
+        match self.commit(work).await {
+            Ok(()) => Ok(Some(record)),
+            Err(DatabaseError::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Streams every change to assets and markets, made by any replica.
+    ///
+    /// The cached lists are invalidated before a change is yielded, so reading the
+    /// registry in response to it returns the new state.
+    pub async fn changes(
+        &self,
+    ) -> Result<impl Stream<Item = Result<RegistryChange, DatabaseError>>, DatabaseError> {
+        let assets = self
+            .assets
+            .live(&Filter::new())
+            .await?
+            .map(|change| change.map(RegistryChange::Asset));
+        let markets = self
+            .markets
+            .live(&Filter::new())
+            .await?
+            .map(|change| change.map(RegistryChange::Market));
+        let cache = self.cache.clone();
+        Ok(stream::select(assets, markets).then(move |change| {
+            let cache = cache.clone();
+            async move {
+                let change = change?;
+                let key = match &change {
+                    RegistryChange::Asset(_) => ASSETS_KEY,
+                    RegistryChange::Market(_) => MARKETS_KEY,
+                };
+                cache.invalidate(key).await?;
+                Ok(change)
+            }
+        }))
+    }
+}
+
+/// Records the change of a listing by an admin.
+fn audit<M: Record>(
+    admin_id: Uuid,
+    action: &str,
+    before: Option<&M>,
+    after: Option<&M>,
+) -> Result<AuditEvent, DatabaseError> {
+    AuditEvent::new(Actor::Admin(admin_id), AuditCategory::Admin, action).record(before, after)
+}
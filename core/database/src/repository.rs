@@ -0,0 +1,255 @@
+use std::marker::PhantomData;
+
+use futures::Stream;
+
+use models::{AssetRaw, BalanceRaw, MarketRaw, OrderRaw, TradeRaw, UserRaw};
+use serde_json::Value;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use crate::{
+    errors::DatabaseError,
+    filter::{validate_field, Direction, Filter},
+    live::{watch, Change},
+    pagination::{Cursor, CursorSigner, Page, PageRequest, Paginated, MAX_PAGE_SIZE},
+    record::{from_rows, sha256_hex, to_content, Record, SELECT_FIELDS},
+};
+
+pub type UserRepository<C> = Repository<C, UserRaw>;
+pub type AssetRepository<C> = Repository<C, AssetRaw>;
+pub type MarketRepository<C> = Repository<C, MarketRaw>;
+pub type BalanceRepository<C> = Repository<C, BalanceRaw>;
+pub type OrderRepository<C> = Repository<C, OrderRaw>;
+pub type TradeRepository<C> = Repository<C, TradeRaw>;
+
+/// Typed CRUD access to the table of a single [`Record`] type. Records of read-only
+/// tables can only be read.
+#[derive(Debug)]
+pub struct Repository<C: Connection, M: Record> {
+    db: Surreal<C>,
+    _record: PhantomData<fn() -> M>,
+}
+impl<C: Connection, M: Record> Clone for Repository<C, M> {
+    fn clone(&self) -> Self {
+        Self::new(self.db.clone())
+    }
+}
+impl<C: Connection, M: Record> Repository<C, M> {
+    pub fn new(db: Surreal<C>) -> Self {
+        Self {
+            db,
+            _record: PhantomData,
+        }
+    }
+
+    pub fn db(&self) -> &Surreal<C> {
+        &self.db
+    }
+
+    /// Inserts a new record, failing with `AlreadyExists` if its id is taken.
+    pub async fn create(&self, record: &M) -> Result<M, DatabaseError> {
+        writable::<M>()?;
+        let rows: Vec<Value> = self
+            .db
+            .query(format!(
+                "CREATE type::thing($table, $id) CONTENT $content RETURN {SELECT_FIELDS}"
+            ))
+            .bind(("table", M::TABLE))
+            .bind(("id", record.id().to_string()))
+            .bind(("content", to_content(record)?))
+            .await?
+            .take(0)?;
+        from_rows(rows)?.pop().ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn get(&self, id: &Uuid) -> Result<Option<M>, DatabaseError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(format!(
+                "SELECT {SELECT_FIELDS} FROM type::thing($table, $id)"
+            ))
+            .bind(("table", M::TABLE))
+            .bind(("id", id.to_string()))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?.pop())
+    }
+
+    /// Replaces an existing record, failing with `NotFound` instead of inserting it.
+    ///
+    /// Versioned records are only replaced if the stored version still matches the one
+    /// they were read at, otherwise `Conflict` is returned. The stored version is bumped.
+    pub async fn update(&self, record: &M) -> Result<M, DatabaseError> {
+        writable::<M>()?;
+        let mut content = to_content(record)?;
+        let condition = match record.version() {
+            Some(version) => {
+                if let Some(object) = content.as_object_mut() {
+                    object.insert("version".to_string(), Value::from(version + 1));
+                }
+                "version = $version"
+            }
+            None => "id != NONE",
+        };
+        let mut response = self
+            .db
+            .query(format!(
+                "SELECT meta::id(id) AS id FROM type::thing($table, $id);\
+                 UPDATE type::thing($table, $id) CONTENT $content WHERE {condition} RETURN {SELECT_FIELDS};"
+            ))
+            .bind(("table", M::TABLE))
+            .bind(("id", record.id().to_string()))
+            .bind(("content", content))
+            .bind(("version", record.version()))
+            .await?;
+        let existing: Vec<Value> = response.take(0)?;
+        let rows: Vec<Value> = response.take(1)?;
+        match from_rows(rows)?.pop() {
+            Some(updated) => Ok(updated),
+            None if existing.is_empty() => Err(DatabaseError::NotFound),
+            None => Err(DatabaseError::Conflict),
+        }
+    }
+
+    /// Removes a record and returns it, or `None` if it did not exist.
+    pub async fn delete(&self, id: &Uuid) -> Result<Option<M>, DatabaseError> {
+        writable::<M>()?;
+        let rows: Vec<Value> = self
+            .db
+            .query(format!(
+                "SELECT {SELECT_FIELDS} FROM type::thing($table, $id); DELETE type::thing($table, $id);"
+            ))
+            .bind(("table", M::TABLE))
+            .bind(("id", id.to_string()))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?.pop())
+    }
+
+    pub async fn list(&self, filter: &Filter) -> Result<Vec<M>, DatabaseError> {
+        let (clauses, bindings) = filter.render()?;
+        let mut query = self
+            .db
+            .query(format!(
+                "SELECT {SELECT_FIELDS} FROM type::table($table){clauses}"
+            ))
+            .bind(("table", M::TABLE));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+        let rows: Vec<Value> = query.await?.take(0)?;
+        from_rows(rows)
+    }
+
+    /// Streams creations, updates and deletions of the records matching `filter`,
+    /// resubscribing whenever the live query is lost.
+    pub async fn live(
+        &self,
+        filter: &Filter,
+    ) -> Result<impl Stream<Item = Result<Change<M>, DatabaseError>>, DatabaseError> {
+        validate_field(M::TABLE)?;
+        let clauses = filter.render_live()?;
+        watch(
+            self.db.clone(),
+            format!("LIVE SELECT {SELECT_FIELDS} FROM {}{clauses}", M::TABLE),
+        )
+        .await
+    }
+}
+
+/// Fails with `ReadOnly` for records the repository must not write.
+fn writable<M: Record>() -> Result<(), DatabaseError> {
+    match M::READ_ONLY {
+        true => Err(DatabaseError::ReadOnly(M::TABLE)),
+        false => Ok(()),
+    }
+}
+
+impl<C: Connection, M: Paginated> Repository<C, M> {
+    /// Fetches one page of the records matching `filter` by seeking past the cursor
+    /// instead of skipping rows, so every page costs the same.
+    ///
+    /// Relies on `created_at` being stored as a datetime, as the migrated schema does.
+    /// A cursor taken with another filter fails with `InvalidCursor`.
+    pub async fn page(
+        &self,
+        filter: &Filter,
+        request: &PageRequest,
+        signer: &CursorSigner,
+    ) -> Result<Page<M>, DatabaseError> {
+        let (mut clauses, bindings) = filter
+            .render_conditions("pages are ordered by creation time and bounded by cursors")?;
+        let scope = sha256_hex(&serde_json::to_vec(&(M::TABLE, &clauses, &bindings))?);
+        let cursor = request
+            .cursor
+            .as_deref()
+            .map(|token| signer.verify(token))
+            .transpose()?;
+        if cursor.as_ref().is_some_and(|cursor| cursor.scope != scope) {
+            return Err(DatabaseError::InvalidCursor);
+        }
+        let order = cursor.as_ref().map_or(request.order, |cursor| cursor.order);
+        let backward = cursor.as_ref().is_some_and(|cursor| cursor.backward);
+        let limit = request.limit.clamp(1, MAX_PAGE_SIZE);
+
+        // Pages before the cursor are read in reverse and flipped afterwards.
+        let (comparison, direction) = match (order == Direction::Asc) != backward {
+            true => (">", "ASC"),
+            false => ("<", "DESC"),
+        };
+        if cursor.is_some() {
+            clauses.push_str(if clauses.is_empty() {
+                " WHERE "
+            } else {
+                " AND "
+            });
+            clauses.push_str(&format!(
+                "(created_at {comparison} <datetime> $cursor_time \
+                 OR (created_at = <datetime> $cursor_time AND meta::id(id) {comparison} $cursor_id))"
+            ));
+        }
+        let mut query = self
+            .db
+            .query(format!(
+                "SELECT {SELECT_FIELDS} FROM type::table($table){clauses} \
+                 ORDER BY created_at {direction}, id {direction} LIMIT {}",
+                limit + 1
+            ))
+            .bind(("table", M::TABLE));
+        for binding in bindings {
+            query = query.bind(binding);
+        }
+        if let Some(cursor) = &cursor {
+            query = query
+                .bind(("cursor_time", cursor.created_at))
+                .bind(("cursor_id", cursor.id.to_string()));
+        }
+        let rows: Vec<Value> = query.await?.take(0)?;
+        let mut items: Vec<M> = from_rows(rows)?;
+        let more = items.len() > limit;
+        items.truncate(limit);
+        if backward {
+            items.reverse();
+        }
+
+        let (has_next, has_previous) = match backward {
+            true => (true, more),
+            false => (more, cursor.is_some()),
+        };
+        let next = match items.last() {
+            Some(last) if has_next => Some(signer.sign(&Cursor::at(last, order, false, &scope))?),
+            _ => None,
+        };
+        let previous = match items.first() {
+            Some(first) if has_previous => {
+                Some(signer.sign(&Cursor::at(first, order, true, &scope))?)
+            }
+            _ => None,
+        };
+        Ok(Page {
+            items,
+            next,
+            previous,
+        })
+    }
+}
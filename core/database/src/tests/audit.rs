@@ -0,0 +1,118 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde_json::json;
+use uuid::Uuid;
+
+use super::migrated;
+use crate::{Actor, AuditCategory, AuditEvent, AuditLog, DatabaseError, GENESIS_HASH};
+
+fn credit(user_id: Uuid, before: i64, after: i64) -> AuditEvent {
+    AuditEvent::new(
+        Actor::Admin(Uuid::new_v4()),
+        AuditCategory::Balance,
+        "credit",
+    )
+    .target(format!("balances:{user_id}"))
+    .change(
+        Some(json!({ "value": before })),
+        Some(json!({ "value": after })),
+    )
+    .request(
+        Uuid::new_v4().to_string(),
+        Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    )
+}
+
+#[tokio::test]
+async fn appends_a_verifiable_chain() {
+    let log = AuditLog::new(migrated().await);
+    let user_id = Uuid::new_v4();
+
+    let first = log.append(credit(user_id, 0, 10)).await.unwrap();
+    let second = log
+        .append(AuditEvent::new(
+            Actor::User(user_id),
+            AuditCategory::Auth,
+            "login",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!((first.sequence, second.sequence), (0, 1));
+    assert_eq!(first.previous_hash, GENESIS_HASH);
+    assert_eq!(second.previous_hash, first.hash);
+    assert_eq!(log.list(0, 10).await.unwrap(), vec![first, second.clone()]);
+
+    let head = log.verify().await.unwrap();
+    assert_eq!((head.length, head.hash), (2, second.hash));
+}
+
+#[tokio::test]
+async fn concurrent_appends_stay_chained() {
+    let log = AuditLog::new(migrated().await);
+    let user_id = Uuid::new_v4();
+
+    let appends = (0..5).map(|value| log.append(credit(user_id, value, value + 1)));
+    for result in futures::future::join_all(appends).await {
+        result.unwrap();
+    }
+
+    assert_eq!(log.verify().await.unwrap().length, 5);
+}
+
+#[tokio::test]
+async fn entries_cannot_be_changed_or_removed() {
+    let db = migrated().await;
+    let log = AuditLog::new(db.clone());
+    log.append(credit(Uuid::new_v4(), 0, 10)).await.unwrap();
+
+    for statement in [
+        "UPDATE audit_log:0 SET action = 'debit'",
+        "DELETE audit_log:0",
+    ] {
+        assert!(db.query(statement).await.unwrap().check().is_err());
+    }
+    assert_eq!(log.verify().await.unwrap().length, 1);
+}
+
+#[tokio::test]
+async fn verification_detects_tampering() {
+    let db = migrated().await;
+    let log = AuditLog::new(db.clone());
+    let user_id = Uuid::new_v4();
+    for value in 0..3 {
+        log.append(credit(user_id, value, value + 1)).await.unwrap();
+    }
+
+    db.query("REMOVE EVENT audit_log_append_only ON audit_log; UPDATE audit_log:1 SET after.value = 1000")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    assert!(matches!(
+        log.verify().await,
+        Err(DatabaseError::AuditChainBroken(1))
+    ));
+}
+
+#[tokio::test]
+async fn verification_detects_removed_entries() {
+    let db = migrated().await;
+    let log = AuditLog::new(db.clone());
+    let user_id = Uuid::new_v4();
+    for value in 0..3 {
+        log.append(credit(user_id, value, value + 1)).await.unwrap();
+    }
+
+    db.query("REMOVE EVENT audit_log_append_only ON audit_log; DELETE audit_log:1")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    assert!(matches!(
+        log.verify().await,
+        Err(DatabaseError::AuditChainBroken(1))
+    ));
+}
@@ -0,0 +1,273 @@
+use std::io::Cursor;
+
+use chrono::Utc;
+use models::{OrderRaw, UserRaw};
+use serde_json::Value;
+use surrealdb::{engine::local::Db, Surreal};
+use uuid::Uuid;
+
+use super::{balance, migrated, seed_balance};
+use crate::{
+    export, import, BalanceRepository, DatabaseError, ExportOptions, Filter, OrderRepository,
+    UserRepository,
+};
+
+async fn seeded() -> (Surreal<Db>, Uuid) {
+    let db = migrated().await;
+    let user_id = Uuid::new_v4();
+    UserRepository::new(db.clone())
+        .create(&UserRaw {
+            id: user_id,
+            handle: Some("alice".to_string()),
+        })
+        .await
+        .unwrap();
+    seed_balance(&db, &balance(user_id, Uuid::new_v4(), 100))
+        .await
+        .unwrap();
+    OrderRepository::new(db.clone())
+        .create(&OrderRaw {
+            id: Uuid::new_v4(),
+            user_id,
+            base_asset_id: Uuid::new_v4(),
+            base_asset_volume: 10,
+            quote_asset_id: Uuid::new_v4(),
+            quote_asset_volume: 200,
+            price: 20.0,
+            created_at: Utc::now(),
+            version: 0,
+        })
+        .await
+        .unwrap();
+    let account = format!("user:{user_id}");
+    db.query(
+        "CREATE type::thing('journal_entries', $entry) CONTENT {
+            kind: 'deposit',
+            postings: [
+                { account: 'hot_wallet', asset_id: $asset, side: 'debit', amount: 100 },
+                { account: $account, asset_id: $asset, side: 'credit', amount: 100 }
+            ],
+            accounts: ['hot_wallet', $account],
+            created_at: time::now()
+        };
+        CREATE type::thing('ledger_balances', [$account, $asset])
+            SET account = $account, asset_id = $asset, balance = 100;
+        CREATE type::thing('evm-network', 'mainnet') SET name = 'mainnet', rpc_url = 'http://localhost:8545/';",
+    )
+    .bind(("entry", Uuid::new_v4().to_string()))
+    .bind(("asset", Uuid::new_v4().to_string()))
+    .bind(("account", account))
+    .await
+    .unwrap()
+    .check()
+    .unwrap();
+    (db, user_id)
+}
+
+/// Ledger account names of the ledger balances, then of the journal entries.
+async fn accounts(db: &Surreal<Db>) -> Vec<Value> {
+    let mut response = db
+        .query("SELECT VALUE account FROM ledger_balances")
+        .query("SELECT VALUE accounts FROM journal_entries")
+        .await
+        .unwrap();
+    let mut accounts: Vec<Value> = response.take(0).unwrap();
+    let entries: Vec<Vec<Value>> = response.take(1).unwrap();
+    accounts.extend(entries.into_iter().flatten());
+    accounts
+}
+
+#[tokio::test]
+async fn exports_round_trip() {
+    let (source, user_id) = seeded().await;
+    let mut exported = Vec::new();
+    let checksums = export(&source, &ExportOptions::default(), &mut exported)
+        .await
+        .unwrap();
+    assert_eq!(
+        checksums
+            .iter()
+            .map(|checksum| (checksum.table.as_str(), checksum.count))
+            .collect::<Vec<_>>(),
+        vec![
+            ("users", 1),
+            ("assets", 0),
+            ("evm-network", 1),
+            ("markets", 0),
+            ("balances", 1),
+            ("journal_entries", 1),
+            ("ledger_balances", 1),
+            ("orders", 1),
+            ("trades", 0)
+        ]
+    );
+
+    let target = migrated().await;
+    assert_eq!(
+        import(&target, Cursor::new(&exported)).await.unwrap(),
+        checksums
+    );
+
+    let filter = Filter::new().eq("user_id", user_id);
+    for db in [&source, &target] {
+        assert!(UserRepository::new(db.clone())
+            .get(&user_id)
+            .await
+            .unwrap()
+            .is_some());
+    }
+    assert_eq!(
+        OrderRepository::new(target.clone())
+            .list(&filter)
+            .await
+            .unwrap(),
+        OrderRepository::new(source.clone())
+            .list(&filter)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        BalanceRepository::new(target.clone())
+            .list(&filter)
+            .await
+            .unwrap(),
+        BalanceRepository::new(source.clone())
+            .list(&filter)
+            .await
+            .unwrap()
+    );
+    assert_eq!(accounts(&target).await, accounts(&source).await);
+}
+
+#[tokio::test]
+async fn anonymized_exports_replace_user_ids_consistently() {
+    let (source, user_id) = seeded().await;
+    let mut exported = Vec::new();
+    let options = ExportOptions {
+        anonymize: true,
+        ..ExportOptions::default()
+    };
+    export(&source, &options, &mut exported).await.unwrap();
+    let exported_text = String::from_utf8_lossy(&exported);
+    assert!(!exported_text.contains(&user_id.to_string()));
+    assert!(!exported_text.contains("alice"));
+
+    let target = migrated().await;
+    import(&target, Cursor::new(&exported)).await.unwrap();
+    let users = UserRepository::new(target.clone())
+        .list(&Filter::new())
+        .await
+        .unwrap();
+    assert_eq!(users.len(), 1);
+    let filter = Filter::new().eq("user_id", users[0].id);
+    let account = Value::from(format!("user:{}", users[0].id));
+    assert_eq!(
+        accounts(&target).await,
+        vec![account.clone(), Value::from("hot_wallet"), account]
+    );
+    assert_eq!(
+        BalanceRepository::new(target.clone())
+            .list(&filter)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        OrderRepository::new(target)
+            .list(&filter)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn tampered_exports_are_rejected() {
+    let (source, _) = seeded().await;
+    let mut exported = Vec::new();
+    export(&source, &ExportOptions::default(), &mut exported)
+        .await
+        .unwrap();
+    let tampered = String::from_utf8(exported).unwrap().replace("100", "1000");
+
+    let target = migrated().await;
+    assert!(matches!(
+        import(&target, Cursor::new(tampered)).await,
+        Err(DatabaseError::Backup(_))
+    ));
+    assert!(UserRepository::new(target)
+        .list(&Filter::new())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn existing_records_are_not_overwritten() {
+    let (source, _) = seeded().await;
+    let mut exported = Vec::new();
+    let options = ExportOptions {
+        tables: vec!["users".to_string()],
+        ..ExportOptions::default()
+    };
+    export(&source, &options, &mut exported).await.unwrap();
+    let users = UserRepository::new(source.clone());
+    let mut user = users.list(&Filter::new()).await.unwrap().remove(0);
+    user.handle = Some("mallory".to_string());
+    users.update(&user).await.unwrap();
+
+    assert!(matches!(
+        import(&source, Cursor::new(&exported)).await,
+        Err(DatabaseError::AlreadyExists)
+    ));
+    assert_eq!(users.get(&user.id).await.unwrap(), Some(user));
+}
+
+#[tokio::test]
+async fn interrupted_imports_resume() {
+    let (source, user_id) = seeded().await;
+    let mut exported = Vec::new();
+    let checksums = export(&source, &ExportOptions::default(), &mut exported)
+        .await
+        .unwrap();
+
+    let target = migrated().await;
+    target
+        .query("DEFINE FIELD price ON orders TYPE number ASSERT $value < 0")
+        .await
+        .unwrap();
+    assert!(import(&target, Cursor::new(&exported)).await.is_err());
+    assert!(UserRepository::new(target.clone())
+        .get(&user_id)
+        .await
+        .unwrap()
+        .is_some());
+
+    target
+        .query("DEFINE FIELD price ON orders TYPE number ASSERT $value > 0")
+        .await
+        .unwrap();
+    assert_eq!(
+        import(&target, Cursor::new(&exported)).await.unwrap(),
+        checksums
+    );
+    let filter = Filter::new().eq("user_id", user_id);
+    assert_eq!(
+        OrderRepository::new(target).list(&filter).await.unwrap(),
+        OrderRepository::new(source).list(&filter).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn unknown_tables_are_refused() {
+    let options = ExportOptions {
+        tables: vec!["migrations".to_string()],
+        ..ExportOptions::default()
+    };
+    assert!(matches!(
+        export(&migrated().await, &options, Vec::new()).await,
+        Err(DatabaseError::Backup(_))
+    ));
+}
@@ -0,0 +1,51 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use models::BalanceRaw;
+use uuid::Uuid;
+
+use super::balance;
+use crate::{balance_key, Cache, DatabaseError, MemoryStore};
+
+#[tokio::test]
+async fn entries_expire() {
+    let cache = Cache::new(MemoryStore::new(), Duration::from_secs(60));
+    let stored = balance(Uuid::new_v4(), Uuid::new_v4(), 10);
+    let key = balance_key(stored.user_id, stored.asset_id);
+
+    cache.set(&key, &stored).await.unwrap();
+    assert_eq!(
+        cache.get::<BalanceRaw>(&key).await.unwrap(),
+        Some(stored.clone())
+    );
+
+    cache
+        .set_with_ttl(&key, &stored, Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(cache.get::<BalanceRaw>(&key).await.unwrap(), None);
+
+    cache.set(&key, &stored).await.unwrap();
+    cache.invalidate(&key).await.unwrap();
+    assert_eq!(cache.get::<BalanceRaw>(&key).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn misses_are_loaded_once() {
+    let cache = Cache::new(MemoryStore::new(), Duration::from_secs(60));
+    let loads = AtomicU32::new(0);
+    for _ in 0..2 {
+        let value = cache
+            .get_or_load("ticker", || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, DatabaseError>(42)
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 42);
+    }
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+}
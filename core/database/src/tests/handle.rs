@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use models::UserRaw;
+use uuid::Uuid;
+
+use crate::{DatabaseConfig, DatabaseHandle};
+
+fn memory_config() -> DatabaseConfig {
+    DatabaseConfig::new("mem://", "test", "test")
+}
+
+#[tokio::test]
+async fn clones_share_the_reconnected_client() {
+    let database = DatabaseHandle::connect(memory_config()).await.unwrap();
+    let clone = database.clone();
+    let user = UserRaw {
+        id: Uuid::new_v4(),
+        handle: None,
+    };
+    database
+        .repository::<UserRaw>()
+        .create(&user)
+        .await
+        .unwrap();
+    database.ensure_healthy().await.unwrap();
+    assert_eq!(
+        clone.repository::<UserRaw>().get(&user.id).await.unwrap(),
+        Some(user.clone())
+    );
+
+    // a fresh in-memory database replaces the old one
+    database.reconnect().await.unwrap();
+    clone.health_check().await.unwrap();
+    assert_eq!(
+        clone.repository::<UserRaw>().get(&user.id).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn connecting_gives_up_after_the_last_attempt() {
+    let mut config = DatabaseConfig::new("ws://127.0.0.1:1", "test", "test");
+    config.connect_attempts = 2;
+    config.initial_backoff = Duration::from_millis(1);
+    assert!(DatabaseHandle::connect(config).await.is_err());
+}
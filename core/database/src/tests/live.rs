@@ -0,0 +1,86 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use futures::{stream, Stream, StreamExt};
+use uuid::Uuid;
+
+use super::{migrated, order};
+use crate::{
+    live::resubscribing, BalanceRepository, Change, DatabaseError, Direction, Filter, Operator,
+    OrderRepository,
+};
+
+async fn next<S: Stream + Unpin>(changes: &mut S) -> S::Item {
+    tokio::time::timeout(Duration::from_secs(2), changes.next())
+        .await
+        .expect("no change received")
+        .expect("stream ended")
+}
+
+// The embedded engine does not deliver notifications on a current-thread runtime.
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_are_streamed() {
+    let repository = OrderRepository::new(migrated().await);
+    let user_id = Uuid::new_v4();
+    let mut changes = Box::pin(
+        repository
+            .live(&Filter::new().eq("user_id", user_id).condition(
+                "quote_asset_volume",
+                Operator::Gt,
+                1,
+            ))
+            .await
+            .unwrap(),
+    );
+
+    repository.create(&order(Uuid::new_v4(), 10)).await.unwrap();
+    repository.create(&order(user_id, 1)).await.unwrap();
+    let mut stored = repository.create(&order(user_id, 10)).await.unwrap();
+    assert_eq!(
+        next(&mut changes).await.unwrap(),
+        Change::Created(stored.clone())
+    );
+
+    stored.quote_asset_volume = 20;
+    let stored = repository.update(&stored).await.unwrap();
+    assert_eq!(
+        next(&mut changes).await.unwrap(),
+        Change::Updated(stored.clone())
+    );
+
+    repository.delete(&stored.id).await.unwrap();
+    assert_eq!(next(&mut changes).await.unwrap(), Change::Deleted(stored));
+}
+
+#[tokio::test]
+async fn ordered_filters_are_rejected() {
+    let repository = BalanceRepository::new(migrated().await);
+    assert!(matches!(
+        repository
+            .live(&Filter::new().order_by("value", Direction::Asc))
+            .await
+            .err(),
+        Some(DatabaseError::UnsupportedFilter(_))
+    ));
+}
+
+#[tokio::test]
+async fn ended_streams_are_resubscribed() {
+    let subscriptions = AtomicU32::new(0);
+    let items: Vec<u32> = resubscribing(stream::iter(vec![0]), || {
+        let subscription = subscriptions.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            match subscription {
+                // the connection is still down
+                1 => Err(DatabaseError::NotFound),
+                _ => Ok(stream::iter(vec![subscription])),
+            }
+        }
+    })
+    .take(3)
+    .collect()
+    .await;
+    assert_eq!(items, vec![0, 2, 3]);
+}
@@ -0,0 +1,105 @@
+use models::UserRaw;
+use uuid::Uuid;
+
+use super::{balance, memory, migrated, seed_balance};
+use crate::{DatabaseError, Migration, Migrator, UserRepository};
+
+fn migration(file_name: &str, sql: &str) -> Migration {
+    Migration::from_file(file_name, sql.to_string()).unwrap()
+}
+
+#[test]
+fn file_names_are_parsed() {
+    let parsed = migration("V0012__add_index.surql", "");
+    assert_eq!(parsed.version, 12);
+    assert_eq!(parsed.name, "add_index");
+    for file_name in ["0001__x.surql", "V0001_x.surql", "Vx__y.surql", "V1__x.sql"] {
+        assert!(matches!(
+            Migration::from_file(file_name, String::new()),
+            Err(DatabaseError::Migration(_))
+        ));
+    }
+    assert!(matches!(
+        Migrator::new(vec![
+            migration("V1__a.surql", ""),
+            migration("V1__b.surql", "")
+        ]),
+        Err(DatabaseError::Migration(_))
+    ));
+}
+
+#[tokio::test]
+async fn migrations_are_applied_once_in_order() {
+    let db = memory().await;
+    let migrator = Migrator::new(vec![
+        migration("V2__index.surql", "DEFINE INDEX a_value ON a FIELDS value;"),
+        migration("V1__table.surql", "DEFINE TABLE a SCHEMALESS;"),
+    ])
+    .unwrap();
+
+    assert_eq!(migrator.run(&db, true).await.unwrap(), vec![1, 2]);
+    assert!(migrator.applied(&db).await.unwrap().is_empty());
+
+    assert_eq!(migrator.run(&db, false).await.unwrap(), vec![1, 2]);
+    assert_eq!(migrator.applied(&db).await.unwrap(), vec![1, 2]);
+    assert!(migrator.run(&db, false).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_migration_is_not_recorded() {
+    let db = memory().await;
+    let migrator = Migrator::new(vec![migration(
+        "V1__broken.surql",
+        "DEFINE TABLE a SCHEMALESS; THROW 'broken';",
+    )])
+    .unwrap();
+
+    assert!(matches!(
+        migrator.run(&db, false).await,
+        Err(DatabaseError::Migration(_))
+    ));
+    assert!(migrator.applied(&db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn embedded_schema_enforces_assertions() {
+    let db = memory().await;
+    Migrator::embedded().run(&db, false).await.unwrap();
+
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    seed_balance(&db, &balance(user_id, asset_id, 10))
+        .await
+        .unwrap();
+    assert!(matches!(
+        seed_balance(&db, &balance(user_id, Uuid::new_v4(), -1)).await,
+        Err(DatabaseError::Surrealdb(_))
+    ));
+    assert!(matches!(
+        seed_balance(&db, &balance(user_id, asset_id, 5)).await,
+        Err(DatabaseError::Surrealdb(_))
+    ));
+}
+
+#[tokio::test]
+async fn user_handles_are_unique_and_well_formed() {
+    let repository = UserRepository::new(migrated().await);
+    let user = |handle: Option<&str>| UserRaw {
+        id: Uuid::new_v4(),
+        handle: handle.map(str::to_string),
+    };
+    repository.create(&user(Some("alice_1"))).await.unwrap();
+    repository.create(&user(None)).await.unwrap();
+    repository.create(&user(None)).await.unwrap();
+    for handle in ["alice_1", "Alice", "al", "alice bob"] {
+        assert!(matches!(
+            repository.create(&user(Some(handle))).await,
+            Err(DatabaseError::Surrealdb(_))
+        ));
+    }
+}
+
+#[test]
+fn embedded_migrations_match_directory() {
+    let from_dir = Migrator::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations")).unwrap();
+    assert_eq!(from_dir.migrations(), Migrator::embedded().migrations());
+}
@@ -0,0 +1,75 @@
+mod audit;
+mod backup;
+mod cache;
+mod handle;
+mod live;
+mod migrations;
+mod outbox;
+mod pagination;
+mod pubsub;
+mod registry;
+mod repository;
+mod transaction;
+
+use chrono::Utc;
+use models::{BalanceRaw, OrderRaw};
+use serde_json::Value;
+use surrealdb::{
+    engine::local::{Db, Mem},
+    Surreal,
+};
+use uuid::Uuid;
+
+use crate::{from_rows, to_content, DatabaseError, Record};
+
+pub async fn memory() -> Surreal<Db> {
+    let db = Surreal::new::<Mem>(()).await.unwrap();
+    db.use_ns("test").use_db("test").await.unwrap();
+    db
+}
+
+/// In-memory database with the embedded schema applied.
+pub async fn migrated() -> Surreal<Db> {
+    let db = memory().await;
+    crate::Migrator::embedded().run(&db, false).await.unwrap();
+    db
+}
+
+pub fn balance(user_id: Uuid, asset_id: Uuid, value: i64) -> BalanceRaw {
+    BalanceRaw {
+        id: Uuid::new_v4(),
+        user_id,
+        asset_id,
+        value,
+        version: 0,
+    }
+}
+
+/// Stores `balance` with a plain `CREATE`, as the repository refuses to write balances.
+pub async fn seed_balance(
+    db: &Surreal<Db>,
+    balance: &BalanceRaw,
+) -> Result<BalanceRaw, DatabaseError> {
+    let rows: Vec<Value> = db
+        .query("CREATE type::thing($table, $id) CONTENT $content RETURN *, meta::id(id) AS id")
+        .bind(("table", BalanceRaw::TABLE))
+        .bind(("id", balance.id.to_string()))
+        .bind(("content", to_content(balance)?))
+        .await?
+        .take(0)?;
+    from_rows(rows)?.pop().ok_or(DatabaseError::NotFound)
+}
+
+pub fn order(user_id: Uuid, quote_asset_volume: i64) -> OrderRaw {
+    OrderRaw {
+        id: Uuid::new_v4(),
+        user_id,
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 10,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume,
+        price: 1.0,
+        created_at: Utc::now(),
+        version: 0,
+    }
+}
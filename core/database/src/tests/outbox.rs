@@ -0,0 +1,136 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt};
+use models::TradeRaw;
+use uuid::Uuid;
+
+use super::migrated;
+use crate::{
+    Broker, DatabaseError, EventBus, MemoryBroker, OutboxRelay, RetryPolicy, TradeRepository,
+    UnitOfWork, TRADES_CHANNEL,
+};
+
+/// Broker that refuses to publish while `down` is set.
+#[derive(Default)]
+struct FlakyBroker {
+    inner: MemoryBroker,
+    down: AtomicBool,
+}
+#[async_trait]
+impl Broker for FlakyBroker {
+    async fn publish(&self, channel: &str, payload: Vec<u8>) -> Result<(), DatabaseError> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err(DatabaseError::Config("broker is down".to_string()));
+        }
+        self.inner.publish(channel, payload).await
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, Vec<u8>>, DatabaseError> {
+        self.inner.subscribe(channel).await
+    }
+}
+
+fn trade() -> TradeRaw {
+    TradeRaw {
+        id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 10,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume: 200,
+        created_at: Utc::now(),
+    }
+}
+
+#[tokio::test]
+async fn settled_trades_are_relayed() {
+    let db = migrated().await;
+    let broker = Arc::new(MemoryBroker::new());
+    let relay = OutboxRelay::new(db.clone(), broker.clone());
+    let bus = EventBus::new(broker);
+    let mut trades = Box::pin(bus.subscribe::<TradeRaw>(TRADES_CHANNEL).await.unwrap());
+
+    let trade = trade();
+    UnitOfWork::new()
+        .insert(&trade)
+        .publish(TRADES_CHANNEL, &trade)
+        .commit(&db, &RetryPolicy::default())
+        .await
+        .unwrap();
+
+    assert_eq!(relay.relay_once().await.unwrap(), 1);
+    assert_eq!(trades.next().await.unwrap().unwrap(), trade);
+    assert!(relay.pending(10).await.unwrap().is_empty());
+    assert_eq!(relay.relay_once().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn rolled_back_transactions_publish_nothing() {
+    let db = migrated().await;
+    let relay = OutboxRelay::new(db.clone(), MemoryBroker::new());
+
+    let trade = trade();
+    TradeRepository::new(db.clone())
+        .create(&trade)
+        .await
+        .unwrap();
+
+    let result = UnitOfWork::new()
+        .publish(TRADES_CHANNEL, &trade)
+        .insert(&trade)
+        .commit(&db, &RetryPolicy::default())
+        .await;
+
+    assert!(matches!(result, Err(DatabaseError::AlreadyExists)));
+    assert!(relay.pending(10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_messages_are_retried_in_order() {
+    let db = migrated().await;
+    let broker = Arc::new(FlakyBroker::default());
+    let relay = OutboxRelay::new(db.clone(), broker.clone()).with_batch_size(2);
+    let bus = EventBus::new(broker.clone());
+    let mut events = Box::pin(bus.subscribe::<u32>("events").await.unwrap());
+
+    (0..3)
+        .fold(UnitOfWork::new(), |work, event| {
+            work.publish("events", &event)
+        })
+        .commit(&db, &RetryPolicy::default())
+        .await
+        .unwrap();
+
+    broker.down.store(true, Ordering::SeqCst);
+    assert_eq!(relay.relay_once().await.unwrap(), 0);
+    let pending = relay.pending(10).await.unwrap();
+    assert_eq!(pending.len(), 3);
+    assert_eq!(pending[0].attempts, 1);
+
+    broker.down.store(false, Ordering::SeqCst);
+    let handle = relay
+        .clone()
+        .with_poll_interval(Duration::from_millis(10))
+        .spawn();
+    for expected in 0..3 {
+        assert_eq!(events.next().await.unwrap().unwrap(), expected);
+    }
+    handle.abort();
+
+    let before = Utc::now() + chrono::Duration::seconds(1);
+    relay.prune(before).await.unwrap();
+    let remaining: Vec<serde_json::Value> = db
+        .query("SELECT * FROM outbox")
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    assert!(remaining.is_empty());
+}
@@ -0,0 +1,163 @@
+use chrono::{Duration, TimeZone, Utc};
+use models::OrderRaw;
+use uuid::Uuid;
+
+use super::migrated;
+use crate::{CursorSigner, DatabaseError, Direction, Filter, OrderRepository, Page, PageRequest};
+
+fn order(user_id: Uuid, seconds: i64) -> OrderRaw {
+    OrderRaw {
+        id: Uuid::new_v4(),
+        user_id,
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 1,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume: 1,
+        price: 1.0,
+        created_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds),
+        version: 0,
+    }
+}
+
+fn ids(page: &Page<OrderRaw>) -> Vec<Uuid> {
+    page.items.iter().map(|order| order.id).collect()
+}
+
+/// Orders of one user, two of them created at the same time, with their ids in descending order.
+async fn setup() -> (
+    OrderRepository<surrealdb::engine::local::Db>,
+    Uuid,
+    Vec<Uuid>,
+) {
+    let repository = OrderRepository::new(migrated().await);
+    let user_id = Uuid::new_v4();
+    let mut orders: Vec<_> = [0, 1, 2, 3, 3, 4, 5]
+        .into_iter()
+        .map(|seconds| order(user_id, seconds))
+        .collect();
+    for order in &orders {
+        repository.create(order).await.unwrap();
+    }
+    repository.create(&order(Uuid::new_v4(), 3)).await.unwrap();
+
+    orders.sort_by_key(|order| std::cmp::Reverse((order.created_at, order.id.to_string())));
+    (
+        repository,
+        user_id,
+        orders.iter().map(|order| order.id).collect(),
+    )
+}
+
+#[tokio::test]
+async fn pages_forward_and_back() {
+    let (repository, user_id, expected) = setup().await;
+    let signer = CursorSigner::new(b"secret");
+    let filter = Filter::new().eq("user_id", user_id);
+
+    let mut pages = vec![repository
+        .page(&filter, &PageRequest::first(3, Direction::Desc), &signer)
+        .await
+        .unwrap()];
+    assert_eq!(pages[0].previous, None);
+    while let Some(next) = pages.last().unwrap().next.clone() {
+        pages.push(
+            repository
+                .page(&filter, &PageRequest::at(next, 3), &signer)
+                .await
+                .unwrap(),
+        );
+    }
+    assert_eq!(pages.len(), 3);
+    assert_eq!(pages.iter().flat_map(ids).collect::<Vec<_>>(), expected);
+
+    let mut page = pages.pop().unwrap();
+    while let Some(previous) = page.previous.clone() {
+        page = repository
+            .page(&filter, &PageRequest::at(previous, 3), &signer)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), ids(&pages.pop().unwrap()));
+    }
+    assert!(pages.is_empty());
+}
+
+#[tokio::test]
+async fn ascending_pages_reverse_the_order() {
+    let (repository, user_id, mut expected) = setup().await;
+    let signer = CursorSigner::new(b"secret");
+    let filter = Filter::new().eq("user_id", user_id);
+    expected.reverse();
+
+    let first = repository
+        .page(&filter, &PageRequest::first(4, Direction::Asc), &signer)
+        .await
+        .unwrap();
+    let second = repository
+        .page(
+            &filter,
+            &PageRequest::at(first.next.clone().unwrap(), 4),
+            &signer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.next, None);
+    assert_eq!([ids(&first), ids(&second)].concat(), expected);
+}
+
+#[tokio::test]
+async fn forged_cursors_are_rejected() {
+    let (repository, user_id, _) = setup().await;
+    let filter = Filter::new().eq("user_id", user_id);
+    let signer = CursorSigner::new(b"secret");
+    let foreign = repository
+        .page(
+            &filter,
+            &PageRequest::first(3, Direction::Desc),
+            &CursorSigner::new(b"other"),
+        )
+        .await
+        .unwrap()
+        .next
+        .unwrap();
+    let tampered = repository
+        .page(&filter, &PageRequest::first(3, Direction::Desc), &signer)
+        .await
+        .unwrap()
+        .next
+        .unwrap()
+        .replacen('e', "f", 1);
+
+    for cursor in [foreign, tampered, "garbage".to_string()] {
+        assert!(matches!(
+            repository
+                .page(&filter, &PageRequest::at(cursor, 3), &signer)
+                .await,
+            Err(DatabaseError::InvalidCursor)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn cursors_are_bound_to_their_filter() {
+    let (repository, user_id, _) = setup().await;
+    let signer = CursorSigner::new(b"secret");
+    let next = repository
+        .page(
+            &Filter::new().eq("user_id", user_id),
+            &PageRequest::first(3, Direction::Desc),
+            &signer,
+        )
+        .await
+        .unwrap()
+        .next
+        .unwrap();
+
+    for filter in [Filter::new(), Filter::new().eq("user_id", Uuid::new_v4())] {
+        assert!(matches!(
+            repository
+                .page(&filter, &PageRequest::at(next.clone(), 3), &signer)
+                .await,
+            Err(DatabaseError::InvalidCursor)
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use models::BalanceRaw;
+use uuid::Uuid;
+
+use super::balance;
+use crate::{EventBus, MemoryBroker};
+
+#[tokio::test]
+async fn events_fan_out_to_every_subscriber() {
+    let bus = EventBus::new(MemoryBroker::new());
+    let mut first = Box::pin(bus.subscribe::<BalanceRaw>("balances").await.unwrap());
+    let mut second = Box::pin(bus.subscribe::<BalanceRaw>("balances").await.unwrap());
+    let mut other = Box::pin(bus.subscribe::<BalanceRaw>("trades").await.unwrap());
+
+    let event = balance(Uuid::new_v4(), Uuid::new_v4(), 10);
+    bus.publish("balances", &event).await.unwrap();
+
+    assert_eq!(first.next().await.unwrap().unwrap(), event);
+    assert_eq!(second.next().await.unwrap().unwrap(), event);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(20), other.next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn malformed_events_are_reported() {
+    let bus = EventBus::new(MemoryBroker::new());
+    let mut events = Box::pin(bus.subscribe::<BalanceRaw>("balances").await.unwrap());
+    bus.publish("balances", &"not a balance").await.unwrap();
+    assert!(events.next().await.unwrap().is_err());
+}
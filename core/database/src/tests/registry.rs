@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use models::{AssetRaw, MarketRaw, Network};
+use surrealdb::{engine::local::Db, Surreal};
+use uuid::Uuid;
+
+use super::migrated;
+use crate::{
+    AssetRepository, AuditCategory, AuditLog, Cache, Change, DatabaseError, Filter, MemoryStore,
+    Registry, RegistryChange,
+};
+
+fn asset(symbol: &str) -> AssetRaw {
+    AssetRaw {
+        id: Uuid::new_v4(),
+        name: symbol.to_lowercase(),
+        symbol: symbol.to_string(),
+        precision: 18,
+        network: Network::Ethereum,
+        contract_address: Some(format!("0x{}", Uuid::new_v4().simple())),
+        deposits_enabled: true,
+        withdrawals_enabled: true,
+        trading_enabled: true,
+    }
+}
+
+fn market(base: &AssetRaw, quote: &AssetRaw) -> MarketRaw {
+    MarketRaw {
+        id: Uuid::new_v4(),
+        base_asset_id: base.id,
+        quote_asset_id: quote.id,
+        enabled: true,
+    }
+}
+
+fn registry(db: Surreal<Db>) -> Registry<Db, MemoryStore> {
+    Registry::new(db, Cache::new(MemoryStore::new(), Duration::from_secs(60)))
+}
+
+#[tokio::test]
+async fn listings_are_cached_and_invalidated_by_admin_changes() {
+    let db = migrated().await;
+    let registry = registry(db.clone());
+    let admin_id = Uuid::new_v4();
+    let (usdc, weth) = (asset("USDC"), asset("WETH"));
+
+    registry.create_asset(admin_id, &weth).await.unwrap();
+    registry.create_asset(admin_id, &usdc).await.unwrap();
+    assert_eq!(
+        registry.assets().await.unwrap(),
+        vec![usdc.clone(), weth.clone()]
+    );
+
+    // Writes that bypass the registry are only seen once the cache is invalidated.
+    let mut delisted = weth.clone();
+    delisted.trading_enabled = false;
+    AssetRepository::new(db.clone())
+        .update(&delisted)
+        .await
+        .unwrap();
+    assert_eq!(registry.asset(weth.id).await.unwrap(), Some(weth.clone()));
+
+    delisted.withdrawals_enabled = false;
+    registry.update_asset(admin_id, &delisted).await.unwrap();
+    assert_eq!(registry.asset(weth.id).await.unwrap(), Some(delisted));
+
+    let entries = AuditLog::new(db).list(0, 10).await.unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries
+        .iter()
+        .all(|entry| entry.event.category == AuditCategory::Admin));
+    assert_eq!(entries[2].event.action, "asset.update");
+}
+
+#[tokio::test]
+async fn markets_require_listed_assets() {
+    let registry = registry(migrated().await);
+    let admin_id = Uuid::new_v4();
+    let (weth, usdc) = (asset("WETH"), asset("USDC"));
+    registry.create_asset(admin_id, &weth).await.unwrap();
+
+    assert!(matches!(
+        registry
+            .create_market(admin_id, &market(&weth, &usdc))
+            .await,
+        Err(DatabaseError::NotFound)
+    ));
+
+    registry.create_asset(admin_id, &usdc).await.unwrap();
+    let listed = market(&weth, &usdc);
+    registry.create_market(admin_id, &listed).await.unwrap();
+    assert!(matches!(
+        registry
+            .create_market(admin_id, &market(&weth, &usdc))
+            .await,
+        Err(DatabaseError::AlreadyExists)
+    ));
+    assert_eq!(
+        registry.market_for(weth.id, usdc.id).await.unwrap(),
+        Some(listed.clone())
+    );
+
+    assert!(matches!(
+        registry.delete_asset(admin_id, usdc.id).await,
+        Err(DatabaseError::Referenced("markets"))
+    ));
+    registry.delete_market(admin_id, listed.id).await.unwrap();
+    registry.delete_asset(admin_id, usdc.id).await.unwrap();
+    assert!(registry.markets().await.unwrap().is_empty());
+    assert_eq!(registry.assets().await.unwrap(), vec![weth]);
+}
+
+#[tokio::test]
+async fn assets_held_in_the_ledger_are_not_deleted() {
+    let db = migrated().await;
+    let registry = registry(db.clone());
+    let admin_id = Uuid::new_v4();
+    let weth = asset("WETH");
+    registry.create_asset(admin_id, &weth).await.unwrap();
+    let account = format!("user:{}", Uuid::new_v4());
+    db.query(
+        "CREATE type::thing('ledger_balances', [$account, $asset])
+            SET account = $account, asset_id = $asset, balance = 5",
+    )
+    .bind(("account", account.as_str()))
+    .bind(("asset", weth.id.to_string()))
+    .await
+    .unwrap()
+    .check()
+    .unwrap();
+
+    assert!(matches!(
+        registry.delete_asset(admin_id, weth.id).await,
+        Err(DatabaseError::Referenced("ledger_balances"))
+    ));
+    assert_eq!(registry.assets().await.unwrap(), vec![weth.clone()]);
+
+    db.query("UPDATE type::thing('ledger_balances', [$account, $asset]) SET balance = 0")
+        .bind(("account", account.as_str()))
+        .bind(("asset", weth.id.to_string()))
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    assert_eq!(
+        registry.delete_asset(admin_id, weth.id).await.unwrap(),
+        Some(weth)
+    );
+}
+
+#[tokio::test]
+async fn changes_are_not_applied_without_their_audit_entry() {
+    let db = migrated().await;
+    let registry = registry(db.clone());
+    db.query("DEFINE FIELD action ON audit_log TYPE string ASSERT $value != 'asset.create'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    assert!(registry
+        .create_asset(Uuid::new_v4(), &asset("WETH"))
+        .await
+        .is_err());
+    assert!(AssetRepository::new(db.clone())
+        .list(&Filter::new())
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(AuditLog::new(db).list(0, 10).await.unwrap().is_empty());
+}
+
+// Live query notifications are only delivered on a multi-threaded runtime.
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_reach_other_replicas() {
+    let db = migrated().await;
+    let (admin, replica) = (registry(db.clone()), registry(db));
+    let admin_id = Uuid::new_v4();
+    assert!(replica.assets().await.unwrap().is_empty());
+    let mut changes = Box::pin(replica.changes().await.unwrap());
+
+    let weth = asset("WETH");
+    admin.create_asset(admin_id, &weth).await.unwrap();
+
+    let change = tokio::time::timeout(Duration::from_secs(5), changes.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(change, RegistryChange::Asset(Change::Created(weth.clone())));
+    assert_eq!(replica.assets().await.unwrap(), vec![weth]);
+}
@@ -0,0 +1,223 @@
+use chrono::Utc;
+use models::{AssetRaw, Network, OrderRaw, UserRaw};
+use uuid::Uuid;
+
+use super::{balance, memory, migrated, order, seed_balance};
+use crate::{
+    AssetRepository, BalanceRepository, DatabaseError, Direction, Filter, Operator,
+    OrderRepository, RetryPolicy, UnitOfWork, UserRepository,
+};
+
+#[tokio::test]
+async fn crud_roundtrip() {
+    let repository = AssetRepository::new(memory().await);
+    let mut asset = AssetRaw {
+        id: Uuid::new_v4(),
+        name: "Ether".to_string(),
+        symbol: "ETH".to_string(),
+        precision: 18,
+        network: Network::Ethereum,
+        contract_address: None,
+        deposits_enabled: true,
+        withdrawals_enabled: true,
+        trading_enabled: true,
+    };
+
+    assert_eq!(repository.create(&asset).await.unwrap(), asset);
+    assert!(matches!(
+        repository.create(&asset).await,
+        Err(DatabaseError::AlreadyExists)
+    ));
+    assert_eq!(
+        repository.get(&asset.id).await.unwrap(),
+        Some(asset.clone())
+    );
+
+    asset.precision = 6;
+    assert_eq!(repository.update(&asset).await.unwrap(), asset);
+    assert_eq!(
+        repository.delete(&asset.id).await.unwrap(),
+        Some(asset.clone())
+    );
+    assert_eq!(repository.get(&asset.id).await.unwrap(), None);
+    assert_eq!(repository.delete(&asset.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn update_does_not_insert() {
+    let repository = UserRepository::new(memory().await);
+    let user = UserRaw {
+        id: Uuid::new_v4(),
+        handle: None,
+    };
+    assert!(matches!(
+        repository.update(&user).await,
+        Err(DatabaseError::NotFound)
+    ));
+    assert_eq!(repository.get(&user.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn list_filters_and_orders() {
+    let db = memory().await;
+    let repository = BalanceRepository::new(db.clone());
+    let (user_id, other_id) = (Uuid::new_v4(), Uuid::new_v4());
+    for value in [30, 10, 20] {
+        seed_balance(&db, &balance(user_id, Uuid::new_v4(), value))
+            .await
+            .unwrap();
+    }
+    seed_balance(&db, &balance(other_id, Uuid::new_v4(), 5))
+        .await
+        .unwrap();
+
+    let filter = Filter::new()
+        .eq("user_id", user_id)
+        .condition("value", Operator::Gte, 15)
+        .order_by("value", Direction::Desc);
+    let values: Vec<i64> = repository
+        .list(&filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|balance| balance.value)
+        .collect();
+    assert_eq!(values, vec![30, 20]);
+
+    let page = repository
+        .list(
+            &Filter::new()
+                .order_by("value", Direction::Asc)
+                .limit(2)
+                .start(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].value, 10);
+
+    let orders = OrderRepository::new(db);
+    assert!(orders.list(&Filter::new()).await.unwrap().is_empty());
+    assert!(matches!(
+        orders
+            .list(&Filter::new().eq("price; DELETE orders", 1.0))
+            .await,
+        Err(DatabaseError::InvalidField(_))
+    ));
+}
+
+#[tokio::test]
+async fn order_fields_survive_roundtrip() {
+    let repository = OrderRepository::new(memory().await);
+    let order = OrderRaw {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 100,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume: 250,
+        price: 2.5,
+        created_at: Utc::now(),
+        version: 0,
+    };
+    repository.create(&order).await.unwrap();
+    assert_eq!(
+        repository
+            .list(&Filter::new().eq("base_asset_id", order.base_asset_id))
+            .await
+            .unwrap(),
+        vec![order]
+    );
+}
+
+#[tokio::test]
+async fn stale_update_conflicts() {
+    for db in [memory().await, migrated().await] {
+        let repository = OrderRepository::new(db);
+        let stored = repository.create(&order(Uuid::new_v4(), 10)).await.unwrap();
+
+        let mut first = stored.clone();
+        first.quote_asset_volume = 20;
+        let updated = repository.update(&first).await.unwrap();
+        assert_eq!((updated.quote_asset_volume, updated.version), (20, 1));
+
+        let mut second = stored;
+        second.quote_asset_volume = 30;
+        assert!(matches!(
+            repository.update(&second).await,
+            Err(DatabaseError::Conflict)
+        ));
+        assert_eq!(repository.get(&updated.id).await.unwrap(), Some(updated));
+    }
+}
+
+#[tokio::test]
+async fn versioned_update_does_not_insert() {
+    let repository = OrderRepository::new(memory().await);
+    assert!(matches!(
+        repository.update(&order(Uuid::new_v4(), 10)).await,
+        Err(DatabaseError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn conflicting_update_succeeds_after_reread() {
+    let repository = OrderRepository::new(migrated().await);
+    let stored = repository.create(&order(Uuid::new_v4(), 10)).await.unwrap();
+    let mut concurrent = stored.clone();
+    concurrent.quote_asset_volume = 15;
+    repository.update(&concurrent).await.unwrap();
+
+    let mut stale = Some(stored.clone());
+    let updated = RetryPolicy::default()
+        .run(|| {
+            let repository = repository.clone();
+            let cached = stale.take();
+            async move {
+                let mut current = match cached {
+                    Some(current) => current,
+                    None => repository
+                        .get(&stored.id)
+                        .await?
+                        .ok_or(DatabaseError::NotFound)?,
+                };
+                current.quote_asset_volume *= 2;
+                repository.update(&current).await
+            }
+        })
+        .await
+        .unwrap();
+    assert_eq!((updated.quote_asset_volume, updated.version), (30, 2));
+}
+
+#[tokio::test]
+async fn balances_are_read_only() {
+    let db = migrated().await;
+    let repository = BalanceRepository::new(db.clone());
+    let stored = seed_balance(&db, &balance(Uuid::new_v4(), Uuid::new_v4(), 10))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        repository
+            .create(&balance(Uuid::new_v4(), Uuid::new_v4(), 10))
+            .await,
+        Err(DatabaseError::ReadOnly("balances"))
+    ));
+    assert!(matches!(
+        repository.update(&stored).await,
+        Err(DatabaseError::ReadOnly("balances"))
+    ));
+    assert!(matches!(
+        repository.delete(&stored.id).await,
+        Err(DatabaseError::ReadOnly("balances"))
+    ));
+    assert!(matches!(
+        UnitOfWork::new()
+            .insert(&balance(Uuid::new_v4(), Uuid::new_v4(), 10))
+            .commit(&db, &RetryPolicy::default())
+            .await,
+        Err(DatabaseError::ReadOnly("balances"))
+    ));
+    assert_eq!(repository.get(&stored.id).await.unwrap(), Some(stored));
+}
@@ -0,0 +1,173 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use chrono::Utc;
+use models::{OrderRaw, TradeRaw};
+use uuid::Uuid;
+
+use super::{memory, migrated, order};
+use crate::{
+    Actor, AuditCategory, AuditEvent, AuditLog, DatabaseError, OrderRepository, RetryPolicy,
+    TradeRepository, UnitOfWork,
+};
+
+fn trade() -> TradeRaw {
+    TradeRaw {
+        id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 10,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume: 200,
+        created_at: Utc::now(),
+    }
+}
+
+#[tokio::test]
+async fn inserts_are_committed_together() {
+    for db in [memory().await, migrated().await] {
+        let (trade, order) = (trade(), order(Uuid::new_v4(), 200));
+        UnitOfWork::new()
+            .insert(&trade)
+            .insert(&order)
+            .commit(&db, &RetryPolicy::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            TradeRepository::new(db.clone())
+                .get(&trade.id)
+                .await
+                .unwrap(),
+            Some(trade)
+        );
+        assert_eq!(
+            OrderRepository::new(db).get(&order.id).await.unwrap(),
+            Some(order)
+        );
+    }
+}
+
+#[tokio::test]
+async fn failed_insert_leaves_no_partial_writes() {
+    for db in [memory().await, migrated().await] {
+        let (trade, order) = (trade(), order(Uuid::new_v4(), 200));
+        TradeRepository::new(db.clone())
+            .create(&trade)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            UnitOfWork::new()
+                .insert(&order)
+                .insert(&trade)
+                .commit(&db, &RetryPolicy::default())
+                .await,
+            Err(DatabaseError::AlreadyExists)
+        ));
+        assert_eq!(OrderRepository::new(db).get(&order.id).await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn audit_events_are_committed_with_the_changes() {
+    let db = migrated().await;
+    let log = AuditLog::new(db.clone());
+    let event = |action: &str| AuditEvent::new(Actor::System, AuditCategory::Admin, action);
+    let mut order = order(Uuid::new_v4(), 200);
+
+    assert!(matches!(
+        UnitOfWork::new()
+            .update(&order)
+            .audit(event("order.update"))
+            .commit(&db, &RetryPolicy::default())
+            .await,
+        Err(DatabaseError::NotFound)
+    ));
+    assert!(log.list(0, 10).await.unwrap().is_empty());
+
+    UnitOfWork::new()
+        .insert(&order)
+        .audit(event("order.create"))
+        .commit(&db, &RetryPolicy::default())
+        .await
+        .unwrap();
+    order.quote_asset_volume = 100;
+    UnitOfWork::new()
+        .update(&order)
+        .audit(event("order.update"))
+        .commit(&db, &RetryPolicy::default())
+        .await
+        .unwrap();
+    // the stored version moved on, so replacing the order read at the old one conflicts
+    assert!(matches!(
+        UnitOfWork::new()
+            .update(&order)
+            .commit(&db, &RetryPolicy::default())
+            .await,
+        Err(DatabaseError::Conflict)
+    ));
+    UnitOfWork::new()
+        .delete::<OrderRaw>(order.id)
+        .audit(event("order.delete"))
+        .commit(&db, &RetryPolicy::default())
+        .await
+        .unwrap();
+
+    assert_eq!(OrderRepository::new(db).get(&order.id).await.unwrap(), None);
+    let actions: Vec<String> = log
+        .list(0, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.event.action)
+        .collect();
+    assert_eq!(actions, ["order.create", "order.update", "order.delete"]);
+    assert_eq!(log.verify().await.unwrap().length, 3);
+}
+
+#[tokio::test]
+async fn conflicts_are_retried() {
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+    };
+    let conflict = || {
+        DatabaseError::from(surrealdb::Error::Db(
+            surrealdb::error::Db::TxConditionNotMet,
+        ))
+    };
+
+    let attempts = AtomicU32::new(0);
+    let result = policy
+        .run(|| async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(conflict()),
+                _ => Ok(()),
+            }
+        })
+        .await;
+    assert!(result.is_ok());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    let attempts = AtomicU32::new(0);
+    let result: Result<(), _> = policy
+        .run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(conflict())
+        })
+        .await;
+    assert!(result.unwrap_err().is_retryable());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    let attempts = AtomicU32::new(0);
+    let result: Result<(), _> = policy
+        .run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(DatabaseError::InsufficientBalance)
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
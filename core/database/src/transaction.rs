@@ -0,0 +1,374 @@
+use std::{future::Future, time::Duration};
+
+use serde::Serialize;
+use serde_json::Value;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use crate::{
+    audit::{AuditEvent, AuditLog},
+    errors::DatabaseError,
+    filter::Filter,
+    outbox::OUTBOX_TABLE,
+    record::{to_content, Record},
+};
+
+/// Message a transaction `THROW`s to abort with `DatabaseError::InsufficientBalance`.
+pub const INSUFFICIENT_BALANCE: &str = "insufficient balance";
+
+/// Message a transaction `THROW`s to abort with `DatabaseError::Conflict`, e.g. when it
+/// finds a record modified since it was read.
+pub const CONFLICT: &str = "record was modified concurrently";
+
+/// Message a transaction `THROW`s to abort with `DatabaseError::NotFound` when a record it
+/// changes does not exist.
+pub const NOT_FOUND: &str = "record not found";
+
+/// Message a transaction `THROW`s, followed by a table name, to abort with
+/// `DatabaseError::Referenced` for that table.
+pub const REFERENCED: &str = "record is still referenced by ";
+
+/// How often and how fast a transaction is retried after a conflict.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+        }
+    }
+}
+impl RetryPolicy {
+    /// Runs `operation` until it succeeds, fails with a non-retryable error or
+    /// runs out of attempts, doubling the backoff after every conflict.
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, DatabaseError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, DatabaseError>>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(err) if err.is_retryable() && attempt < self.max_attempts => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs `query`, usually a `BEGIN`/`COMMIT` block, with `bindings`, retrying it on
+    /// conflicts. Fails with the error of the statement that aborted the transaction,
+    /// mapping [`INSUFFICIENT_BALANCE`] and [`CONFLICT`] to their `DatabaseError`.
+    pub async fn execute<C: Connection>(
+        &self,
+        db: &Surreal<C>,
+        query: &str,
+        bindings: &[(String, Value)],
+    ) -> Result<(), DatabaseError> {
+        self.run(|| execute_once(db, query, bindings)).await
+    }
+}
+
+/// Runs `query` with `bindings` a single time, failing like [`RetryPolicy::execute`].
+pub(crate) async fn execute_once<C: Connection>(
+    db: &Surreal<C>,
+    query: &str,
+    bindings: &[(String, Value)],
+) -> Result<(), DatabaseError> {
+    let mut request = db.query(query);
+    for binding in bindings.iter().cloned() {
+        request = request.bind(binding);
+    }
+    let mut response = request.await?;
+    match first_cause(response.take_errors().into_iter().collect()) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Insert {
+        table: &'static str,
+        id: Uuid,
+        content: Result<Value, String>,
+    },
+    Update {
+        table: &'static str,
+        id: Uuid,
+        content: Result<Value, String>,
+        version: Option<u64>,
+    },
+    Delete {
+        table: &'static str,
+        id: Uuid,
+    },
+    Publish {
+        id: Uuid,
+        channel: String,
+        payload: Result<Value, String>,
+    },
+    /// Check that no record of `table` matches `filter`.
+    Unreferenced {
+        table: &'static str,
+        filter: Filter,
+    },
+    /// Write to a read-only table, which fails the commit.
+    ReadOnly(&'static str),
+}
+
+/// Record writes, outbox messages and audit events that are committed in a single
+/// SurrealDB transaction: either all of them persist or none does.
+///
+/// Balances are not written here; they belong to the ledger of the `transfer` crate.
+/// Trades are settled there by `Ledger::post_trade`, which moves the four balances and
+/// stores the trade in one transaction that aborts with [`INSUFFICIENT_BALANCE`], so
+/// they persist together or not at all.
+#[derive(Debug, Clone, Default)]
+pub struct UnitOfWork {
+    operations: Vec<Operation>,
+    events: Vec<AuditEvent>,
+}
+impl UnitOfWork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty() && self.events.is_empty()
+    }
+
+    /// Inserts `record`; the commit fails with `ReadOnly` for records of a read-only
+    /// table.
+    pub fn insert<M: Record>(mut self, record: &M) -> Self {
+        self.operations.push(match M::READ_ONLY {
+            true => Operation::ReadOnly(M::TABLE),
+            false => Operation::Insert {
+                table: M::TABLE,
+                id: record.id(),
+                content: to_content(record).map_err(|err| err.to_string()),
+            },
+        });
+        self
+    }
+
+    /// Replaces `record`; the commit fails with `NotFound` if it does not exist and, for
+    /// versioned records, with `Conflict` if it was changed since it was read.
+    pub fn update<M: Record>(mut self, record: &M) -> Self {
+        self.operations.push(match M::READ_ONLY {
+            true => Operation::ReadOnly(M::TABLE),
+            false => Operation::Update {
+                table: M::TABLE,
+                id: record.id(),
+                content: to_content(record).map_err(|err| err.to_string()),
+                version: record.version(),
+            },
+        });
+        self
+    }
+
+    /// Removes the record `id` of `M`; the commit fails with `NotFound` if it does not
+    /// exist.
+    pub fn delete<M: Record>(mut self, id: Uuid) -> Self {
+        self.operations.push(match M::READ_ONLY {
+            true => Operation::ReadOnly(M::TABLE),
+            false => Operation::Delete {
+                table: M::TABLE,
+                id,
+            },
+        });
+        self
+    }
+
+    /// Fails the commit with `Referenced(table)` if a record of `table` matches `filter`
+    /// when the transaction runs, so no reference can be added between the check and the
+    /// other operations.
+    pub fn unreferenced(mut self, table: &'static str, filter: &Filter) -> Self {
+        self.operations.push(Operation::Unreferenced {
+            table,
+            filter: filter.clone(),
+        });
+        self
+    }
+
+    /// Queues `event` in the outbox, to be published on `channel` by an
+    /// [`OutboxRelay`](crate::OutboxRelay) once the transaction has committed.
+    pub fn publish<T: Serialize>(mut self, channel: &str, event: &T) -> Self {
+        self.operations.push(Operation::Publish {
+            id: Uuid::new_v4(),
+            channel: channel.to_string(),
+            payload: serde_json::to_value(event).map_err(|err| err.to_string()),
+        });
+        self
+    }
+
+    /// Appends `event` to the audit log as part of the transaction.
+    pub fn audit(mut self, event: AuditEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    fn render(&self) -> Result<(String, Vec<(String, Value)>), DatabaseError> {
+        let mut query = String::new();
+        let mut bindings = Vec::new();
+        for (index, operation) in self.operations.iter().enumerate() {
+            match operation {
+                Operation::Insert { table, id, content } => {
+                    let content = content.clone().map_err(DatabaseError::serialization)?;
+                    bindings.push((format!("table_{index}"), Value::from(*table)));
+                    bindings.push((format!("id_{index}"), Value::from(id.to_string())));
+                    bindings.push((format!("content_{index}"), content));
+                    query.push_str(&format!(
+                        "CREATE type::thing($table_{index}, $id_{index}) CONTENT $content_{index} RETURN NONE;\n"
+                    ));
+                }
+                Operation::Update {
+                    table,
+                    id,
+                    content,
+                    version,
+                } => {
+                    let mut content = content.clone().map_err(DatabaseError::serialization)?;
+                    bindings.push((format!("table_{index}"), Value::from(*table)));
+                    bindings.push((format!("id_{index}"), Value::from(id.to_string())));
+                    query.push_str(&format!(
+                        "IF !(SELECT VALUE id FROM type::thing($table_{index}, $id_{index})) \
+                         {{ THROW '{NOT_FOUND}' }};\n"
+                    ));
+                    let condition = match version {
+                        Some(version) => {
+                            if let Some(object) = content.as_object_mut() {
+                                object.insert("version".to_string(), Value::from(version + 1));
+                            }
+                            bindings.push((format!("version_{index}"), Value::from(*version)));
+                            format!("version = $version_{index}")
+                        }
+                        None => "id != NONE".to_string(),
+                    };
+                    bindings.push((format!("content_{index}"), content));
+                    query.push_str(&format!(
+                        "LET $updated_{index} = (UPDATE type::thing($table_{index}, $id_{index}) \
+                         CONTENT $content_{index} WHERE {condition} RETURN AFTER);\n\
+                         IF array::len($updated_{index}) = 0 {{ THROW '{CONFLICT}' }};\n"
+                    ));
+                }
+                Operation::Delete { table, id } => {
+                    bindings.push((format!("table_{index}"), Value::from(*table)));
+                    bindings.push((format!("id_{index}"), Value::from(id.to_string())));
+                    query.push_str(&format!(
+                        "LET $deleted_{index} = (DELETE type::thing($table_{index}, $id_{index}) RETURN BEFORE);\n\
+                         IF array::len($deleted_{index}) = 0 {{ THROW '{NOT_FOUND}' }};\n"
+                    ));
+                }
+                Operation::Publish {
+                    id,
+                    channel,
+                    payload,
+                } => {
+                    let payload = payload.clone().map_err(DatabaseError::serialization)?;
+                    bindings.push((format!("table_{index}"), Value::from(OUTBOX_TABLE)));
+                    bindings.push((format!("id_{index}"), Value::from(id.to_string())));
+                    bindings.push((format!("channel_{index}"), Value::from(channel.as_str())));
+                    bindings.push((format!("payload_{index}"), payload));
+                    query.push_str(&format!(
+                        "CREATE type::thing($table_{index}, $id_{index}) SET channel = $channel_{index}, \
+                         payload = $payload_{index}, created_at = time::now(), attempts = 0 RETURN NONE;\n"
+                    ));
+                }
+                Operation::Unreferenced { table, filter } => {
+                    let (clauses, conditions) = filter.render_prefixed(
+                        &format!("filter_{index}"),
+                        "reference checks cannot be ordered or bounded",
+                    )?;
+                    bindings.push((format!("table_{index}"), Value::from(*table)));
+                    bindings.extend(conditions);
+                    query.push_str(&format!(
+                        "IF (SELECT VALUE id FROM type::table($table_{index}){clauses} LIMIT 1) \
+                         {{ THROW '{REFERENCED}{table}' }};\n"
+                    ));
+                }
+                Operation::ReadOnly(table) => return Err(DatabaseError::ReadOnly(table)),
+            }
+        }
+        Ok((query, bindings))
+    }
+
+    /// Commits all operations atomically, retrying on transaction conflicts.
+    pub async fn commit<C: Connection>(
+        &self,
+        db: &Surreal<C>,
+        retry: &RetryPolicy,
+    ) -> Result<(), DatabaseError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let (query, bindings) = self.render()?;
+        AuditLog::new(db.clone())
+            .with_retry_policy(retry.clone())
+            .commit(&query, &bindings, &self.events)
+            .await
+            .map_err(|err| self.referenced(err))?;
+        Ok(())
+    }
+
+    /// Maps the [`REFERENCED`] error thrown by a reference check to `Referenced`.
+    fn referenced(&self, err: DatabaseError) -> DatabaseError {
+        let DatabaseError::Surrealdb(cause) = &err else {
+            return err;
+        };
+        let surrealdb::Error::Db(surrealdb::error::Db::Thrown(message)) = cause.as_ref() else {
+            return err;
+        };
+        let table = self
+            .operations
+            .iter()
+            .find_map(|operation| match operation {
+                Operation::Unreferenced { table, .. }
+                    if message.strip_prefix(REFERENCED) == Some(*table) =>
+                {
+                    Some(*table)
+                }
+                _ => None,
+            });
+        match table {
+            Some(table) => DatabaseError::Referenced(table),
+            None => err,
+        }
+    }
+}
+
+/// Picks the statement error that aborted the transaction; every other
+/// statement only reports that it was not executed.
+fn first_cause(mut errors: Vec<(usize, surrealdb::Error)>) -> Option<DatabaseError> {
+    errors.sort_by_key(|(index, _)| *index);
+    let position = errors
+        .iter()
+        .position(|(_, err)| {
+            !matches!(
+                err,
+                surrealdb::Error::Db(surrealdb::error::Db::QueryNotExecuted)
+            )
+        })
+        .unwrap_or(0);
+    if errors.is_empty() {
+        return None;
+    }
+    let err = errors.swap_remove(position).1;
+    Some(match &err {
+        surrealdb::Error::Db(surrealdb::error::Db::Thrown(message)) => match message.as_str() {
+            INSUFFICIENT_BALANCE => DatabaseError::InsufficientBalance,
+            CONFLICT => DatabaseError::Conflict,
+            NOT_FOUND => DatabaseError::NotFound,
+            _ => err.into(),
+        },
+        _ => err.into(),
+    })
+}
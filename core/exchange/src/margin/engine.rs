@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use models::Fraction;
+use num_traits::{One, Signed, Zero};
+use uuid::Uuid;
+
+use crate::perpetual::market::PerpetualMarket;
+
+use super::{
+    errors::MarginError,
+    models::{
+        ClosedPosition, Haircut, InsuranceFund, Liquidation, MarginAccount, MarginRequirements,
+        MarginSummary,
+    },
+    traits::LiquidationVenue,
+};
+
+/// Computes margin requirements of accounts and closes the ones that fall below
+/// maintenance margin.
+#[derive(Debug, Clone, Default)]
+pub struct LiquidationEngine {
+    requirements: HashMap<Uuid, MarginRequirements>,
+    insurance_fund: InsuranceFund,
+}
+impl LiquidationEngine {
+    pub fn new(insurance_fund: InsuranceFund) -> Self {
+        Self {
+            requirements: HashMap::new(),
+            insurance_fund,
+        }
+    }
+
+    pub fn insurance_fund(&self) -> &InsuranceFund {
+        &self.insurance_fund
+    }
+
+    pub fn insurance_fund_mut(&mut self) -> &mut InsuranceFund {
+        &mut self.insurance_fund
+    }
+
+    pub fn set_requirements(&mut self, contract_id: Uuid, requirements: MarginRequirements) {
+        self.requirements.insert(contract_id, requirements);
+    }
+
+    pub fn requirements(&self, contract_id: &Uuid) -> Result<&MarginRequirements, MarginError> {
+        self.requirements
+            .get(contract_id)
+            .ok_or(MarginError::UnknownMarket)
+    }
+
+    pub fn summary(
+        &self,
+        account: &MarginAccount,
+        markets: &HashMap<Uuid, PerpetualMarket>,
+        mark_prices: &HashMap<Uuid, Fraction>,
+    ) -> Result<MarginSummary, MarginError> {
+        let mut summary = MarginSummary {
+            equity: account.collateral.clone(),
+            initial_margin: Fraction::zero(),
+            maintenance_margin: Fraction::zero(),
+        };
+        for (contract_id, market) in markets {
+            let Some(position) = market.position(&account.user_id) else {
+                continue;
+            };
+            let mark_price = mark_prices
+                .get(contract_id)
+                .ok_or(MarginError::MissingMarkPrice)?;
+            summary.equity += position.total_pnl(mark_price);
+            if position.is_flat() {
+                continue;
+            }
+            let requirements = self.requirements(contract_id)?;
+            let notional = position.notional(mark_price);
+            summary.initial_margin += notional.clone() * requirements.initial.clone();
+            summary.maintenance_margin += notional * requirements.maintenance.clone();
+        }
+        Ok(summary)
+    }
+
+    /// Fails with `InsufficientMargin` if the account does not cover initial margin,
+    /// e.g. after applying a hypothetical fill to a copy of the markets.
+    pub fn check_initial_margin(
+        &self,
+        account: &MarginAccount,
+        markets: &HashMap<Uuid, PerpetualMarket>,
+        mark_prices: &HashMap<Uuid, Fraction>,
+    ) -> Result<MarginSummary, MarginError> {
+        let summary = self.summary(account, markets, mark_prices)?;
+        if summary.free_margin().is_negative() {
+            return Err(MarginError::InsufficientMargin);
+        }
+        Ok(summary)
+    }
+
+    /// Liquidates the account if its equity dropped below maintenance margin.
+    ///
+    /// Every open position is closed through `venue`, both sides of each fill are
+    /// applied to the market and the resulting PnL is settled into collateral. Negative
+    /// equity left is handed over to the insurance fund, and what it cannot cover is
+    /// socialised over the other positions' unsettled profit at `mark_prices`.
+    ///
+    /// A position the venue fails to close is reported in `failed` rather than aborting
+    /// the others, whose closes have already executed; the deficit is then left in
+    /// collateral until a later call closes it. Returns `None` if the account is
+    /// sufficiently margined.
+    pub fn liquidate<V: LiquidationVenue>(
+        &mut self,
+        account: &mut MarginAccount,
+        markets: &mut HashMap<Uuid, PerpetualMarket>,
+        mark_prices: &HashMap<Uuid, Fraction>,
+        venue: &mut V,
+    ) -> Result<Option<Liquidation>, MarginError> {
+        if !self
+            .summary(account, markets, mark_prices)?
+            .is_liquidatable()
+        {
+            return Ok(None);
+        }
+
+        let user_id = account.user_id;
+        let mut liquidation = Liquidation {
+            user_id,
+            closed: Vec::new(),
+            failed: Vec::new(),
+            deficit: Fraction::zero(),
+            haircuts: Vec::new(),
+            uncovered: Fraction::zero(),
+        };
+        for (contract_id, market) in markets.iter_mut() {
+            let Some(position) = market.position(&user_id) else {
+                continue;
+            };
+            if !position.is_flat() {
+                match close(*contract_id, market, user_id, venue) {
+                    Ok(closed) => liquidation.closed.push(closed),
+                    Err(err) => liquidation.failed.push((*contract_id, err)),
+                }
+            }
+            if let Some(position) = market.position_mut(&user_id) {
+                account.collateral += position.take_settled_pnl();
+            }
+        }
+
+        if liquidation.is_complete() && account.collateral.is_negative() {
+            liquidation.deficit = -account.collateral.clone();
+            let uncovered = self.insurance_fund.absorb(liquidation.deficit.clone());
+            account.collateral = Fraction::zero();
+            if uncovered.is_positive() {
+                liquidation.haircuts = socialize(user_id, markets, mark_prices, &uncovered);
+                liquidation.uncovered = liquidation
+                    .haircuts
+                    .iter()
+                    .fold(uncovered, |acc, haircut| acc - haircut.amount.clone());
+            }
+        }
+        Ok(Some(liquidation))
+    }
+}
+
+/// Closes the position of `user_id` through `venue`, applying each fill to both the
+/// user and its counterparty. Fills are checked before any is applied, so the market
+/// is left untouched on error.
+fn close<V: LiquidationVenue>(
+    contract_id: Uuid,
+    market: &mut PerpetualMarket,
+    user_id: Uuid,
+    venue: &mut V,
+) -> Result<ClosedPosition, MarginError> {
+    let size = market
+        .position(&user_id)
+        .map_or_else(Fraction::zero, |position| -position.size.clone());
+    let fills = venue.close(contract_id, user_id, &size)?;
+    let filled = fills
+        .iter()
+        .fold(Fraction::zero(), |acc, fill| acc + fill.size.clone());
+    let valid = fills.iter().all(|fill| {
+        fill.counterparty_id != user_id
+            && fill.size.signum() == size.signum()
+            && fill.price.is_positive()
+    });
+    if !valid || filled != size {
+        return Err(MarginError::InvalidFill);
+    }
+
+    let mut realized_pnl = Fraction::zero();
+    for fill in &fills {
+        realized_pnl += market.apply_fill(user_id, fill.size.clone(), fill.price.clone())?;
+        market.apply_fill(fill.counterparty_id, -fill.size.clone(), fill.price.clone())?;
+    }
+    Ok(ClosedPosition {
+        contract_id,
+        size,
+        fills,
+        realized_pnl,
+    })
+}
+
+/// Takes `amount` from the unsettled profit of every other position, pro rata and at
+/// most all of it, and returns the haircuts booked against their realized PnL.
+/// Positions in markets without a mark price are left alone.
+fn socialize(
+    user_id: Uuid,
+    markets: &mut HashMap<Uuid, PerpetualMarket>,
+    mark_prices: &HashMap<Uuid, Fraction>,
+    amount: &Fraction,
+) -> Vec<Haircut> {
+    let mut profits = Vec::new();
+    for (contract_id, market) in markets.iter() {
+        let Some(mark_price) = mark_prices.get(contract_id) else {
+            continue;
+        };
+        for position in market.positions() {
+            let pnl = position.total_pnl(mark_price);
+            if position.user_id != user_id && pnl.is_positive() {
+                profits.push((*contract_id, position.user_id, pnl));
+            }
+        }
+    }
+    let total = profits
+        .iter()
+        .fold(Fraction::zero(), |acc, (_, _, pnl)| acc + pnl.clone());
+    if total.is_zero() {
+        return Vec::new();
+    }
+    let ratio = std::cmp::min(Fraction::one(), amount.clone() / total);
+
+    let mut haircuts = Vec::new();
+    for (contract_id, owner, pnl) in profits {
+        let amount = pnl * ratio.clone();
+        if let Some(position) = markets
+            .get_mut(&contract_id)
+            .and_then(|market| market.position_mut(&owner))
+        {
+            position.realized_pnl -= amount.clone();
+        }
+        haircuts.push(Haircut {
+            user_id: owner,
+            contract_id,
+            amount,
+        });
+    }
+    haircuts
+}
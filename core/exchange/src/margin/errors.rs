@@ -0,0 +1,22 @@
+use crate::perpetual::errors::PerpetualError;
+
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum MarginError {
+    #[error("no margin requirements for market")]
+    UnknownMarket,
+
+    #[error("no mark price for market")]
+    MissingMarkPrice,
+
+    #[error("insufficient margin")]
+    InsufficientMargin,
+
+    #[error("liquidation venue error")]
+    Venue(String),
+
+    #[error("venue fills do not close the position")]
+    InvalidFill,
+
+    #[error("perpetual error")]
+    Perpetual(#[from] PerpetualError),
+}
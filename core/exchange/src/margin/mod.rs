@@ -0,0 +1,7 @@
+pub mod engine;
+pub mod errors;
+pub mod models;
+pub mod traits;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,132 @@
+use models::{BalanceRaw, Fraction};
+use num_traits::{Signed, ToPrimitive, Zero};
+use uuid::Uuid;
+
+use super::errors::MarginError;
+
+/// Margin ratios of a market, expressed as fractions of position notional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginRequirements {
+    /// Required to open or increase a position.
+    pub initial: Fraction,
+    /// Below this the account gets liquidated.
+    pub maintenance: Fraction,
+}
+
+/// Collateral a user posts for leveraged trading, held in a single quote asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginAccount {
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub collateral: Fraction,
+}
+impl MarginAccount {
+    pub fn from_balance(balance: &BalanceRaw) -> Self {
+        Self {
+            user_id: balance.user_id,
+            asset_id: balance.asset_id,
+            collateral: Fraction::from(balance.value),
+        }
+    }
+
+    /// Collateral rounded down to the integer value stored in `BalanceRaw`.
+    pub fn balance_value(&self) -> Option<i64> {
+        self.collateral.floor().to_integer().to_i64()
+    }
+}
+
+/// Point-in-time margin state of an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginSummary {
+    /// Collateral plus realized, unrealized and funding PnL of all positions.
+    pub equity: Fraction,
+    pub initial_margin: Fraction,
+    pub maintenance_margin: Fraction,
+}
+impl MarginSummary {
+    pub fn is_liquidatable(&self) -> bool {
+        self.equity < self.maintenance_margin
+    }
+
+    pub fn free_margin(&self) -> Fraction {
+        self.equity.clone() - self.initial_margin.clone()
+    }
+}
+
+/// Tops up accounts left with negative equity after liquidation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InsuranceFund {
+    pub balance: Fraction,
+}
+impl InsuranceFund {
+    pub fn new(balance: Fraction) -> Self {
+        Self { balance }
+    }
+
+    pub fn deposit(&mut self, amount: Fraction) {
+        self.balance += amount;
+    }
+
+    /// Covers as much of `deficit` as the fund holds and returns the part left uncovered.
+    pub fn absorb(&mut self, deficit: Fraction) -> Fraction {
+        let covered = std::cmp::min(
+            deficit.clone(),
+            std::cmp::max(self.balance.clone(), Fraction::zero()),
+        );
+        self.balance -= covered.clone();
+        deficit - covered
+    }
+}
+
+/// One execution of a closing order, matched against `counterparty_id`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueFill {
+    pub counterparty_id: Uuid,
+    /// Signed size from the side of the liquidated user; the counterparty takes the
+    /// opposite.
+    pub size: Fraction,
+    pub price: Fraction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedPosition {
+    pub contract_id: Uuid,
+    pub size: Fraction,
+    pub fills: Vec<VenueFill>,
+    pub realized_pnl: Fraction,
+}
+
+/// Unsettled profit taken from a position to cover a deficit the insurance fund could
+/// not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Haircut {
+    pub user_id: Uuid,
+    pub contract_id: Uuid,
+    pub amount: Fraction,
+}
+
+/// Result of liquidating one account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Liquidation {
+    pub user_id: Uuid,
+    pub closed: Vec<ClosedPosition>,
+    /// Markets whose position could not be closed. Their positions stay open and the
+    /// deficit is only settled once a later liquidation closes them.
+    pub failed: Vec<(Uuid, MarginError)>,
+    /// Negative equity left after closing, taken over by the insurance fund.
+    pub deficit: Fraction,
+    /// Part of the deficit beyond the insurance fund, socialised pro rata over the
+    /// unsettled profit of other positions.
+    pub haircuts: Vec<Haircut>,
+    /// Part of the deficit neither the insurance fund nor haircuts could cover.
+    pub uncovered: Fraction,
+}
+impl Liquidation {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub fn is_fully_covered(&self) -> bool {
+        !self.uncovered.is_positive()
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use models::{BalanceRaw, Fraction};
+use num_traits::Zero;
+use uuid::Uuid;
+
+use crate::perpetual::{market::PerpetualMarket, models::PerpetualContract};
+
+use super::{
+    engine::LiquidationEngine,
+    errors::MarginError,
+    models::{Haircut, InsuranceFund, MarginAccount, MarginRequirements, VenueFill},
+    traits::LiquidationVenue,
+};
+
+/// Fills every closing order at one price against one counterparty, except in
+/// `halted` markets.
+struct FixedPriceVenue {
+    price: Fraction,
+    counterparty_id: Uuid,
+    halted: Option<Uuid>,
+}
+impl FixedPriceVenue {
+    fn new(price: &str, counterparty_id: Uuid) -> Self {
+        Self {
+            price: fraction(price),
+            counterparty_id,
+            halted: None,
+        }
+    }
+}
+impl LiquidationVenue for FixedPriceVenue {
+    fn close(
+        &mut self,
+        contract_id: Uuid,
+        _: Uuid,
+        size: &Fraction,
+    ) -> Result<Vec<VenueFill>, MarginError> {
+        if self.halted == Some(contract_id) {
+            return Err(MarginError::Venue("halted".to_string()));
+        }
+        Ok(vec![VenueFill {
+            counterparty_id: self.counterparty_id,
+            size: size.clone(),
+            price: self.price.clone(),
+        }])
+    }
+}
+
+fn fraction(value: &str) -> Fraction {
+    Fraction::from_str_numeric(value).unwrap()
+}
+
+fn account(value: i64) -> MarginAccount {
+    MarginAccount::from_balance(&BalanceRaw {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        asset_id: Uuid::new_v4(),
+        value,
+        version: 0,
+    })
+}
+
+struct Setup {
+    engine: LiquidationEngine,
+    markets: HashMap<Uuid, PerpetualMarket>,
+    contract_id: Uuid,
+    long: MarginAccount,
+    short: MarginAccount,
+}
+
+/// Market where `long` holds 10 contracts against `short`, both entered at 100.
+fn market(long: &MarginAccount, short: &MarginAccount) -> PerpetualMarket {
+    let contract = PerpetualContract {
+        id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        quote_asset_id: Uuid::new_v4(),
+        funding_interval: Duration::hours(8),
+        interest_rate: Fraction::zero(),
+        max_funding_rate: fraction("0.0075"),
+    };
+    let mut market = PerpetualMarket::new(contract, Utc::now()).unwrap();
+    market
+        .apply_fill(long.user_id, fraction("10"), fraction("100"))
+        .unwrap();
+    market
+        .apply_fill(short.user_id, -fraction("10"), fraction("100"))
+        .unwrap();
+    market
+}
+
+fn engine(insurance_fund: &str, markets: &HashMap<Uuid, PerpetualMarket>) -> LiquidationEngine {
+    let mut engine = LiquidationEngine::new(InsuranceFund::new(fraction(insurance_fund)));
+    for contract_id in markets.keys() {
+        engine.set_requirements(
+            *contract_id,
+            MarginRequirements {
+                initial: fraction("0.1"),
+                maintenance: fraction("0.05"),
+            },
+        );
+    }
+    engine
+}
+
+fn setup(insurance_fund: &str) -> Setup {
+    let (long, short) = (account(100), account(1000));
+    let market = market(&long, &short);
+    let contract_id = market.contract().id;
+    let markets = HashMap::from([(contract_id, market)]);
+    Setup {
+        engine: engine(insurance_fund, &markets),
+        markets,
+        contract_id,
+        long,
+        short,
+    }
+}
+
+#[test]
+fn equity_includes_unrealized_pnl() {
+    let Setup {
+        engine,
+        markets,
+        contract_id,
+        long,
+        short,
+    } = setup("0");
+    let marks = HashMap::from([(contract_id, fraction("95"))]);
+
+    let summary = engine.summary(&long, &markets, &marks).unwrap();
+    assert_eq!(summary.equity, fraction("50"));
+    assert_eq!(summary.initial_margin, fraction("95"));
+    assert_eq!(summary.maintenance_margin, fraction("47.5"));
+    assert_eq!(
+        engine.check_initial_margin(&long, &markets, &marks),
+        Err(MarginError::InsufficientMargin)
+    );
+    assert!(engine
+        .check_initial_margin(&short, &markets, &marks)
+        .is_ok());
+}
+
+#[test]
+fn healthy_account_is_not_liquidated() {
+    let Setup {
+        mut engine,
+        mut markets,
+        contract_id,
+        mut short,
+        ..
+    } = setup("0");
+    let marks = HashMap::from([(contract_id, fraction("91"))]);
+    let mut venue = FixedPriceVenue::new("89", Uuid::new_v4());
+    assert_eq!(
+        engine
+            .liquidate(&mut short, &mut markets, &marks, &mut venue)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn deficit_is_taken_over_by_insurance_fund() {
+    let Setup {
+        mut engine,
+        mut markets,
+        contract_id,
+        mut long,
+        short,
+    } = setup("10");
+    let marks = HashMap::from([(contract_id, fraction("91"))]);
+    let maker = Uuid::new_v4();
+    let mut venue = FixedPriceVenue::new("89", maker);
+
+    let liquidation = engine
+        .liquidate(&mut long, &mut markets, &marks, &mut venue)
+        .unwrap()
+        .unwrap();
+    assert_eq!(liquidation.closed.len(), 1);
+    assert_eq!(liquidation.closed[0].realized_pnl, -fraction("110"));
+    assert_eq!(liquidation.deficit, fraction("10"));
+    assert!(liquidation.haircuts.is_empty());
+    assert!(liquidation.is_complete() && liquidation.is_fully_covered());
+    assert!(engine.insurance_fund().balance.is_zero());
+    assert!(long.collateral.is_zero());
+
+    let market = &markets[&contract_id];
+    assert!(market.position(&long.user_id).unwrap().is_flat());
+    assert_eq!(market.position(&maker).unwrap().size, fraction("10"));
+    assert_eq!(
+        market.position(&short.user_id).unwrap().size,
+        -fraction("10")
+    );
+    assert_eq!(market.open_interest(), fraction("10"));
+}
+
+#[test]
+fn deficit_beyond_the_insurance_fund_is_socialised() {
+    let Setup {
+        mut engine,
+        mut markets,
+        contract_id,
+        mut long,
+        short,
+    } = setup("5");
+    let marks = HashMap::from([(contract_id, fraction("91"))]);
+    let mut venue = FixedPriceVenue::new("89", short.user_id);
+
+    let liquidation = engine
+        .liquidate(&mut long, &mut markets, &marks, &mut venue)
+        .unwrap()
+        .unwrap();
+    assert_eq!(liquidation.deficit, fraction("10"));
+    assert_eq!(
+        liquidation.haircuts,
+        [Haircut {
+            user_id: short.user_id,
+            contract_id,
+            amount: fraction("5"),
+        }]
+    );
+    assert!(liquidation.is_fully_covered());
+
+    let position = markets[&contract_id].position(&short.user_id).unwrap();
+    assert!(position.is_flat());
+    assert_eq!(position.realized_pnl, fraction("105"));
+    assert!(markets[&contract_id].open_interest().is_zero());
+}
+
+#[test]
+fn failed_closes_are_reported_without_undoing_the_others() {
+    let (mut long, short) = (account(100), account(1000));
+    let (first, second) = (market(&long, &short), market(&long, &short));
+    let (first_id, second_id) = (first.contract().id, second.contract().id);
+    let mut markets = HashMap::from([(first_id, first), (second_id, second)]);
+    let mut engine = engine("1000", &markets);
+    let marks = HashMap::from([(first_id, fraction("95")), (second_id, fraction("95"))]);
+    let mut venue = FixedPriceVenue::new("94", short.user_id);
+    venue.halted = Some(second_id);
+
+    let liquidation = engine
+        .liquidate(&mut long, &mut markets, &marks, &mut venue)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (liquidation.closed.len(), liquidation.closed[0].contract_id),
+        (1, first_id)
+    );
+    assert_eq!(
+        liquidation.failed,
+        [(second_id, MarginError::Venue("halted".to_string()))]
+    );
+    assert!(!liquidation.is_complete());
+    assert!(liquidation.deficit.is_zero());
+    assert_eq!(long.collateral, fraction("40"));
+    assert!(markets[&first_id].open_interest().is_zero());
+    assert_eq!(markets[&second_id].open_interest(), fraction("10"));
+    assert_eq!(engine.insurance_fund().balance, fraction("1000"));
+}
@@ -0,0 +1,16 @@
+use models::Fraction;
+use uuid::Uuid;
+
+use super::{errors::MarginError, models::VenueFill};
+
+/// Where the liquidation engine sends the orders that close under-margined positions.
+pub trait LiquidationVenue {
+    /// Executes a closing order of signed `size` for `user_id` in `contract_id`
+    /// and returns its executions, which must add up to `size`.
+    fn close(
+        &mut self,
+        contract_id: Uuid,
+        user_id: Uuid,
+        size: &Fraction,
+    ) -> Result<Vec<VenueFill>, MarginError>;
+}
@@ -0,0 +1,14 @@
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum PerpetualError {
+    #[error("price index has no weighted components")]
+    EmptyIndex,
+
+    #[error("price must be positive")]
+    InvalidPrice,
+
+    #[error("fill size must not be zero")]
+    ZeroSize,
+
+    #[error("funding interval must be positive")]
+    InvalidFundingInterval,
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use models::Fraction;
+use num_traits::{One, Signed, Zero};
+use uuid::Uuid;
+
+use super::{
+    errors::PerpetualError,
+    models::{FundingPayment, FundingSettlement, PerpetualContract, Position},
+};
+
+/// Book-keeping of a single perpetual contract: user positions and the premium
+/// samples collected during the current funding interval.
+#[derive(Debug, Clone)]
+pub struct PerpetualMarket {
+    contract: PerpetualContract,
+    positions: HashMap<Uuid, Position>,
+    premium_samples: Vec<Fraction>,
+    next_funding_time: DateTime<Utc>,
+}
+impl PerpetualMarket {
+    pub fn new(contract: PerpetualContract, start: DateTime<Utc>) -> Result<Self, PerpetualError> {
+        if contract.funding_interval <= chrono::Duration::zero() {
+            return Err(PerpetualError::InvalidFundingInterval);
+        }
+        let next_funding_time = start + contract.funding_interval;
+        Ok(Self {
+            contract,
+            positions: HashMap::new(),
+            premium_samples: Vec::new(),
+            next_funding_time,
+        })
+    }
+
+    pub fn contract(&self) -> &PerpetualContract {
+        &self.contract
+    }
+
+    pub fn next_funding_time(&self) -> DateTime<Utc> {
+        self.next_funding_time
+    }
+
+    pub fn position(&self, user_id: &Uuid) -> Option<&Position> {
+        self.positions.get(user_id)
+    }
+
+    pub fn position_mut(&mut self, user_id: &Uuid) -> Option<&mut Position> {
+        self.positions.get_mut(user_id)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions.values()
+    }
+
+    /// Total size of all long positions, equal to the total size of all shorts
+    /// as long as every fill has a counterparty in the same market.
+    pub fn open_interest(&self) -> Fraction {
+        self.positions
+            .values()
+            .filter(|position| position.is_long())
+            .fold(Fraction::zero(), |acc, position| {
+                acc + position.size.clone()
+            })
+    }
+
+    /// Applies a signed fill for `user_id` and returns the PnL it realized.
+    pub fn apply_fill(
+        &mut self,
+        user_id: Uuid,
+        size: Fraction,
+        price: Fraction,
+    ) -> Result<Fraction, PerpetualError> {
+        let contract_id = self.contract.id;
+        self.positions
+            .entry(user_id)
+            .or_insert_with(|| Position::new(user_id, contract_id))
+            .apply_fill(size, price)
+    }
+
+    /// Records how far the traded (fair) price deviates from the index.
+    pub fn record_premium(
+        &mut self,
+        index_price: &Fraction,
+        fair_price: &Fraction,
+    ) -> Result<(), PerpetualError> {
+        if !index_price.is_positive() || !fair_price.is_positive() {
+            return Err(PerpetualError::InvalidPrice);
+        }
+        self.premium_samples
+            .push((fair_price.clone() - index_price.clone()) / index_price.clone());
+        Ok(())
+    }
+
+    /// Average premium over the current funding interval.
+    pub fn premium(&self) -> Fraction {
+        if self.premium_samples.is_empty() {
+            return Fraction::zero();
+        }
+        let sum = self
+            .premium_samples
+            .iter()
+            .fold(Fraction::zero(), |acc, sample| acc + sample.clone());
+        sum / Fraction::from(self.premium_samples.len())
+    }
+
+    /// Mark price used for PnL and funding: the index adjusted by the average premium,
+    /// which keeps it close to the index and hard to move with a single trade.
+    pub fn mark_price(&self, index_price: &Fraction) -> Fraction {
+        index_price.clone() * (Fraction::one() + self.premium())
+    }
+
+    /// Funding rate of the current interval, capped at `max_funding_rate`.
+    pub fn funding_rate(&self) -> Fraction {
+        let cap = self.contract.max_funding_rate.abs();
+        let rate = self.premium() + self.contract.interest_rate.clone();
+        std::cmp::max(-cap.clone(), std::cmp::min(cap, rate))
+    }
+
+    /// Settles funding between longs and shorts for every interval that has elapsed.
+    ///
+    /// With a positive rate longs pay shorts, with a negative rate shorts pay longs.
+    /// Payments are booked into each position's `funding` and returned, one settlement
+    /// per interval, so they can be settled into balances. Premium samples belong to the
+    /// first due interval; intervals missed after it carry no premium and are charged
+    /// the interest rate at `index_price`. Returns nothing if funding is not due yet.
+    pub fn settle_funding(
+        &mut self,
+        now: DateTime<Utc>,
+        index_price: &Fraction,
+    ) -> Result<Vec<FundingSettlement>, PerpetualError> {
+        if now < self.next_funding_time {
+            return Ok(Vec::new());
+        }
+        if !index_price.is_positive() {
+            return Err(PerpetualError::InvalidPrice);
+        }
+
+        let mut settlements = Vec::new();
+        while self.next_funding_time <= now {
+            let rate = self.funding_rate();
+            let mark_price = self.mark_price(index_price);
+            let mut payments = Vec::new();
+            for position in self.positions.values_mut().filter(|p| !p.is_flat()) {
+                let amount = -(position.size.clone() * mark_price.clone() * rate.clone());
+                position.funding += amount.clone();
+                payments.push(FundingPayment {
+                    user_id: position.user_id,
+                    amount,
+                });
+            }
+            settlements.push(FundingSettlement {
+                contract_id: self.contract.id,
+                time: self.next_funding_time,
+                rate,
+                mark_price,
+                payments,
+            });
+            self.premium_samples.clear();
+            self.next_funding_time += self.contract.funding_interval;
+        }
+        Ok(settlements)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod errors;
+pub mod market;
+pub mod models;
+
+#[cfg(test)]
+mod tests;
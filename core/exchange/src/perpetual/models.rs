@@ -0,0 +1,178 @@
+use chrono::{DateTime, Duration, Utc};
+use models::Fraction;
+use num_traits::{Signed, Zero};
+use uuid::Uuid;
+
+use super::errors::PerpetualError;
+
+/// Static definition of a perpetual swap listed next to the spot markets.
+#[derive(Debug, Clone)]
+pub struct PerpetualContract {
+    pub id: Uuid,
+    pub base_asset_id: Uuid,
+    pub quote_asset_id: Uuid,
+    /// Time between two consecutive funding settlements.
+    pub funding_interval: Duration,
+    /// Interest component added to the premium when computing the funding rate.
+    pub interest_rate: Fraction,
+    /// Absolute cap applied to the funding rate of a single interval.
+    pub max_funding_rate: Fraction,
+}
+
+/// A single price feed contributing to the index price.
+#[derive(Debug, Clone)]
+pub struct IndexComponent {
+    pub source: String,
+    pub price: Fraction,
+    pub weight: Fraction,
+}
+
+/// Weighted average of external spot prices the contract is anchored to.
+#[derive(Debug, Clone, Default)]
+pub struct PriceIndex {
+    components: Vec<IndexComponent>,
+}
+impl PriceIndex {
+    pub fn new(components: Vec<IndexComponent>) -> Self {
+        Self { components }
+    }
+
+    pub fn components(&self) -> &[IndexComponent] {
+        &self.components
+    }
+
+    pub fn price(&self) -> Result<Fraction, PerpetualError> {
+        let mut weighted = Fraction::zero();
+        let mut total_weight = Fraction::zero();
+        for component in &self.components {
+            if !component.price.is_positive() {
+                return Err(PerpetualError::InvalidPrice);
+            }
+            if component.weight.is_positive() {
+                weighted += component.price.clone() * component.weight.clone();
+                total_weight += component.weight.clone();
+            }
+        }
+        if total_weight.is_zero() {
+            return Err(PerpetualError::EmptyIndex);
+        }
+        Ok(weighted / total_weight)
+    }
+}
+
+/// Open exposure of a user in one perpetual contract.
+///
+/// `size` is signed: positive for longs, negative for shorts. `realized_pnl` and
+/// `funding` accumulate until the owner settles them into a balance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub user_id: Uuid,
+    pub contract_id: Uuid,
+    pub size: Fraction,
+    pub entry_price: Fraction,
+    pub realized_pnl: Fraction,
+    pub funding: Fraction,
+}
+impl Position {
+    pub fn new(user_id: Uuid, contract_id: Uuid) -> Self {
+        Self {
+            user_id,
+            contract_id,
+            size: Fraction::zero(),
+            entry_price: Fraction::zero(),
+            realized_pnl: Fraction::zero(),
+            funding: Fraction::zero(),
+        }
+    }
+
+    pub fn is_long(&self) -> bool {
+        self.size.is_positive()
+    }
+
+    pub fn is_short(&self) -> bool {
+        self.size.is_negative()
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.size.is_zero()
+    }
+
+    /// Absolute value of the position at the given price.
+    pub fn notional(&self, price: &Fraction) -> Fraction {
+        self.size.abs() * price.clone()
+    }
+
+    pub fn unrealized_pnl(&self, mark_price: &Fraction) -> Fraction {
+        self.size.clone() * (mark_price.clone() - self.entry_price.clone())
+    }
+
+    /// Realized and unrealized PnL together with accumulated funding.
+    pub fn total_pnl(&self, mark_price: &Fraction) -> Fraction {
+        self.realized_pnl.clone() + self.funding.clone() + self.unrealized_pnl(mark_price)
+    }
+
+    /// Moves realized PnL and funding out of the position, returning their sum
+    /// so it can be booked into the owner's balance.
+    pub fn take_settled_pnl(&mut self) -> Fraction {
+        let settled = self.realized_pnl.clone() + self.funding.clone();
+        self.realized_pnl = Fraction::zero();
+        self.funding = Fraction::zero();
+        settled
+    }
+
+    /// Applies a signed fill to the position and returns the PnL it realized.
+    ///
+    /// Fills in the direction of the position average into the entry price, fills
+    /// against it realize PnL on the closed part and may flip the position, in which
+    /// case the remainder is opened at the fill price.
+    pub fn apply_fill(
+        &mut self,
+        size: Fraction,
+        price: Fraction,
+    ) -> Result<Fraction, PerpetualError> {
+        if size.is_zero() {
+            return Err(PerpetualError::ZeroSize);
+        }
+        if !price.is_positive() {
+            return Err(PerpetualError::InvalidPrice);
+        }
+
+        if self.size.is_zero() || self.size.signum() == size.signum() {
+            let current = self.size.abs();
+            let added = size.abs();
+            self.entry_price = (current.clone() * self.entry_price.clone() + added.clone() * price)
+                / (current + added);
+            self.size += size;
+            return Ok(Fraction::zero());
+        }
+
+        let direction = self.size.signum();
+        let closed = std::cmp::min(self.size.abs(), size.abs());
+        let realized = closed * (price.clone() - self.entry_price.clone()) * direction.clone();
+        self.realized_pnl += realized.clone();
+        self.size += size;
+        if self.size.is_zero() {
+            self.entry_price = Fraction::zero();
+        } else if self.size.signum() != direction {
+            self.entry_price = price;
+        }
+        Ok(realized)
+    }
+}
+
+/// Amount a single position paid (negative) or received (positive) at settlement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundingPayment {
+    pub user_id: Uuid,
+    pub amount: Fraction,
+}
+
+/// Outcome of one funding interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundingSettlement {
+    pub contract_id: Uuid,
+    pub time: DateTime<Utc>,
+    pub rate: Fraction,
+    pub mark_price: Fraction,
+    pub payments: Vec<FundingPayment>,
+}
@@ -0,0 +1,154 @@
+use chrono::{Duration, TimeZone, Utc};
+use models::Fraction;
+use num_traits::Zero;
+use uuid::Uuid;
+
+use super::{
+    errors::PerpetualError,
+    market::PerpetualMarket,
+    models::{IndexComponent, PerpetualContract, Position, PriceIndex},
+};
+
+fn fraction(value: &str) -> Fraction {
+    Fraction::from_str_numeric(value).unwrap()
+}
+
+fn contract() -> PerpetualContract {
+    PerpetualContract {
+        id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        quote_asset_id: Uuid::new_v4(),
+        funding_interval: Duration::hours(8),
+        interest_rate: fraction("0.0001"),
+        max_funding_rate: fraction("0.0075"),
+    }
+}
+
+#[test]
+fn index_price_is_weighted_average() {
+    let index = PriceIndex::new(vec![
+        IndexComponent {
+            source: "a".to_string(),
+            price: fraction("100"),
+            weight: fraction("3"),
+        },
+        IndexComponent {
+            source: "b".to_string(),
+            price: fraction("104"),
+            weight: fraction("1"),
+        },
+    ]);
+    assert_eq!(index.price().unwrap(), fraction("101"));
+    assert_eq!(
+        PriceIndex::default().price(),
+        Err(PerpetualError::EmptyIndex)
+    );
+}
+
+#[test]
+fn position_averages_entry_and_realizes_pnl() {
+    let mut position = Position::new(Uuid::new_v4(), Uuid::new_v4());
+    position.apply_fill(fraction("1"), fraction("100")).unwrap();
+    position.apply_fill(fraction("1"), fraction("110")).unwrap();
+    assert_eq!(position.entry_price, fraction("105"));
+    assert_eq!(position.unrealized_pnl(&fraction("115")), fraction("20"));
+
+    let realized = position
+        .apply_fill(-fraction("1"), fraction("120"))
+        .unwrap();
+    assert_eq!(realized, fraction("15"));
+    assert_eq!(position.size, fraction("1"));
+    assert_eq!(position.entry_price, fraction("105"));
+
+    // flip to a short of one
+    let realized = position.apply_fill(-fraction("2"), fraction("90")).unwrap();
+    assert_eq!(realized, -fraction("15"));
+    assert!(position.is_short());
+    assert_eq!(position.entry_price, fraction("90"));
+    assert_eq!(position.realized_pnl, Fraction::zero());
+}
+
+#[test]
+fn funding_is_exchanged_between_longs_and_shorts() {
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let mut market = PerpetualMarket::new(contract(), start).unwrap();
+    let (long, short) = (Uuid::new_v4(), Uuid::new_v4());
+    market
+        .apply_fill(long, fraction("2"), fraction("100"))
+        .unwrap();
+    market
+        .apply_fill(short, -fraction("2"), fraction("100"))
+        .unwrap();
+    assert_eq!(market.open_interest(), fraction("2"));
+
+    let index = fraction("100");
+    market.record_premium(&index, &fraction("100.1")).unwrap();
+    market.record_premium(&index, &fraction("100.3")).unwrap();
+    assert_eq!(market.mark_price(&index), fraction("100.2"));
+
+    assert!(market
+        .settle_funding(start + Duration::hours(1), &index)
+        .unwrap()
+        .is_empty());
+    let settlements = market
+        .settle_funding(start + Duration::hours(8), &index)
+        .unwrap();
+    assert_eq!(settlements.len(), 1);
+    let settlement = &settlements[0];
+    assert_eq!(settlement.rate, fraction("0.0021"));
+    let total = settlement
+        .payments
+        .iter()
+        .fold(Fraction::zero(), |acc, payment| {
+            acc + payment.amount.clone()
+        });
+    assert!(total.is_zero());
+    assert!(market.position(&long).unwrap().funding < Fraction::zero());
+    assert!(market.position(&short).unwrap().funding > Fraction::zero());
+    assert_eq!(market.next_funding_time(), start + Duration::hours(16));
+}
+
+#[test]
+fn missed_intervals_are_each_settled() {
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let mut market = PerpetualMarket::new(contract(), start).unwrap();
+    let (long, short) = (Uuid::new_v4(), Uuid::new_v4());
+    market
+        .apply_fill(long, fraction("1"), fraction("100"))
+        .unwrap();
+    market
+        .apply_fill(short, -fraction("1"), fraction("100"))
+        .unwrap();
+    let index = fraction("100");
+    market.record_premium(&index, &fraction("100.2")).unwrap();
+
+    let settlements = market
+        .settle_funding(start + Duration::hours(25), &index)
+        .unwrap();
+    assert_eq!(
+        settlements
+            .iter()
+            .map(|settlement| (settlement.time, settlement.rate.clone()))
+            .collect::<Vec<_>>(),
+        [
+            (start + Duration::hours(8), fraction("0.0021")),
+            (start + Duration::hours(16), fraction("0.0001")),
+            (start + Duration::hours(24), fraction("0.0001")),
+        ]
+    );
+    assert_eq!(
+        market.position(&long).unwrap().funding,
+        -fraction("0.23042")
+    );
+    assert_eq!(market.next_funding_time(), start + Duration::hours(32));
+}
+
+#[test]
+fn funding_rate_is_capped() {
+    let start = Utc::now();
+    let mut market = PerpetualMarket::new(contract(), start).unwrap();
+    market
+        .record_premium(&fraction("100"), &fraction("150"))
+        .unwrap();
+    assert_eq!(market.funding_rate(), fraction("0.0075"));
+}
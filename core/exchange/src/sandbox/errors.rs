@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SandboxError {
+    #[error("no market data for pair")]
+    NoMarketData,
+
+    #[error("insufficient virtual balance")]
+    InsufficientBalance,
+
+    #[error("amount must be positive")]
+    InvalidAmount,
+
+    #[error("quoted price must be positive")]
+    InvalidPrice,
+
+    #[error("slippage must be at least 0 and below 1")]
+    InvalidSlippage,
+}
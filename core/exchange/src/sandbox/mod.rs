@@ -0,0 +1,7 @@
+pub mod errors;
+pub mod models;
+pub mod simulator;
+pub mod traits;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use models::Fraction;
+use num_traits::{Signed, Zero};
+use uuid::Uuid;
+
+use super::errors::SandboxError;
+
+/// Best bid and ask of a live market at the time the sandbox order arrives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub bid: Fraction,
+    pub ask: Fraction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// Order placed with virtual funds. `size` is denominated in the base asset,
+/// orders without `limit_price` execute at the quoted price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxOrder {
+    pub user_id: Uuid,
+    pub base_asset_id: Uuid,
+    pub quote_asset_id: Uuid,
+    pub side: Side,
+    pub size: Fraction,
+    pub limit_price: Option<Fraction>,
+}
+
+/// Execution of a sandbox order against the simulated counterparty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxFill {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub base_asset_id: Uuid,
+    pub quote_asset_id: Uuid,
+    pub side: Side,
+    pub size: Fraction,
+    pub price: Fraction,
+    pub time: DateTime<Utc>,
+}
+
+/// Virtual balances keyed by user and asset, unrelated to `models::BalanceRaw`.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualBalances {
+    balances: HashMap<(Uuid, Uuid), Fraction>,
+}
+impl VirtualBalances {
+    pub fn get(&self, user_id: &Uuid, asset_id: &Uuid) -> Fraction {
+        self.balances
+            .get(&(*user_id, *asset_id))
+            .cloned()
+            .unwrap_or_else(Fraction::zero)
+    }
+
+    pub fn credit(
+        &mut self,
+        user_id: Uuid,
+        asset_id: Uuid,
+        amount: Fraction,
+    ) -> Result<(), SandboxError> {
+        if !amount.is_positive() {
+            return Err(SandboxError::InvalidAmount);
+        }
+        *self
+            .balances
+            .entry((user_id, asset_id))
+            .or_insert_with(Fraction::zero) += amount;
+        Ok(())
+    }
+
+    pub fn debit(
+        &mut self,
+        user_id: Uuid,
+        asset_id: Uuid,
+        amount: Fraction,
+    ) -> Result<(), SandboxError> {
+        if !amount.is_positive() {
+            return Err(SandboxError::InvalidAmount);
+        }
+        let balance = self
+            .balances
+            .get_mut(&(user_id, asset_id))
+            .filter(|balance| **balance >= amount)
+            .ok_or(SandboxError::InsufficientBalance)?;
+        *balance -= amount;
+        Ok(())
+    }
+
+    /// Drops all virtual funds of a user, e.g. when they restart their sandbox.
+    pub fn reset(&mut self, user_id: &Uuid) {
+        self.balances.retain(|(owner, _), _| owner != user_id);
+    }
+}
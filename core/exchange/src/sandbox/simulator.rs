@@ -0,0 +1,133 @@
+use chrono::Utc;
+use models::Fraction;
+use num_traits::{One, Signed, Zero};
+use uuid::Uuid;
+
+use super::{
+    errors::SandboxError,
+    models::{SandboxFill, SandboxOrder, Side, VirtualBalances},
+    traits::MarketDataSource,
+};
+
+/// Paper-trading venue: orders are filled by a simulated counterparty at live quotes
+/// and settled in virtual balances, so nothing touches real balances or trades.
+#[derive(Debug, Clone)]
+pub struct Sandbox<M: MarketDataSource> {
+    market_data: M,
+    balances: VirtualBalances,
+    fills: Vec<SandboxFill>,
+    /// Price penalty applied against the user, as a fraction of the quoted price.
+    slippage: Fraction,
+}
+impl<M: MarketDataSource> Sandbox<M> {
+    pub fn new(market_data: M) -> Self {
+        Self {
+            market_data,
+            balances: VirtualBalances::default(),
+            fills: Vec::new(),
+            slippage: Fraction::zero(),
+        }
+    }
+
+    /// Fails with `InvalidSlippage` unless `slippage` is in `[0, 1)`, as anything else
+    /// would fill at a zero or negative price.
+    pub fn with_slippage(mut self, slippage: Fraction) -> Result<Self, SandboxError> {
+        if slippage.is_negative() || slippage >= Fraction::one() {
+            return Err(SandboxError::InvalidSlippage);
+        }
+        self.slippage = slippage;
+        Ok(self)
+    }
+
+    pub fn balances(&self) -> &VirtualBalances {
+        &self.balances
+    }
+
+    /// Sandbox-local history of executions.
+    pub fn fills(&self) -> &[SandboxFill] {
+        &self.fills
+    }
+
+    /// Grants virtual funds to a user.
+    pub fn deposit(
+        &mut self,
+        user_id: Uuid,
+        asset_id: Uuid,
+        amount: Fraction,
+    ) -> Result<(), SandboxError> {
+        self.balances.credit(user_id, asset_id, amount)
+    }
+
+    pub fn reset(&mut self, user_id: &Uuid) {
+        self.balances.reset(user_id);
+        self.fills.retain(|fill| &fill.user_id != user_id);
+    }
+
+    /// Executes the order against the simulated counterparty.
+    ///
+    /// Returns `None` for limit orders that do not cross the current quote; the
+    /// sandbox keeps no resting orders. Fails with `InvalidPrice` when the quote is not
+    /// positive, before any balance is touched.
+    pub fn submit(&mut self, order: SandboxOrder) -> Result<Option<SandboxFill>, SandboxError> {
+        if !order.size.is_positive() {
+            return Err(SandboxError::InvalidAmount);
+        }
+        let quote = self
+            .market_data
+            .quote(&order.base_asset_id, &order.quote_asset_id)
+            .ok_or(SandboxError::NoMarketData)?;
+
+        let price = match order.side {
+            Side::Buy => quote.ask * (Fraction::one() + self.slippage.clone()),
+            Side::Sell => quote.bid * (Fraction::one() - self.slippage.clone()),
+        };
+        if !price.is_positive() {
+            return Err(SandboxError::InvalidPrice);
+        }
+        let crosses = match (&order.limit_price, order.side) {
+            (None, _) => true,
+            (Some(limit), Side::Buy) => limit >= &price,
+            (Some(limit), Side::Sell) => limit <= &price,
+        };
+        if !crosses {
+            return Ok(None);
+        }
+
+        let notional = order.size.clone() * price.clone();
+        let (paid_asset, paid, received_asset, received) = match order.side {
+            Side::Buy => (
+                order.quote_asset_id,
+                notional,
+                order.base_asset_id,
+                order.size.clone(),
+            ),
+            Side::Sell => (
+                order.base_asset_id,
+                order.size.clone(),
+                order.quote_asset_id,
+                notional,
+            ),
+        };
+        // Both legs are checked before the debit, so a failing credit cannot leave the
+        // user without the asset they paid.
+        if !paid.is_positive() || !received.is_positive() {
+            return Err(SandboxError::InvalidAmount);
+        }
+        self.balances.debit(order.user_id, paid_asset, paid)?;
+        self.balances
+            .credit(order.user_id, received_asset, received)?;
+
+        let fill = SandboxFill {
+            id: Uuid::new_v4(),
+            user_id: order.user_id,
+            base_asset_id: order.base_asset_id,
+            quote_asset_id: order.quote_asset_id,
+            side: order.side,
+            size: order.size,
+            price,
+            time: Utc::now(),
+        };
+        self.fills.push(fill.clone());
+        Ok(Some(fill))
+    }
+}
@@ -0,0 +1,141 @@
+use models::Fraction;
+use num_traits::Zero;
+use uuid::Uuid;
+
+use super::{
+    errors::SandboxError,
+    models::{Quote, SandboxOrder, Side},
+    simulator::Sandbox,
+    traits::MarketDataSource,
+};
+
+struct StaticMarket(Quote);
+impl MarketDataSource for StaticMarket {
+    fn quote(&self, _: &Uuid, _: &Uuid) -> Option<Quote> {
+        Some(self.0.clone())
+    }
+}
+
+fn fraction(value: &str) -> Fraction {
+    Fraction::from_str_numeric(value).unwrap()
+}
+
+fn sandbox() -> Sandbox<StaticMarket> {
+    Sandbox::new(StaticMarket(Quote {
+        bid: fraction("99"),
+        ask: fraction("101"),
+    }))
+}
+
+fn order(user_id: Uuid, side: Side, size: &str, limit_price: Option<&str>) -> SandboxOrder {
+    SandboxOrder {
+        user_id,
+        base_asset_id: Uuid::from_u128(1),
+        quote_asset_id: Uuid::from_u128(2),
+        side,
+        size: fraction(size),
+        limit_price: limit_price.map(fraction),
+    }
+}
+
+#[test]
+fn market_orders_fill_at_quote() {
+    let mut sandbox = sandbox();
+    let user_id = Uuid::new_v4();
+    let (base, quote) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    sandbox.deposit(user_id, quote, fraction("1000")).unwrap();
+
+    let fill = sandbox
+        .submit(order(user_id, Side::Buy, "2", None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(fill.price, fraction("101"));
+    assert_eq!(sandbox.balances().get(&user_id, &quote), fraction("798"));
+    assert_eq!(sandbox.balances().get(&user_id, &base), fraction("2"));
+
+    sandbox
+        .submit(order(user_id, Side::Sell, "1", None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(sandbox.balances().get(&user_id, &quote), fraction("897"));
+    assert_eq!(sandbox.fills().len(), 2);
+}
+
+#[test]
+fn limit_orders_fill_only_when_crossing() {
+    let mut sandbox = sandbox();
+    let user_id = Uuid::new_v4();
+    sandbox
+        .deposit(user_id, Uuid::from_u128(2), fraction("1000"))
+        .unwrap();
+
+    assert_eq!(
+        sandbox
+            .submit(order(user_id, Side::Buy, "1", Some("100")))
+            .unwrap(),
+        None
+    );
+    assert!(sandbox
+        .submit(order(user_id, Side::Buy, "1", Some("101")))
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn orders_are_limited_by_virtual_funds() {
+    let mut sandbox = sandbox();
+    let user_id = Uuid::new_v4();
+    sandbox
+        .deposit(user_id, Uuid::from_u128(2), fraction("100"))
+        .unwrap();
+
+    assert_eq!(
+        sandbox.submit(order(user_id, Side::Buy, "1", None)),
+        Err(SandboxError::InsufficientBalance)
+    );
+    sandbox.reset(&user_id);
+    assert!(sandbox
+        .balances()
+        .get(&user_id, &Uuid::from_u128(2))
+        .is_zero());
+}
+
+#[test]
+fn slippage_must_be_below_one() {
+    for slippage in [-fraction("0.01"), fraction("1"), fraction("1.5")] {
+        assert_eq!(
+            sandbox().with_slippage(slippage).err(),
+            Some(SandboxError::InvalidSlippage)
+        );
+    }
+
+    let mut sandbox = sandbox().with_slippage(fraction("0.01")).unwrap();
+    let user_id = Uuid::new_v4();
+    sandbox
+        .deposit(user_id, Uuid::from_u128(2), fraction("1000"))
+        .unwrap();
+    let fill = sandbox
+        .submit(order(user_id, Side::Buy, "1", None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(fill.price, fraction("102.01"));
+}
+
+#[test]
+fn orders_against_a_zero_quote_are_rejected() {
+    let mut sandbox = Sandbox::new(StaticMarket(Quote {
+        bid: Fraction::zero(),
+        ask: fraction("101"),
+    }));
+    let user_id = Uuid::new_v4();
+    let (base, quote) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    sandbox.deposit(user_id, base, fraction("1")).unwrap();
+
+    assert_eq!(
+        sandbox.submit(order(user_id, Side::Sell, "1", None)),
+        Err(SandboxError::InvalidPrice)
+    );
+    assert_eq!(sandbox.balances().get(&user_id, &base), fraction("1"));
+    assert!(sandbox.balances().get(&user_id, &quote).is_zero());
+    assert!(sandbox.fills().is_empty());
+}
@@ -0,0 +1,8 @@
+use uuid::Uuid;
+
+use super::models::Quote;
+
+/// Source of real market prices the sandbox trades against.
+pub trait MarketDataSource {
+    fn quote(&self, base_asset_id: &Uuid, quote_asset_id: &Uuid) -> Option<Quote>;
+}
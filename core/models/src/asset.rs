@@ -1,11 +1,19 @@
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::network::Network;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AssetRaw {
     pub id: Uuid,
     pub name: String,
     pub symbol: String,
     pub precision: i64,
     pub network: Network,
+    /// Token contract on `network`, or `None` for the network's native coin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_address: Option<String>,
+    pub deposits_enabled: bool,
+    pub withdrawals_enabled: bool,
+    pub trading_enabled: bool,
 }
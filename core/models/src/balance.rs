@@ -1,8 +1,12 @@
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BalanceRaw {
     pub id: Uuid,
     pub user_id: Uuid,
     pub asset_id: Uuid,
     pub value: i64,
+    #[serde(default)]
+    pub version: u64,
 }
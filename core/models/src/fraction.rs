@@ -220,6 +220,12 @@ impl From<usize> for Fraction {
     }
 }
 
+impl From<i64> for Fraction {
+    fn from(value: i64) -> Self {
+        Self(BigRational::from_integer(value.into()))
+    }
+}
+
 impl From<BigInt> for Fraction {
     fn from(value: BigInt) -> Self {
         Self(BigRational::from(value))
@@ -332,9 +338,7 @@ impl<'de> Deserialize<'de> for Fraction {
                 }
                 let numer = numer.ok_or_else(|| de::Error::missing_field(FIELDS[0]))?;
                 let denom = denom.ok_or_else(|| de::Error::missing_field(FIELDS[1]))?;
-                Ok(Fraction(
-                    BigRational::try_from((numer, denom)).map_err(de::Error::custom)?,
-                ))
+                Ok(Fraction(BigRational::from((numer, denom))))
             }
         }
 
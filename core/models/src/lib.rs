@@ -1,6 +1,7 @@
 mod asset;
 mod balance;
 mod fraction;
+mod market;
 mod network;
 mod order;
 mod trade;
@@ -9,6 +10,7 @@ mod user;
 pub use asset::AssetRaw;
 pub use balance::BalanceRaw;
 pub use fraction::Fraction;
+pub use market::MarketRaw;
 pub use network::Network;
 pub use order::OrderRaw;
 pub use trade::TradeRaw;
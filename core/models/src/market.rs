@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketRaw {
+    pub id: Uuid,
+    pub base_asset_id: Uuid,
+    pub quote_asset_id: Uuid,
+    pub enabled: bool,
+}
@@ -1,5 +1,8 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderRaw {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -8,4 +11,7 @@ pub struct OrderRaw {
     pub quote_asset_id: Uuid,
     pub quote_asset_volume: i64,
     pub price: f64,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: u64,
 }
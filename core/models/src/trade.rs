@@ -1,9 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TradeRaw {
     pub id: Uuid,
     pub base_asset_id: Uuid,
     pub base_asset_volume: i64,
     pub quote_asset_id: Uuid,
     pub quote_asset_volume: i64,
+    pub created_at: DateTime<Utc>,
 }
@@ -1,5 +1,10 @@
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserRaw {
     pub id: Uuid,
+    /// Public name other users can address the user by, unique when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
 }
@@ -0,0 +1,43 @@
+use database::DatabaseError;
+
+use super::models::ApprovalStatus;
+use crate::{ledger::errors::LedgerError, withdrawal::errors::WithdrawalError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApprovalError {
+    #[error("approval request not found")]
+    NotFound,
+
+    #[error("approver may not decide on this withdrawal")]
+    Unauthorized,
+
+    #[error("approver has decided on this withdrawal already")]
+    AlreadyDecided,
+
+    #[error("approval request is {0}")]
+    Closed(ApprovalStatus),
+
+    #[error("approval request was modified concurrently")]
+    Conflict,
+
+    #[error(transparent)]
+    Withdrawal(WithdrawalError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<WithdrawalError> for ApprovalError {
+    fn from(err: WithdrawalError) -> Self {
+        match err {
+            WithdrawalError::Conflict => Self::Conflict,
+            err => Self::Withdrawal(err),
+        }
+    }
+}
+
+impl From<LedgerError> for ApprovalError {
+    fn from(err: LedgerError) -> Self {
+        WithdrawalError::from(err).into()
+    }
+}
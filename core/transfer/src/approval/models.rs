@@ -0,0 +1,197 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+use database::Record;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::withdrawal::models::Withdrawal;
+
+/// Hours an approval request stays open unless configured otherwise.
+pub const DEFAULT_APPROVAL_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApproverRole {
+    Operator,
+    Compliance,
+    Treasury,
+}
+impl fmt::Display for ApproverRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApproverRole::Operator => "operator",
+            ApproverRole::Compliance => "compliance",
+            ApproverRole::Treasury => "treasury",
+        })
+    }
+}
+
+/// An authenticated operator deciding on a withdrawal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approver {
+    pub id: Uuid,
+    pub role: ApproverRole,
+}
+
+/// Withdrawals of an asset above `threshold` need `required` distinct approvers holding
+/// one of `roles`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumRule {
+    pub threshold: i64,
+    pub required: u32,
+    pub roles: Vec<ApproverRole>,
+}
+impl QuorumRule {
+    pub fn new(
+        threshold: i64,
+        required: u32,
+        roles: impl IntoIterator<Item = ApproverRole>,
+    ) -> Self {
+        Self {
+            threshold,
+            required: required.max(1),
+            roles: roles.into_iter().collect(),
+        }
+    }
+}
+
+/// Quorum rules per asset; withdrawals of assets without a rule need no approval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalPolicy {
+    assets: HashMap<Uuid, QuorumRule>,
+    ttl: Duration,
+}
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            assets: HashMap::new(),
+            ttl: Duration::hours(DEFAULT_APPROVAL_TTL_HOURS),
+        }
+    }
+}
+impl ApprovalPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn asset(mut self, asset_id: Uuid, rule: QuorumRule) -> Self {
+        self.assets.insert(asset_id, rule);
+        self
+    }
+
+    /// Time after which a request that has not reached quorum expires.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn time_to_live(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The rule `withdrawal` falls under, if it needs approval.
+    pub fn rule(&self, withdrawal: &Withdrawal) -> Option<&QuorumRule> {
+        self.assets
+            .get(&withdrawal.asset_id)
+            .filter(|rule| withdrawal.amount > rule.threshold)
+    }
+
+    pub fn requires_quorum(&self, withdrawal: &Withdrawal) -> bool {
+        self.rule(withdrawal).is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Approve,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    pub approver_id: Uuid,
+    pub role: ApproverRole,
+    pub verdict: Verdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+}
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+            ApprovalStatus::Expired => "expired",
+        })
+    }
+}
+
+/// Decisions collected on a withdrawal under review, stored under the withdrawal's id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub amount: i64,
+    pub required: u32,
+    pub roles: Vec<ApproverRole>,
+    #[serde(default)]
+    pub decisions: Vec<Decision>,
+    pub status: ApprovalStatus,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: u64,
+}
+impl ApprovalRequest {
+    pub fn new(withdrawal: &Withdrawal, rule: &QuorumRule, ttl: Duration) -> Self {
+        let now = Utc::now();
+        Self {
+            id: withdrawal.id,
+            user_id: withdrawal.user_id,
+            asset_id: withdrawal.asset_id,
+            amount: withdrawal.amount,
+            required: rule.required,
+            roles: rule.roles.clone(),
+            decisions: Vec::new(),
+            status: ApprovalStatus::Pending,
+            expires_at: now + ttl,
+            created_at: now,
+            updated_at: now,
+            version: 0,
+        }
+    }
+
+    pub fn approvals(&self) -> usize {
+        self.decisions
+            .iter()
+            .filter(|decision| decision.verdict == Verdict::Approve)
+            .count()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+impl Record for ApprovalRequest {
+    const TABLE: &'static str = "withdrawal_approvals";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::Utc;
+use database::{
+    Actor, AuditCategory, AuditEvent, DatabaseError, Direction, Filter, Repository, RetryPolicy,
+    UnitOfWork,
+};
+use surrealdb::Connection;
+use uuid::Uuid;
+
+use super::{
+    errors::ApprovalError,
+    models::{ApprovalPolicy, ApprovalRequest, ApprovalStatus, Approver, Decision, Verdict},
+};
+use crate::{
+    ledger::journal::Statement,
+    withdrawal::{
+        errors::WithdrawalError,
+        models::{Withdrawal, WithdrawalStatus},
+        service::Withdrawals,
+    },
+};
+
+/// Collects the sign-off of a quorum of approvers on large withdrawals.
+///
+/// Submitting a withdrawal that needs a quorum moves it to `PendingReview` and opens an
+/// approval request. It is approved once enough distinct approvers agreed and fails as
+/// soon as one rejects it or the request expires. Each decision is committed together
+/// with the status change it causes and its entry in the audit log.
+#[derive(Debug)]
+pub struct Approvals<C: Connection> {
+    withdrawals: Withdrawals<C>,
+    requests: Repository<C, ApprovalRequest>,
+    policy: ApprovalPolicy,
+}
+impl<C: Connection> Clone for Approvals<C> {
+    fn clone(&self) -> Self {
+        Self {
+            withdrawals: self.withdrawals.clone(),
+            requests: self.requests.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+impl<C: Connection> Approvals<C> {
+    /// Decides on withdrawals of `withdrawals` under their approval policy.
+    pub fn new(withdrawals: Withdrawals<C>) -> Self {
+        Self {
+            requests: Repository::new(withdrawals.db().clone()),
+            policy: withdrawals.approval_policy().clone(),
+            withdrawals,
+        }
+    }
+
+    /// Sends a requested withdrawal to review if it needs a quorum, or approves it
+    /// right away otherwise, on behalf of `actor`.
+    pub async fn submit(&self, actor: Actor, id: &Uuid) -> Result<Withdrawal, ApprovalError> {
+        let withdrawal = self
+            .withdrawals
+            .get(id)
+            .await?
+            .ok_or(WithdrawalError::NotFound)?;
+        let Some(rule) = self.policy.rule(&withdrawal) else {
+            return Ok(self.withdrawals.approve(actor, id).await?);
+        };
+        let request = ApprovalRequest::new(&withdrawal, rule, self.policy.time_to_live());
+        Ok(self
+            .withdrawals
+            .advance_with(
+                actor,
+                id,
+                WithdrawalStatus::PendingReview,
+                |_| (),
+                vec![Statement::create(&request)?],
+            )
+            .await?)
+    }
+
+    pub async fn get(&self, id: &Uuid) -> Result<Option<ApprovalRequest>, ApprovalError> {
+        Ok(self.requests.get(id).await?)
+    }
+
+    /// Requests still waiting for a quorum, oldest first.
+    pub async fn pending(&self) -> Result<Vec<ApprovalRequest>, ApprovalError> {
+        Ok(self
+            .requests
+            .list(
+                &Filter::new()
+                    .eq("status", ApprovalStatus::Pending)
+                    .order_by("created_at", Direction::Asc),
+            )
+            .await?)
+    }
+
+    /// Adds the approval of `approver`, approving the withdrawal if quorum is reached.
+    pub async fn approve(
+        &self,
+        id: &Uuid,
+        approver: &Approver,
+    ) -> Result<ApprovalRequest, ApprovalError> {
+        self.decide(id, approver, Verdict::Approve, None).await
+    }
+
+    /// Rejects the withdrawal, failing it and releasing its funds.
+    pub async fn reject(
+        &self,
+        id: &Uuid,
+        approver: &Approver,
+        reason: &str,
+    ) -> Result<ApprovalRequest, ApprovalError> {
+        self.decide(id, approver, Verdict::Reject, Some(reason.to_string()))
+            .await
+    }
+
+    /// Fails every withdrawal whose approval request expired before reaching quorum.
+    pub async fn expire(&self) -> Result<Vec<ApprovalRequest>, ApprovalError> {
+        let mut expired = Vec::new();
+        for request in self.pending().await? {
+            if !request.is_expired() {
+                continue;
+            }
+            match self.close_expired(request).await {
+                Ok(request) => expired.push(request),
+                Err(ApprovalError::Conflict) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(expired)
+    }
+
+    async fn decide(
+        &self,
+        id: &Uuid,
+        approver: &Approver,
+        verdict: Verdict,
+        reason: Option<String>,
+    ) -> Result<ApprovalRequest, ApprovalError> {
+        let current = self.get(id).await?.ok_or(ApprovalError::NotFound)?;
+        if current.status != ApprovalStatus::Pending {
+            return Err(ApprovalError::Closed(current.status));
+        }
+        if current.is_expired() {
+            self.close_expired(current).await?;
+            return Err(ApprovalError::Closed(ApprovalStatus::Expired));
+        }
+        if !current.roles.contains(&approver.role) || approver.id == current.user_id {
+            return Err(ApprovalError::Unauthorized);
+        }
+        if current
+            .decisions
+            .iter()
+            .any(|decision| decision.approver_id == approver.id)
+        {
+            return Err(ApprovalError::AlreadyDecided);
+        }
+
+        let now = Utc::now();
+        let mut next = ApprovalRequest {
+            updated_at: now,
+            ..current.clone()
+        };
+        next.decisions.push(Decision {
+            approver_id: approver.id,
+            role: approver.role,
+            verdict,
+            reason: reason.clone(),
+            decided_at: now,
+        });
+        let closing = match verdict {
+            Verdict::Reject => {
+                next.status = ApprovalStatus::Rejected;
+                let reason = format!("rejected: {}", reason.unwrap_or_default());
+                Some((WithdrawalStatus::Failed, Some(reason)))
+            }
+            Verdict::Approve if next.approvals() >= next.required as usize => {
+                next.status = ApprovalStatus::Approved;
+                Some((WithdrawalStatus::Approved, None))
+            }
+            Verdict::Approve => None,
+        };
+        let action = match verdict {
+            Verdict::Approve => "withdrawal.approve",
+            Verdict::Reject => "withdrawal.reject",
+        };
+        let actor = Actor::Admin(approver.id);
+        let event = audit(actor, action, &current, &next)?;
+        match closing {
+            Some((status, failure_reason)) => {
+                self.close(actor, &next, status, failure_reason, event)
+                    .await?
+            }
+            None => self.update(&next, event).await?,
+        }
+        Ok(ApprovalRequest {
+            version: current.version + 1,
+            ..next
+        })
+    }
+
+    async fn close_expired(
+        &self,
+        current: ApprovalRequest,
+    ) -> Result<ApprovalRequest, ApprovalError> {
+        let next = ApprovalRequest {
+            status: ApprovalStatus::Expired,
+            updated_at: Utc::now(),
+            ..current.clone()
+        };
+        let reason = "approval expired".to_string();
+        let event = audit(
+            Actor::System,
+            "withdrawal.approval_expired",
+            &current,
+            &next,
+        )?;
+        match self
+            .close(
+                Actor::System,
+                &next,
+                WithdrawalStatus::Failed,
+                Some(reason),
+                event.clone(),
+            )
+            .await
+        {
+            // The withdrawal was cancelled or failed while under review
+            Err(ApprovalError::Withdrawal(WithdrawalError::IllegalTransition { .. })) => {
+                self.update(&next, event).await?;
+            }
+            result => result?,
+        }
+        Ok(ApprovalRequest {
+            version: current.version + 1,
+            ..next
+        })
+    }
+
+    /// Stores the closed request and `event` together with the withdrawal's move to
+    /// `status` by `actor`.
+    async fn close(
+        &self,
+        actor: Actor,
+        request: &ApprovalRequest,
+        status: WithdrawalStatus,
+        failure_reason: Option<String>,
+        event: AuditEvent,
+    ) -> Result<(), ApprovalError> {
+        self.withdrawals
+            .advance_with(
+                actor,
+                &request.id,
+                status,
+                |withdrawal| withdrawal.failure_reason = failure_reason,
+                vec![Statement::replace(request)?, Statement::audit(event)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the request and `event` without moving the withdrawal.
+    async fn update(
+        &self,
+        request: &ApprovalRequest,
+        event: AuditEvent,
+    ) -> Result<(), ApprovalError> {
+        UnitOfWork::new()
+            .update(request)
+            .audit(event)
+            .commit(self.requests.db(), &RetryPolicy::default())
+            .await
+            .map_err(conflict)
+    }
+}
+
+/// Records the change of an approval request by `actor`.
+fn audit(
+    actor: Actor,
+    action: &str,
+    before: &ApprovalRequest,
+    after: &ApprovalRequest,
+) -> Result<AuditEvent, ApprovalError> {
+    Ok(AuditEvent::new(actor, AuditCategory::Withdrawal, action)
+        .record(Some(before), Some(after))?)
+}
+
+fn conflict(err: DatabaseError) -> ApprovalError {
+    match err {
+        DatabaseError::Conflict => ApprovalError::Conflict,
+        err => err.into(),
+    }
+}
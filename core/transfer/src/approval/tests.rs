@@ -0,0 +1,327 @@
+use chrono::Duration;
+use database::{Actor, AuditCategory, AuditLog};
+use surrealdb::{engine::local::Db, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::ApprovalError,
+    models::{ApprovalPolicy, ApprovalStatus, Approver, ApproverRole, QuorumRule},
+    service::Approvals,
+};
+use crate::{
+    ledger::{journal::Ledger, models::Account},
+    tests::{funded, unlimited, withdrawal_request},
+    withdrawal::{
+        errors::WithdrawalError,
+        models::{Withdrawal, WithdrawalFees, WithdrawalStatus},
+        service::Withdrawals,
+    },
+};
+
+struct Fixture {
+    db: Surreal<Db>,
+    ledger: Ledger<Db>,
+    withdrawals: Withdrawals<Db>,
+    approvals: Approvals<Db>,
+    user_id: Uuid,
+    asset_id: Uuid,
+}
+impl Fixture {
+    async fn new(ttl: Duration) -> Self {
+        let user_id = Uuid::new_v4();
+        let (db, asset_id) = funded(user_id, 100).await;
+        let ledger = Ledger::new(db.clone());
+        let policy = ApprovalPolicy::new()
+            .asset(
+                asset_id,
+                QuorumRule::new(10, 2, [ApproverRole::Operator, ApproverRole::Treasury]),
+            )
+            .ttl(ttl);
+        let withdrawals =
+            Withdrawals::new(db.clone(), WithdrawalFees::new(), unlimited(&db), policy);
+        Self {
+            approvals: Approvals::new(withdrawals.clone()),
+            withdrawals,
+            db,
+            ledger,
+            user_id,
+            asset_id,
+        }
+    }
+
+    async fn request(&self, amount: i64) -> Withdrawal {
+        self.withdrawals
+            .request(&withdrawal_request(self.user_id, self.asset_id, amount))
+            .await
+            .unwrap()
+    }
+
+    async fn status(&self, id: &Uuid) -> WithdrawalStatus {
+        self.withdrawals.get(id).await.unwrap().unwrap().status
+    }
+}
+
+fn approver(role: ApproverRole) -> Approver {
+    Approver {
+        id: Uuid::new_v4(),
+        role,
+    }
+}
+
+#[tokio::test]
+async fn withdrawals_below_the_threshold_are_approved_without_review() {
+    let fixture = Fixture::new(Duration::hours(1)).await;
+    let withdrawal = fixture.request(10).await;
+
+    let approved = fixture
+        .approvals
+        .submit(Actor::User(fixture.user_id), &withdrawal.id)
+        .await
+        .unwrap();
+    assert_eq!(approved.status, WithdrawalStatus::Approved);
+    assert_eq!(fixture.approvals.get(&withdrawal.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn a_quorum_of_distinct_approvers_approves_the_withdrawal() {
+    let fixture = Fixture::new(Duration::hours(1)).await;
+    let approvals = &fixture.approvals;
+    let withdrawal = fixture.request(50).await;
+    assert!(matches!(
+        fixture
+            .withdrawals
+            .approve(Actor::System, &withdrawal.id)
+            .await,
+        Err(WithdrawalError::ApprovalRequired)
+    ));
+
+    let submitted = approvals
+        .submit(Actor::User(fixture.user_id), &withdrawal.id)
+        .await
+        .unwrap();
+    assert_eq!(submitted.status, WithdrawalStatus::PendingReview);
+    assert_eq!(approvals.pending().await.unwrap().len(), 1);
+
+    let operator = approver(ApproverRole::Operator);
+    let request = approvals.approve(&withdrawal.id, &operator).await.unwrap();
+    assert_eq!(
+        (request.status, request.approvals()),
+        (ApprovalStatus::Pending, 1)
+    );
+    assert!(matches!(
+        approvals.approve(&withdrawal.id, &operator).await,
+        Err(ApprovalError::AlreadyDecided)
+    ));
+    assert!(matches!(
+        approvals
+            .approve(&withdrawal.id, &approver(ApproverRole::Compliance))
+            .await,
+        Err(ApprovalError::Unauthorized)
+    ));
+    let requester = Approver {
+        id: fixture.user_id,
+        role: ApproverRole::Treasury,
+    };
+    assert!(matches!(
+        approvals.approve(&withdrawal.id, &requester).await,
+        Err(ApprovalError::Unauthorized)
+    ));
+    assert_eq!(
+        fixture.status(&withdrawal.id).await,
+        WithdrawalStatus::PendingReview
+    );
+
+    let treasury = approver(ApproverRole::Treasury);
+    let request = approvals.approve(&withdrawal.id, &treasury).await.unwrap();
+    assert_eq!(request.status, ApprovalStatus::Approved);
+    assert_eq!(approvals.get(&withdrawal.id).await.unwrap(), Some(request));
+    assert_eq!(
+        fixture.status(&withdrawal.id).await,
+        WithdrawalStatus::Approved
+    );
+    fixture
+        .withdrawals
+        .sign(Actor::System, &withdrawal.id)
+        .await
+        .unwrap();
+
+    let trail = AuditLog::new(fixture.db.clone()).list(0, 10).await.unwrap();
+    assert_eq!(
+        trail
+            .iter()
+            .map(|entry| (entry.event.category, entry.event.action.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (AuditCategory::Balance, "ledger.deposit"),
+            (AuditCategory::Balance, "ledger.withdrawal"),
+            (AuditCategory::Withdrawal, "withdrawal.request"),
+            (AuditCategory::Withdrawal, "withdrawal.pending_review"),
+            (AuditCategory::Withdrawal, "withdrawal.approve"),
+            (AuditCategory::Withdrawal, "withdrawal.approve"),
+            (AuditCategory::Withdrawal, "withdrawal.approved"),
+            (AuditCategory::Withdrawal, "withdrawal.signed"),
+        ]
+    );
+    // The quorum's last approver moved the withdrawal on
+    assert_eq!(trail[6].event.actor, Actor::Admin(treasury.id));
+}
+
+#[tokio::test]
+async fn withdrawals_needing_a_quorum_are_only_signed_once_it_is_reached() {
+    let fixture = Fixture::new(Duration::hours(1)).await;
+    let withdrawal = fixture.request(50).await;
+
+    // Approved under a policy without the quorum rule
+    Withdrawals::new(
+        fixture.db.clone(),
+        WithdrawalFees::new(),
+        unlimited(&fixture.db),
+        ApprovalPolicy::new(),
+    )
+    .approve(Actor::System, &withdrawal.id)
+    .await
+    .unwrap();
+    assert!(matches!(
+        fixture
+            .withdrawals
+            .sign(Actor::System, &withdrawal.id)
+            .await,
+        Err(WithdrawalError::ApprovalRequired)
+    ));
+    assert_eq!(
+        fixture.status(&withdrawal.id).await,
+        WithdrawalStatus::Approved
+    );
+}
+
+#[tokio::test]
+async fn a_rejection_fails_the_withdrawal_and_releases_its_funds() {
+    let fixture = Fixture::new(Duration::hours(1)).await;
+    let approvals = &fixture.approvals;
+    let withdrawal = fixture.request(50).await;
+    approvals
+        .submit(Actor::User(fixture.user_id), &withdrawal.id)
+        .await
+        .unwrap();
+    approvals
+        .approve(&withdrawal.id, &approver(ApproverRole::Operator))
+        .await
+        .unwrap();
+
+    let request = approvals
+        .reject(
+            &withdrawal.id,
+            &approver(ApproverRole::Treasury),
+            "unknown address",
+        )
+        .await
+        .unwrap();
+    assert_eq!(request.status, ApprovalStatus::Rejected);
+    let failed = fixture
+        .withdrawals
+        .get(&withdrawal.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (failed.status, failed.failure_reason.as_deref()),
+        (WithdrawalStatus::Failed, Some("rejected: unknown address"))
+    );
+    assert_eq!(
+        fixture
+            .ledger
+            .balance(Account::User(fixture.user_id), fixture.asset_id)
+            .await
+            .unwrap(),
+        100
+    );
+    assert!(matches!(
+        approvals
+            .approve(&withdrawal.id, &approver(ApproverRole::Operator))
+            .await,
+        Err(ApprovalError::Closed(ApprovalStatus::Rejected))
+    ));
+}
+
+#[tokio::test]
+async fn requests_expire_without_quorum() {
+    let fixture = Fixture::new(Duration::zero()).await;
+    let approvals = &fixture.approvals;
+    let expired = fixture.request(50).await;
+    let cancelled = fixture.request(20).await;
+    approvals
+        .submit(Actor::User(fixture.user_id), &expired.id)
+        .await
+        .unwrap();
+    approvals
+        .submit(Actor::User(fixture.user_id), &cancelled.id)
+        .await
+        .unwrap();
+    fixture
+        .withdrawals
+        .cancel(Actor::User(fixture.user_id), &cancelled.id)
+        .await
+        .unwrap();
+
+    let closed = approvals.expire().await.unwrap();
+    assert_eq!(closed.len(), 2);
+    assert!(closed
+        .iter()
+        .all(|request| request.status == ApprovalStatus::Expired));
+    assert_eq!(fixture.status(&expired.id).await, WithdrawalStatus::Failed);
+    assert_eq!(
+        fixture.status(&cancelled.id).await,
+        WithdrawalStatus::Cancelled
+    );
+    assert!(approvals.pending().await.unwrap().is_empty());
+    assert!(matches!(
+        approvals
+            .approve(&expired.id, &approver(ApproverRole::Operator))
+            .await,
+        Err(ApprovalError::Closed(ApprovalStatus::Expired))
+    ));
+}
+
+#[tokio::test]
+async fn decisions_are_not_applied_without_their_audit_entry() {
+    let fixture = Fixture::new(Duration::hours(1)).await;
+    let approvals = &fixture.approvals;
+    let withdrawal = fixture.request(50).await;
+    approvals
+        .submit(Actor::User(fixture.user_id), &withdrawal.id)
+        .await
+        .unwrap();
+    fixture
+        .db
+        .query("DEFINE FIELD action ON audit_log TYPE string ASSERT $value != 'withdrawal.reject'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    assert!(approvals
+        .reject(
+            &withdrawal.id,
+            &approver(ApproverRole::Treasury),
+            "unknown address"
+        )
+        .await
+        .is_err());
+    let request = approvals.get(&withdrawal.id).await.unwrap().unwrap();
+    assert_eq!(
+        (request.status, request.decisions.len()),
+        (ApprovalStatus::Pending, 0)
+    );
+    assert_eq!(
+        fixture.status(&withdrawal.id).await,
+        WithdrawalStatus::PendingReview
+    );
+    assert_eq!(
+        fixture
+            .ledger
+            .balance(Account::User(fixture.user_id), fixture.asset_id)
+            .await
+            .unwrap(),
+        50
+    );
+}
@@ -0,0 +1,33 @@
+use database::DatabaseError;
+
+use crate::ledger::errors::LedgerError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DepositError {
+    #[error("deposit amount must be positive")]
+    InvalidAmount,
+
+    #[error("asset not found")]
+    AssetNotFound,
+
+    #[error("deposit does not match the one recorded for its transaction")]
+    Mismatch,
+
+    #[error("deposit was modified concurrently")]
+    Conflict,
+
+    #[error("ledger error")]
+    Ledger(#[source] LedgerError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<LedgerError> for DepositError {
+    fn from(err: LedgerError) -> Self {
+        match err {
+            LedgerError::Database(DatabaseError::Conflict) => Self::Conflict,
+            err => Self::Ledger(err),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use database::Record;
+use models::Network;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Confirmations required when no threshold is configured for an asset.
+pub const DEFAULT_CONFIRMATIONS: u64 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositStatus {
+    /// Seen on chain, waiting for enough confirmations.
+    Pending,
+    /// Added to the user's balance.
+    Credited,
+    /// Dropped from the chain by a reorg; a credit, if any, was reversed.
+    Orphaned,
+}
+
+/// A transfer to a user's deposit address, as reported by the chain watcher.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservedDeposit {
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub tx_hash: String,
+    /// Position of the transfer within the transaction, as several may pay one user.
+    pub log_index: u64,
+    pub amount: i64,
+    /// Block the transaction was included in.
+    pub block_number: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deposit {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub network: Network,
+    pub tx_hash: String,
+    pub log_index: u64,
+    pub amount: i64,
+    pub block_number: u64,
+    /// Confirmations counted when the deposit was last checked.
+    #[serde(default)]
+    pub confirmations: u64,
+    pub status: DepositStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: u64,
+}
+impl Deposit {
+    /// Confirmations of the deposit when the chain's latest block is `tip`.
+    pub fn confirmations_at(&self, tip: u64) -> u64 {
+        match tip.checked_sub(self.block_number) {
+            Some(depth) => depth + 1,
+            None => 0,
+        }
+    }
+}
+impl Record for Deposit {
+    const TABLE: &'static str = "deposits";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
+
+/// Confirmations required before deposits of an asset are credited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationThresholds {
+    default: u64,
+    assets: HashMap<Uuid, u64>,
+}
+impl Default for ConfirmationThresholds {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONFIRMATIONS)
+    }
+}
+impl ConfirmationThresholds {
+    pub fn new(default: u64) -> Self {
+        Self {
+            default: default.max(1),
+            assets: HashMap::new(),
+        }
+    }
+
+    pub fn asset(mut self, asset_id: Uuid, confirmations: u64) -> Self {
+        self.assets.insert(asset_id, confirmations.max(1));
+        self
+    }
+
+    pub fn get(&self, asset_id: &Uuid) -> u64 {
+        self.assets.get(asset_id).copied().unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::Utc;
+use database::{
+    Actor, AssetRepository, AuditCategory, AuditEvent, DatabaseError, Direction, Filter, Operator,
+    Repository,
+};
+use models::Network;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::DepositError,
+    models::{ConfirmationThresholds, Deposit, DepositStatus, ObservedDeposit},
+};
+use crate::ledger::{
+    errors::LedgerError,
+    journal::{Ledger, Statement},
+    models::{Account, EntryKind, JournalEntry},
+};
+
+/// Credits deposits once they are buried deep enough and reverses them if a reorg
+/// drops their transaction.
+///
+/// The chain watcher reports every deposit it sees through [`Deposits::observe`], the
+/// latest block of each network through [`Deposits::confirm`] and reorgs through
+/// [`Deposits::rollback`]. Crediting and reversing a deposit are recorded in the audit
+/// log. Batch operations skip deposits another worker changed concurrently; they are
+/// picked up again on the next call.
+#[derive(Debug)]
+pub struct Deposits<C: Connection> {
+    ledger: Ledger<C>,
+    deposits: Repository<C, Deposit>,
+    assets: AssetRepository<C>,
+    thresholds: ConfirmationThresholds,
+}
+impl<C: Connection> Clone for Deposits<C> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            deposits: self.deposits.clone(),
+            assets: self.assets.clone(),
+            thresholds: self.thresholds.clone(),
+        }
+    }
+}
+impl<C: Connection> Deposits<C> {
+    pub fn new(db: Surreal<C>, thresholds: ConfirmationThresholds) -> Self {
+        Self {
+            ledger: Ledger::new(db.clone()),
+            deposits: Repository::new(db.clone()),
+            assets: AssetRepository::new(db),
+            thresholds,
+        }
+    }
+
+    /// Records a deposit as pending, or updates the block of one seen before. A deposit
+    /// orphaned by a reorg becomes pending again when its transaction is mined anew.
+    pub async fn observe(&self, observed: &ObservedDeposit) -> Result<Deposit, DepositError> {
+        if observed.amount <= 0 {
+            return Err(DepositError::InvalidAmount);
+        }
+        let asset = self
+            .assets
+            .get(&observed.asset_id)
+            .await?
+            .ok_or(DepositError::AssetNotFound)?;
+        let existing = self
+            .deposits
+            .list(
+                &Filter::new()
+                    .eq("tx_hash", observed.tx_hash.as_str())
+                    .eq("log_index", observed.log_index),
+            )
+            .await?
+            .pop();
+        let now = Utc::now();
+        let Some(deposit) = existing else {
+            let deposit = Deposit {
+                id: Uuid::new_v4(),
+                user_id: observed.user_id,
+                asset_id: observed.asset_id,
+                network: asset.network,
+                tx_hash: observed.tx_hash.clone(),
+                log_index: observed.log_index,
+                amount: observed.amount,
+                block_number: observed.block_number,
+                confirmations: 0,
+                status: DepositStatus::Pending,
+                created_at: now,
+                updated_at: now,
+                version: 0,
+            };
+            return self.deposits.create(&deposit).await.map_err(conflict);
+        };
+
+        if (deposit.user_id, deposit.asset_id, deposit.amount)
+            != (observed.user_id, observed.asset_id, observed.amount)
+        {
+            return Err(DepositError::Mismatch);
+        }
+        if deposit.status != DepositStatus::Orphaned
+            && deposit.block_number == observed.block_number
+        {
+            return Ok(deposit);
+        }
+        let status = match deposit.status {
+            DepositStatus::Orphaned => DepositStatus::Pending,
+            status => status,
+        };
+        self.deposits
+            .update(&Deposit {
+                block_number: observed.block_number,
+                confirmations: 0,
+                status,
+                updated_at: now,
+                ..deposit
+            })
+            .await
+            .map_err(conflict)
+    }
+
+    pub async fn get(&self, id: &Uuid) -> Result<Option<Deposit>, DepositError> {
+        Ok(self.deposits.get(id).await?)
+    }
+
+    /// Deposits of a user, newest first.
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Deposit>, DepositError> {
+        Ok(self
+            .deposits
+            .list(
+                &Filter::new()
+                    .eq("user_id", user_id)
+                    .order_by("created_at", Direction::Desc),
+            )
+            .await?)
+    }
+
+    /// Counts the confirmations of pending deposits on `network` given its latest
+    /// block and credits those that reached their asset's threshold.
+    pub async fn confirm(&self, network: Network, tip: u64) -> Result<Vec<Deposit>, DepositError> {
+        let pending = self
+            .deposits
+            .list(
+                &Filter::new()
+                    .eq("network", network)
+                    .eq("status", DepositStatus::Pending)
+                    .order_by("block_number", Direction::Asc),
+            )
+            .await?;
+        let mut credited = Vec::new();
+        for deposit in pending {
+            let confirmations = deposit.confirmations_at(tip);
+            let next = Deposit {
+                confirmations,
+                updated_at: Utc::now(),
+                ..deposit.clone()
+            };
+            let result = if confirmations >= self.thresholds.get(&deposit.asset_id) {
+                let next = Deposit {
+                    status: DepositStatus::Credited,
+                    ..next
+                };
+                let entry = entry(&deposit, "credit")
+                    .debit(Account::HotWallet, deposit.asset_id, deposit.amount)
+                    .credit(
+                        Account::User(deposit.user_id),
+                        deposit.asset_id,
+                        deposit.amount,
+                    );
+                self.commit(&deposit, "deposit.credit", &entry, next)
+                    .await
+                    .map(|deposit| credited.push(deposit))
+            } else if confirmations != deposit.confirmations {
+                self.deposits
+                    .update(&next)
+                    .await
+                    .map(drop)
+                    .map_err(conflict)
+            } else {
+                Ok(())
+            };
+            match result {
+                Ok(()) | Err(DepositError::Conflict) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(credited)
+    }
+
+    /// Orphans every deposit on `network` included at or above `from_block`, the first
+    /// block the reorg replaced, and reverses those that were credited.
+    pub async fn rollback(
+        &self,
+        network: Network,
+        from_block: u64,
+    ) -> Result<Vec<Deposit>, DepositError> {
+        let affected = self
+            .deposits
+            .list(
+                &Filter::new()
+                    .eq("network", network)
+                    .condition("block_number", Operator::Gte, from_block)
+                    .condition("status", Operator::Ne, DepositStatus::Orphaned),
+            )
+            .await?;
+        self.orphan_all(affected).await
+    }
+
+    /// Orphans the deposits of a transaction that was dropped from the chain.
+    pub async fn drop_transaction(&self, tx_hash: &str) -> Result<Vec<Deposit>, DepositError> {
+        let affected = self
+            .deposits
+            .list(&Filter::new().eq("tx_hash", tx_hash).condition(
+                "status",
+                Operator::Ne,
+                DepositStatus::Orphaned,
+            ))
+            .await?;
+        self.orphan_all(affected).await
+    }
+
+    async fn orphan_all(&self, deposits: Vec<Deposit>) -> Result<Vec<Deposit>, DepositError> {
+        let mut orphaned = Vec::new();
+        for deposit in deposits {
+            match self.orphan(deposit).await {
+                Ok(deposit) => orphaned.push(deposit),
+                Err(DepositError::Conflict) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(orphaned)
+    }
+
+    /// Reverses the credit of a deposit from the user's balance or, if the user has
+    /// spent it already, from the suspense account so the shortfall can be recovered.
+    async fn orphan(&self, deposit: Deposit) -> Result<Deposit, DepositError> {
+        let next = Deposit {
+            status: DepositStatus::Orphaned,
+            updated_at: Utc::now(),
+            ..deposit.clone()
+        };
+        if deposit.status != DepositStatus::Credited {
+            return self.deposits.update(&next).await.map_err(conflict);
+        }
+
+        let reversal = entry(&deposit, "reversal")
+            .debit(
+                Account::User(deposit.user_id),
+                deposit.asset_id,
+                deposit.amount,
+            )
+            .credit(Account::HotWallet, deposit.asset_id, deposit.amount);
+        match self
+            .commit(&deposit, "deposit.reverse", &reversal, next.clone())
+            .await
+        {
+            Err(DepositError::Ledger(LedgerError::InsufficientBalance)) => {
+                let reversal = entry(&deposit, "reversal")
+                    .memo("reversal not covered by the user")
+                    .debit(Account::Suspense, deposit.asset_id, deposit.amount)
+                    .credit(Account::HotWallet, deposit.asset_id, deposit.amount);
+                self.commit(&deposit, "deposit.reverse", &reversal, next)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Posts `entry`, stores `next` and records the change from `current` as `action` in
+    /// the audit log in one transaction, provided the deposit was not changed since it was
+    /// read.
+    async fn commit(
+        &self,
+        current: &Deposit,
+        action: &str,
+        entry: &JournalEntry,
+        next: Deposit,
+    ) -> Result<Deposit, DepositError> {
+        let event = AuditEvent::new(Actor::System, AuditCategory::Balance, action)
+            .record(Some(current), Some(&next))?;
+        match self
+            .ledger
+            .post_with(
+                entry,
+                vec![Statement::replace(&next)?, Statement::audit(event)],
+            )
+            .await
+        {
+            Ok(_) => Ok(Deposit {
+                version: next.version + 1,
+                ..next
+            }),
+            Err(LedgerError::DuplicateReference(_)) => Err(DepositError::Conflict),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Journal entry for a step of a deposit; the version makes the reference unique when
+/// a deposit is credited again after a reorg.
+fn entry(deposit: &Deposit, step: &str) -> JournalEntry {
+    JournalEntry::new(EntryKind::Deposit)
+        .reference(format!("deposit:{}:{step}:{}", deposit.id, deposit.version))
+        .memo(step)
+}
+
+fn conflict(err: DatabaseError) -> DepositError {
+    match err {
+        DatabaseError::Conflict | DatabaseError::AlreadyExists => DepositError::Conflict,
+        err => err.into(),
+    }
+}
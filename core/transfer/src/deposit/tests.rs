@@ -0,0 +1,279 @@
+use database::AuditLog;
+use models::Network;
+use surrealdb::engine::local::Db;
+use uuid::Uuid;
+
+use super::{
+    errors::DepositError,
+    models::{ConfirmationThresholds, DepositStatus, ObservedDeposit},
+    service::Deposits,
+};
+use crate::{
+    ledger::{
+        journal::Ledger,
+        models::{Account, EntryKind, JournalEntry},
+    },
+    tests::{add_asset, balance, ether, migrated},
+};
+
+struct Fixture {
+    ledger: Ledger<Db>,
+    deposits: Deposits<Db>,
+    user_id: Uuid,
+    asset_id: Uuid,
+}
+impl Fixture {
+    async fn new() -> Self {
+        let db = migrated().await;
+        let asset_id = Uuid::new_v4();
+        add_asset(&db, &ether(asset_id)).await;
+        Self {
+            ledger: Ledger::new(db.clone()),
+            deposits: Deposits::new(db, ConfirmationThresholds::new(12).asset(asset_id, 3)),
+            user_id: Uuid::new_v4(),
+            asset_id,
+        }
+    }
+
+    fn observed(&self, tx_hash: &str, block_number: u64) -> ObservedDeposit {
+        ObservedDeposit {
+            user_id: self.user_id,
+            asset_id: self.asset_id,
+            tx_hash: tx_hash.to_string(),
+            log_index: 0,
+            amount: 25,
+            block_number,
+        }
+    }
+}
+
+#[test]
+fn thresholds_fall_back_to_the_default_and_require_a_block() {
+    let thresholds = ConfirmationThresholds::new(12);
+    assert_eq!(thresholds.get(&Uuid::new_v4()), 12);
+    assert_eq!(ConfirmationThresholds::new(0).get(&Uuid::new_v4()), 1);
+}
+
+#[tokio::test]
+async fn deposits_are_credited_after_enough_confirmations() {
+    let fixture = Fixture::new().await;
+    let deposits = &fixture.deposits;
+    let deposit = deposits
+        .observe(&fixture.observed("0xa", 100))
+        .await
+        .unwrap();
+    assert_eq!(deposit.status, DepositStatus::Pending);
+    assert_eq!(
+        deposits
+            .observe(&fixture.observed("0xa", 100))
+            .await
+            .unwrap(),
+        deposit
+    );
+
+    assert!(deposits
+        .confirm(Network::Ethereum, 101)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        deposits
+            .get(&deposit.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .confirmations,
+        2
+    );
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        0
+    );
+
+    let credited = deposits.confirm(Network::Ethereum, 102).await.unwrap();
+    assert_eq!(credited.len(), 1);
+    assert_eq!(
+        (credited[0].status, credited[0].confirmations),
+        (DepositStatus::Credited, 3)
+    );
+    assert_eq!(
+        deposits.get(&deposit.id).await.unwrap().unwrap(),
+        credited[0]
+    );
+    assert!(deposits
+        .confirm(Network::Ethereum, 103)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        25
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::HotWallet, fixture.asset_id).await,
+        25
+    );
+    assert!(fixture.ledger.verify().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reorgs_reverse_credits_until_the_transaction_is_mined_again() {
+    let fixture = Fixture::new().await;
+    let deposits = &fixture.deposits;
+    let credited = deposits
+        .observe(&fixture.observed("0xa", 100))
+        .await
+        .unwrap();
+    let pending = deposits
+        .observe(&fixture.observed("0xb", 101))
+        .await
+        .unwrap();
+    deposits.confirm(Network::Ethereum, 102).await.unwrap();
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        25
+    );
+
+    let orphaned = deposits.rollback(Network::Ethereum, 100).await.unwrap();
+    assert_eq!(orphaned.len(), 2);
+    assert!(orphaned
+        .iter()
+        .all(|deposit| deposit.status == DepositStatus::Orphaned));
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        0
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::HotWallet, fixture.asset_id).await,
+        0
+    );
+
+    let remined = deposits
+        .observe(&fixture.observed("0xa", 105))
+        .await
+        .unwrap();
+    assert_eq!(
+        (remined.id, remined.status),
+        (credited.id, DepositStatus::Pending)
+    );
+    let credited = deposits.confirm(Network::Ethereum, 107).await.unwrap();
+    assert_eq!(credited.len(), 1);
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        25
+    );
+    assert_eq!(
+        deposits.get(&pending.id).await.unwrap().unwrap().status,
+        DepositStatus::Orphaned
+    );
+    assert!(fixture.ledger.verify().await.unwrap().is_empty());
+
+    let actions: Vec<String> = AuditLog::new(fixture.ledger.db().clone())
+        .list(0, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.event.action)
+        .filter(|action| action.starts_with("deposit."))
+        .collect();
+    assert_eq!(
+        actions,
+        ["deposit.credit", "deposit.reverse", "deposit.credit"]
+    );
+}
+
+#[tokio::test]
+async fn spent_credits_are_reversed_against_suspense() {
+    let fixture = Fixture::new().await;
+    let deposits = &fixture.deposits;
+    deposits
+        .observe(&fixture.observed("0xa", 100))
+        .await
+        .unwrap();
+    deposits.confirm(Network::Ethereum, 102).await.unwrap();
+    fixture
+        .ledger
+        .post(
+            &JournalEntry::new(EntryKind::Transfer)
+                .debit(Account::User(fixture.user_id), fixture.asset_id, 20)
+                .credit(Account::User(Uuid::new_v4()), fixture.asset_id, 20),
+        )
+        .await
+        .unwrap();
+
+    let orphaned = deposits.drop_transaction("0xa").await.unwrap();
+    assert_eq!(orphaned.len(), 1);
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        5
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Suspense, fixture.asset_id).await,
+        -25
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::HotWallet, fixture.asset_id).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn observations_are_validated() {
+    let fixture = Fixture::new().await;
+    let deposits = &fixture.deposits;
+    deposits
+        .observe(&fixture.observed("0xa", 100))
+        .await
+        .unwrap();
+
+    let mut observed = fixture.observed("0xa", 100);
+    observed.amount = 26;
+    assert!(matches!(
+        deposits.observe(&observed).await,
+        Err(DepositError::Mismatch)
+    ));
+    let mut observed = fixture.observed("0xb", 100);
+    observed.amount = 0;
+    assert!(matches!(
+        deposits.observe(&observed).await,
+        Err(DepositError::InvalidAmount)
+    ));
+    let mut observed = fixture.observed("0xb", 100);
+    observed.asset_id = Uuid::new_v4();
+    assert!(matches!(
+        deposits.observe(&observed).await,
+        Err(DepositError::AssetNotFound)
+    ));
+    assert_eq!(deposits.list(fixture.user_id).await.unwrap().len(), 1);
+}
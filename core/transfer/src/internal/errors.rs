@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use database::DatabaseError;
+
+use crate::{ledger::errors::LedgerError, limits::errors::LimitError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    #[error("transfer amount must be positive")]
+    InvalidAmount,
+
+    #[error("cannot transfer to yourself")]
+    SelfTransfer,
+
+    #[error("recipient not found")]
+    RecipientNotFound,
+
+    #[error("asset not found")]
+    AssetNotFound,
+
+    #[error("memo is longer than {0} characters")]
+    MemoTooLong(usize),
+
+    #[error("transfer exceeds the limit of {0}")]
+    LimitExceeded(i64),
+
+    #[error("transfers are blocked until {0} after a security change")]
+    CoolingOff(DateTime<Utc>),
+
+    #[error("invalid transfer limits: {0}")]
+    InvalidLimits(String),
+
+    #[error("idempotency key was used for a different transfer")]
+    IdempotencyConflict,
+
+    #[error("insufficient balance")]
+    InsufficientBalance,
+
+    #[error("ledger error")]
+    Ledger(#[source] LedgerError),
+
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<LedgerError> for TransferError {
+    fn from(err: LedgerError) -> Self {
+        match err {
+            LedgerError::InsufficientBalance => Self::InsufficientBalance,
+            err => Self::Ledger(err),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::errors::TransferError;
+use crate::ledger::models::{EntryKind, JournalEntry, Side};
+
+/// Longest memo, in characters, a sender may attach to a transfer.
+pub const MAX_MEMO_LENGTH: usize = 140;
+
+/// Who receives a transfer, given as `{"id": "<uuid>"}` or `{"handle": "<handle>"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recipient {
+    Id(Uuid),
+    Handle(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub sender_id: Uuid,
+    pub recipient: Recipient,
+    pub asset_id: Uuid,
+    pub amount: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Key chosen by the sender; repeating a request with the same key returns the
+    /// transfer made the first time instead of moving funds again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+/// A completed transfer, also published to notify the recipient.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: Uuid,
+    pub sender_id: Uuid,
+    pub recipient_id: Uuid,
+    pub asset_id: Uuid,
+    pub amount: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+impl Transfer {
+    /// Reads a transfer back from the journal entry that recorded it.
+    pub fn from_entry(entry: &JournalEntry) -> Option<Self> {
+        if entry.kind != EntryKind::Transfer {
+            return None;
+        }
+        let (debit, credit) = match entry.postings.as_slice() {
+            [debit, credit] if debit.side == Side::Debit && credit.side == Side::Credit => {
+                (debit, credit)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            id: entry.id,
+            sender_id: debit.account.user_id()?,
+            recipient_id: credit.account.user_id()?,
+            asset_id: debit.asset_id,
+            amount: debit.amount,
+            memo: entry.memo.clone(),
+            created_at: entry.created_at,
+        })
+    }
+}
+
+/// Caps on the amount of an asset a user may send, in the asset's smallest unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetLimit {
+    pub per_transfer: i64,
+    /// Total sent over the last 24 hours, including the new transfer.
+    pub daily: i64,
+}
+
+/// Transfer limits by asset; assets without a limit can be sent in any amount.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferLimits {
+    assets: HashMap<Uuid, AssetLimit>,
+}
+impl TransferLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn asset(mut self, asset_id: Uuid, limit: AssetLimit) -> Self {
+        self.assets.insert(asset_id, limit);
+        self
+    }
+
+    pub fn get(&self, asset_id: &Uuid) -> Option<&AssetLimit> {
+        self.assets.get(asset_id)
+    }
+}
+impl FromStr for TransferLimits {
+    type Err = TransferError;
+
+    /// Parses `<asset_id>=<per_transfer>/<daily>` entries separated by commas.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .try_fold(Self::new(), |limits, entry| {
+                let invalid = || TransferError::InvalidLimits(entry.to_string());
+                let (asset_id, amounts) = entry.split_once('=').ok_or_else(invalid)?;
+                let (per_transfer, daily) = amounts.split_once('/').ok_or_else(invalid)?;
+                Ok(limits.asset(
+                    asset_id.parse().map_err(|_| invalid())?,
+                    AssetLimit {
+                        per_transfer: per_transfer.parse().map_err(|_| invalid())?,
+                        daily: daily.parse().map_err(|_| invalid())?,
+                    },
+                ))
+            })
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{Duration, Utc};
+use database::{AssetRepository, Filter, UserRepository};
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::TransferError,
+    models::{Recipient, Transfer, TransferLimits, TransferRequest, MAX_MEMO_LENGTH},
+};
+use crate::{
+    ledger::{
+        errors::LedgerError,
+        journal::Ledger,
+        models::{Account, EntryKind, JournalEntry},
+    },
+    limits::service::WithdrawalLimits,
+};
+
+/// Channel that completed transfers are published on, for notifying recipients.
+pub const TRANSFERS_CHANNEL: &str = "transfers";
+
+/// Moves funds between users by posting to the ledger, without touching the chain.
+///
+/// A transfer and its notification on [`TRANSFERS_CHANNEL`] are committed together.
+/// Daily limits are checked before posting, so concurrent transfers of one sender
+/// may exceed them by up to one transfer each. A sender in the cooling-off period after
+/// a security change cannot transfer either, or a taken-over account could move its
+/// funds to another user instead of withdrawing them.
+#[derive(Debug)]
+pub struct InternalTransfers<C: Connection> {
+    ledger: Ledger<C>,
+    users: UserRepository<C>,
+    assets: AssetRepository<C>,
+    limits: TransferLimits,
+    security: WithdrawalLimits<C>,
+}
+impl<C: Connection> Clone for InternalTransfers<C> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            users: self.users.clone(),
+            assets: self.assets.clone(),
+            limits: self.limits.clone(),
+            security: self.security.clone(),
+        }
+    }
+}
+impl<C: Connection> InternalTransfers<C> {
+    /// Transfers within `limits`, blocking senders in the cooling-off period of
+    /// `security`.
+    pub fn new(db: Surreal<C>, limits: TransferLimits, security: WithdrawalLimits<C>) -> Self {
+        Self {
+            ledger: Ledger::new(db.clone()),
+            users: UserRepository::new(db.clone()),
+            assets: AssetRepository::new(db),
+            limits,
+            security,
+        }
+    }
+
+    pub async fn transfer(&self, request: &TransferRequest) -> Result<Transfer, TransferError> {
+        let reference = request
+            .idempotency_key
+            .as_ref()
+            .map(|key| format!("transfer:{}:{key}", request.sender_id));
+        if let Some(reference) = &reference {
+            if let Some(transfer) = self.replay(request, reference).await? {
+                return Ok(transfer);
+            }
+        }
+
+        if request.amount <= 0 {
+            return Err(TransferError::InvalidAmount);
+        }
+        if let Some(memo) = &request.memo {
+            if memo.chars().count() > MAX_MEMO_LENGTH {
+                return Err(TransferError::MemoTooLong(MAX_MEMO_LENGTH));
+            }
+        }
+        let recipient_id = self.recipient(&request.recipient).await?;
+        if recipient_id == request.sender_id {
+            return Err(TransferError::SelfTransfer);
+        }
+        if self.assets.get(&request.asset_id).await?.is_none() {
+            return Err(TransferError::AssetNotFound);
+        }
+        if let Some(until) = self.security.cooling_off_until(request.sender_id).await? {
+            return Err(TransferError::CoolingOff(until));
+        }
+        self.check_limits(request).await?;
+
+        let mut entry = JournalEntry::new(EntryKind::Transfer)
+            .debit(
+                Account::User(request.sender_id),
+                request.asset_id,
+                request.amount,
+            )
+            .credit(
+                Account::User(recipient_id),
+                request.asset_id,
+                request.amount,
+            );
+        entry.reference = reference.clone();
+        entry.memo = request.memo.clone();
+        let transfer = Transfer::from_entry(&entry).ok_or(TransferError::InvalidAmount)?;
+
+        match self
+            .ledger
+            .post_and_publish(&entry, TRANSFERS_CHANNEL, &transfer)
+            .await
+        {
+            Ok(_) => Ok(transfer),
+            // Another request with the same key won the race
+            Err(LedgerError::DuplicateReference(_)) => match &reference {
+                Some(reference) => self
+                    .replay(request, reference)
+                    .await?
+                    .ok_or(TransferError::IdempotencyConflict),
+                None => Err(TransferError::IdempotencyConflict),
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the transfer recorded under `reference`, if it was made for the same request.
+    async fn replay(
+        &self,
+        request: &TransferRequest,
+        reference: &str,
+    ) -> Result<Option<Transfer>, TransferError> {
+        let Some(entry) = self.ledger.entry_by_reference(reference).await? else {
+            return Ok(None);
+        };
+        let transfer = Transfer::from_entry(&entry).ok_or(TransferError::IdempotencyConflict)?;
+        let recipient_matches = match &request.recipient {
+            Recipient::Id(id) => *id == transfer.recipient_id,
+            Recipient::Handle(_) => {
+                self.recipient(&request.recipient).await? == transfer.recipient_id
+            }
+        };
+        if recipient_matches
+            && transfer.asset_id == request.asset_id
+            && transfer.amount == request.amount
+            && transfer.memo == request.memo
+        {
+            Ok(Some(transfer))
+        } else {
+            Err(TransferError::IdempotencyConflict)
+        }
+    }
+
+    async fn recipient(&self, recipient: &Recipient) -> Result<Uuid, TransferError> {
+        let user = match recipient {
+            Recipient::Id(id) => self.users.get(id).await?,
+            Recipient::Handle(handle) => self
+                .users
+                .list(&Filter::new().eq("handle", handle.as_str()).limit(1))
+                .await?
+                .pop(),
+        };
+        user.map(|user| user.id)
+            .ok_or(TransferError::RecipientNotFound)
+    }
+
+    async fn check_limits(&self, request: &TransferRequest) -> Result<(), TransferError> {
+        let Some(limit) = self.limits.get(&request.asset_id) else {
+            return Ok(());
+        };
+        if request.amount > limit.per_transfer {
+            return Err(TransferError::LimitExceeded(limit.per_transfer));
+        }
+        let until = Utc::now();
+        let sent: i64 = self
+            .ledger
+            .entries(
+                Account::User(request.sender_id),
+                until - Duration::days(1),
+                until,
+            )
+            .await?
+            .iter()
+            .filter_map(Transfer::from_entry)
+            .filter(|transfer| {
+                transfer.sender_id == request.sender_id && transfer.asset_id == request.asset_id
+            })
+            .map(|transfer| transfer.amount)
+            .sum();
+        if sent.saturating_add(request.amount) > limit.daily {
+            return Err(TransferError::LimitExceeded(limit.daily));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use database::{MemoryBroker, OutboxRelay, UserRepository};
+use models::UserRaw;
+use surrealdb::{engine::local::Db, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::TransferError,
+    models::{AssetLimit, Recipient, Transfer, TransferLimits, TransferRequest},
+    service::{InternalTransfers, TRANSFERS_CHANNEL},
+};
+use crate::{
+    ledger::{journal::Ledger, models::Account},
+    limits::models::SecurityEventKind,
+    tests::{add_asset, balance, deposit, ether, migrated, unlimited},
+};
+
+struct Fixture {
+    db: Surreal<Db>,
+    ledger: Ledger<Db>,
+    sender: Uuid,
+    recipient: Uuid,
+    asset_id: Uuid,
+}
+impl Fixture {
+    async fn new() -> Self {
+        let db = migrated().await;
+
+        let users = UserRepository::new(db.clone());
+        let (sender, recipient) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, handle) in [(sender, "sender"), (recipient, "recipient")] {
+            users
+                .create(&UserRaw {
+                    id,
+                    handle: Some(handle.to_string()),
+                })
+                .await
+                .unwrap();
+        }
+        let asset_id = Uuid::new_v4();
+        add_asset(&db, &ether(asset_id)).await;
+        let ledger = Ledger::new(db.clone());
+        deposit(&ledger, Account::User(sender), asset_id, 100).await;
+        Self {
+            db,
+            ledger,
+            sender,
+            recipient,
+            asset_id,
+        }
+    }
+
+    fn transfers(&self, limits: TransferLimits) -> InternalTransfers<Db> {
+        InternalTransfers::new(self.db.clone(), limits, unlimited(&self.db))
+    }
+
+    fn request(&self, recipient: Recipient, amount: i64) -> TransferRequest {
+        TransferRequest {
+            sender_id: self.sender,
+            recipient,
+            asset_id: self.asset_id,
+            amount,
+            memo: None,
+            idempotency_key: None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn transfers_move_funds_and_notify_the_recipient() {
+    let fixture = Fixture::new().await;
+    let mut request = fixture.request(Recipient::Handle("recipient".to_string()), 40);
+    request.memo = Some("lunch".to_string());
+
+    let transfer = fixture
+        .transfers(TransferLimits::new())
+        .transfer(&request)
+        .await
+        .unwrap();
+    assert_eq!(
+        (transfer.sender_id, transfer.recipient_id, transfer.amount),
+        (fixture.sender, fixture.recipient, 40)
+    );
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.sender),
+            fixture.asset_id
+        )
+        .await,
+        60
+    );
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.recipient),
+            fixture.asset_id
+        )
+        .await,
+        40
+    );
+
+    let messages = OutboxRelay::new(fixture.db.clone(), MemoryBroker::new())
+        .pending(10)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].channel, TRANSFERS_CHANNEL);
+    assert_eq!(
+        serde_json::from_value::<Transfer>(messages[0].payload.clone()).unwrap(),
+        transfer
+    );
+}
+
+#[tokio::test]
+async fn repeated_requests_transfer_once() {
+    let fixture = Fixture::new().await;
+    let transfers = fixture.transfers(TransferLimits::new());
+    let mut request = fixture.request(Recipient::Id(fixture.recipient), 30);
+    request.idempotency_key = Some("payment-1".to_string());
+
+    let first = transfers.transfer(&request).await.unwrap();
+    let second = transfers.transfer(&request).await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.sender),
+            fixture.asset_id
+        )
+        .await,
+        70
+    );
+
+    request.amount = 31;
+    assert!(matches!(
+        transfers.transfer(&request).await,
+        Err(TransferError::IdempotencyConflict)
+    ));
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.sender),
+            fixture.asset_id
+        )
+        .await,
+        70
+    );
+}
+
+#[tokio::test]
+async fn invalid_transfers_are_rejected() {
+    let fixture = Fixture::new().await;
+    let transfers = fixture.transfers(TransferLimits::new());
+    let recipient = Recipient::Id(fixture.recipient);
+
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(recipient.clone(), 0))
+            .await,
+        Err(TransferError::InvalidAmount)
+    ));
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(recipient.clone(), 101))
+            .await,
+        Err(TransferError::InsufficientBalance)
+    ));
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(Recipient::Id(fixture.sender), 1))
+            .await,
+        Err(TransferError::SelfTransfer)
+    ));
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(Recipient::Handle("nobody".to_string()), 1))
+            .await,
+        Err(TransferError::RecipientNotFound)
+    ));
+    let mut request = fixture.request(recipient.clone(), 1);
+    request.asset_id = Uuid::new_v4();
+    assert!(matches!(
+        transfers.transfer(&request).await,
+        Err(TransferError::AssetNotFound)
+    ));
+    let mut request = fixture.request(recipient, 1);
+    request.memo = Some("x".repeat(141));
+    assert!(matches!(
+        transfers.transfer(&request).await,
+        Err(TransferError::MemoTooLong(140))
+    ));
+
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.sender),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+}
+
+#[tokio::test]
+async fn limits_cap_single_and_daily_amounts() {
+    let fixture = Fixture::new().await;
+    let transfers = fixture.transfers(TransferLimits::new().asset(
+        fixture.asset_id,
+        AssetLimit {
+            per_transfer: 30,
+            daily: 50,
+        },
+    ));
+    let recipient = Recipient::Id(fixture.recipient);
+
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(recipient.clone(), 31))
+            .await,
+        Err(TransferError::LimitExceeded(30))
+    ));
+    transfers
+        .transfer(&fixture.request(recipient.clone(), 30))
+        .await
+        .unwrap();
+    assert!(matches!(
+        transfers
+            .transfer(&fixture.request(recipient.clone(), 21))
+            .await,
+        Err(TransferError::LimitExceeded(50))
+    ));
+    transfers
+        .transfer(&fixture.request(recipient, 20))
+        .await
+        .unwrap();
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.recipient),
+            fixture.asset_id
+        )
+        .await,
+        50
+    );
+}
+
+#[tokio::test]
+async fn senders_cannot_transfer_while_cooling_off() {
+    let fixture = Fixture::new().await;
+    let changed_at = unlimited(&fixture.db)
+        .record(fixture.sender, SecurityEventKind::PasswordChanged)
+        .await
+        .unwrap()
+        .created_at;
+
+    assert!(matches!(
+        fixture
+            .transfers(TransferLimits::new())
+            .transfer(&fixture.request(Recipient::Id(fixture.recipient), 10))
+            .await,
+        Err(TransferError::CoolingOff(blocked)) if blocked > changed_at
+    ));
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.sender),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+}
+
+#[test]
+fn limits_parse_from_configuration() {
+    let asset_id = Uuid::new_v4();
+    let limits: TransferLimits = format!("{asset_id}=30/50, ").parse().unwrap();
+    assert_eq!(
+        limits.get(&asset_id),
+        Some(&AssetLimit {
+            per_transfer: 30,
+            daily: 50
+        })
+    );
+    assert_eq!("".parse::<TransferLimits>().unwrap(), TransferLimits::new());
+    assert!(matches!(
+        format!("{asset_id}=30").parse::<TransferLimits>(),
+        Err(TransferError::InvalidLimits(_))
+    ));
+}
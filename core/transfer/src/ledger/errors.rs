@@ -0,0 +1,42 @@
+use database::DatabaseError;
+
+use super::models::Account;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("journal entry has no postings")]
+    EmptyEntry,
+
+    #[error("posting amount must be positive")]
+    InvalidAmount,
+
+    #[error("debits and credits of asset {0} do not balance")]
+    Unbalanced(uuid::Uuid),
+
+    #[error("invalid account: {0}")]
+    InvalidAccount(String),
+
+    #[error("entry {0} was recorded already")]
+    DuplicateReference(String),
+
+    #[error("insufficient balance")]
+    InsufficientBalance,
+
+    #[error("balance of {0} would overflow")]
+    Overflow(Account),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<surrealdb::Error> for LedgerError {
+    fn from(err: surrealdb::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
+
+impl From<serde_json::Error> for LedgerError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
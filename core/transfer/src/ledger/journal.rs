@@ -0,0 +1,552 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use database::{
+    from_rows, to_content, Actor, AuditCategory, AuditEvent, AuditLog, DatabaseError, Record,
+    CONFLICT, INSUFFICIENT_BALANCE, OUTBOX_TABLE, TRADES_CHANNEL,
+};
+use models::{BalanceRaw, TradeRaw};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{sql::Datetime, Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::LedgerError,
+    models::{Account, AccountBalance, Discrepancy, EntryKind, JournalEntry, Posting, Side},
+};
+
+pub const LEDGER_BALANCES_TABLE: &str = "ledger_balances";
+
+const REFERENCE_INDEX: &str = "journal_entries_reference";
+const VERIFY_BATCH_SIZE: usize = 1000;
+
+/// A SurrealQL statement or audit event committed together with a journal entry.
+///
+/// Its parameters share the namespace of the entry's own (`entries`, `ledger`, `balances`,
+/// `id`, `entry`, names ending in `_<index>` and those of the audit log starting with
+/// `audit`), so it must use other names. Those of [`Statement::create`] and
+/// [`Statement::replace`] are prefixed with the record's table, so one transaction may
+/// write one record of each table, while those of [`Statement::publish`] are prefixed
+/// with the message's id, so it may publish any number of messages. Throwing
+/// [`CONFLICT`] aborts the transaction with `DatabaseError::Conflict`.
+#[derive(Debug, Clone)]
+pub(crate) struct Statement {
+    query: String,
+    bindings: Vec<(String, Value)>,
+    event: Option<AuditEvent>,
+}
+impl Statement {
+    pub(crate) fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            bindings: Vec::new(),
+            event: None,
+        }
+    }
+
+    /// Appends `event` to the audit log.
+    pub(crate) fn audit(event: AuditEvent) -> Self {
+        Self {
+            event: Some(event),
+            ..Self::new("")
+        }
+    }
+
+    pub(crate) fn bind(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.bindings.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Inserts `record`.
+    pub(crate) fn create<M: Record>(record: &M) -> Result<Self, LedgerError> {
+        let table = M::TABLE;
+        Ok(Self::new(format!(
+            "CREATE type::thing(${table}_table, ${table}_id) CONTENT ${table}_content RETURN NONE;"
+        ))
+        .bind(&format!("{table}_table"), table)
+        .bind(&format!("{table}_id"), record.id().to_string())
+        .bind(&format!("{table}_content"), to_content(record)?))
+    }
+
+    /// Replaces `record` if it is still stored at the version it was read at, bumping
+    /// the version, and throws [`CONFLICT`] otherwise.
+    pub(crate) fn replace<M: Record>(record: &M) -> Result<Self, LedgerError> {
+        let version = record.version().unwrap_or_default();
+        let mut content = to_content(record)?;
+        if let Some(object) = content.as_object_mut() {
+            object.insert("version".to_string(), Value::from(version + 1));
+        }
+        let table = M::TABLE;
+        Ok(Self::new(format!(
+            "LET ${table}_updated = (UPDATE type::thing(${table}_table, ${table}_id) \
+             CONTENT ${table}_content WHERE version = ${table}_version RETURN AFTER);\n\
+             IF array::len(${table}_updated) = 0 {{ THROW '{CONFLICT}' }};"
+        ))
+        .bind(&format!("{table}_table"), table)
+        .bind(&format!("{table}_id"), record.id().to_string())
+        .bind(&format!("{table}_content"), content)
+        .bind(&format!("{table}_version"), version))
+    }
+
+    /// Adds `event` to the outbox for relaying on `channel`.
+    pub(crate) fn publish<E: Serialize>(channel: &str, event: &E) -> Result<Self, LedgerError> {
+        let id = Uuid::new_v4();
+        let message = format!("message_{}", id.simple());
+        Ok(Self::new(format!(
+            "CREATE type::thing(${message}_table, ${message}_id) SET channel = ${message}_channel, \
+             payload = ${message}_payload, created_at = time::now(), attempts = 0 RETURN NONE;"
+        ))
+        .bind(&format!("{message}_table"), OUTBOX_TABLE)
+        .bind(&format!("{message}_id"), id.to_string())
+        .bind(&format!("{message}_channel"), channel)
+        .bind(&format!("{message}_payload"), serde_json::to_value(event)?))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Postings {
+    id: Uuid,
+    postings: Vec<Posting>,
+    created_at: DateTime<Utc>,
+}
+
+/// Sum of the postings to one account on one side in one asset.
+#[derive(Debug, Deserialize)]
+struct SideTotal {
+    asset_id: Uuid,
+    side: Side,
+    amount: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Projection {
+    user_id: Uuid,
+    asset_id: Uuid,
+    value: i64,
+}
+
+/// Double-entry ledger persisted in SurrealDB.
+///
+/// Posting an entry records it, applies its postings to the balance of every account
+/// and appends it to the audit log in one transaction. Balances of user accounts are mirrored into the
+/// `balances` table, so `BalanceRaw` stays a projection of the ledger.
+///
+/// Every posting moves the head of the audit chain, so conflicting postings are retried
+/// with the audit log's policy rather than the shorter default one.
+#[derive(Debug)]
+pub struct Ledger<C: Connection> {
+    db: Surreal<C>,
+    audit: AuditLog<C>,
+}
+impl<C: Connection> Clone for Ledger<C> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            audit: self.audit.clone(),
+        }
+    }
+}
+impl<C: Connection> Ledger<C> {
+    pub fn new(db: Surreal<C>) -> Self {
+        Self {
+            audit: AuditLog::new(db.clone()),
+            db,
+        }
+    }
+
+    pub fn db(&self) -> &Surreal<C> {
+        &self.db
+    }
+
+    /// Records `entry`, failing with `InsufficientBalance` if an account other than
+    /// suspense would become negative and with `DuplicateReference` if its reference
+    /// was recorded before. Nothing is written when posting fails.
+    pub async fn post(&self, entry: &JournalEntry) -> Result<JournalEntry, LedgerError> {
+        self.post_with(entry, Vec::new()).await
+    }
+
+    /// Records `entry` like [`Ledger::post`] and, in the same transaction, adds `event`
+    /// to the outbox for relaying on `channel`.
+    pub async fn post_and_publish<E: Serialize>(
+        &self,
+        entry: &JournalEntry,
+        channel: &str,
+        event: &E,
+    ) -> Result<JournalEntry, LedgerError> {
+        self.post_with(entry, vec![Statement::publish(channel, event)?])
+            .await
+    }
+
+    /// Settles `trade`: the buyer pays the quote asset for the base asset of the seller.
+    /// The trade is stored and queued for relaying on `TRADES_CHANNEL` together with
+    /// the entry, which fails with `DuplicateReference` if the trade was settled before.
+    pub async fn post_trade(
+        &self,
+        trade: &TradeRaw,
+        buyer_id: Uuid,
+        seller_id: Uuid,
+    ) -> Result<JournalEntry, LedgerError> {
+        let (buyer, seller) = (Account::User(buyer_id), Account::User(seller_id));
+        let entry = JournalEntry::new(EntryKind::Trade)
+            .reference(format!("trade:{}", trade.id))
+            .debit(buyer, trade.quote_asset_id, trade.quote_asset_volume)
+            .credit(seller, trade.quote_asset_id, trade.quote_asset_volume)
+            .debit(seller, trade.base_asset_id, trade.base_asset_volume)
+            .credit(buyer, trade.base_asset_id, trade.base_asset_volume);
+        self.post_with(
+            &entry,
+            vec![
+                Statement::create(trade)?,
+                Statement::publish(TRADES_CHANNEL, trade)?,
+            ],
+        )
+        .await
+    }
+
+    /// Records `entry` like [`Ledger::post`] and runs `statements` after it in the same
+    /// transaction, so records describing the movement change together with it.
+    pub(crate) async fn post_with(
+        &self,
+        entry: &JournalEntry,
+        statements: Vec<Statement>,
+    ) -> Result<JournalEntry, LedgerError> {
+        entry.validate()?;
+        let (query, bindings) = render(entry)?;
+        let event = AuditEvent::new(
+            Actor::System,
+            AuditCategory::Balance,
+            format!("ledger.{}", entry.kind),
+        )
+        .record(None, Some(entry))?;
+        let statements = [vec![Statement::audit(event)], statements].concat();
+        match self.execute(query, bindings, statements).await {
+            Ok(()) => Ok(entry.clone()),
+            Err(DatabaseError::InsufficientBalance) => Err(LedgerError::InsufficientBalance),
+            Err(DatabaseError::Surrealdb(err)) => match err.as_ref() {
+                surrealdb::Error::Db(surrealdb::error::Db::IndexExists { index, .. })
+                    if index == REFERENCE_INDEX =>
+                {
+                    Err(LedgerError::DuplicateReference(
+                        entry.reference.clone().unwrap_or_default(),
+                    ))
+                }
+                _ => Err(DatabaseError::Surrealdb(err).into()),
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Runs `statements` in one transaction without posting an entry, for changes to
+    /// records that usually move together with one.
+    pub(crate) async fn commit(&self, statements: Vec<Statement>) -> Result<(), LedgerError> {
+        Ok(self.execute(String::new(), Vec::new(), statements).await?)
+    }
+
+    /// Runs `query` followed by `statements` in one transaction, appending the audit
+    /// events among them, and retries it on conflicts.
+    async fn execute(
+        &self,
+        mut query: String,
+        mut bindings: Vec<(String, Value)>,
+        statements: Vec<Statement>,
+    ) -> Result<(), DatabaseError> {
+        let mut events = Vec::new();
+        for statement in statements {
+            query.push_str(&statement.query);
+            query.push('\n');
+            bindings.extend(statement.bindings);
+            events.extend(statement.event);
+        }
+        self.audit.commit(&query, &bindings, &events).await?;
+        Ok(())
+    }
+
+    pub async fn entry(&self, id: &Uuid) -> Result<Option<JournalEntry>, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query("SELECT *, meta::id(id) AS id FROM type::thing($table, $id)")
+            .bind(("table", JournalEntry::TABLE))
+            .bind(("id", id.to_string()))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?.pop())
+    }
+
+    pub async fn entry_by_reference(
+        &self,
+        reference: &str,
+    ) -> Result<Option<JournalEntry>, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query("SELECT *, meta::id(id) AS id FROM type::table($table) WHERE reference = $reference")
+            .bind(("table", JournalEntry::TABLE))
+            .bind(("reference", reference))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?.pop())
+    }
+
+    /// Entries posting to `account` that were created in `[from, until)`, oldest first.
+    pub async fn entries(
+        &self,
+        account: Account,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<JournalEntry>, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT *, meta::id(id) AS id FROM type::table($table) \
+                 WHERE $account INSIDE accounts AND created_at >= $from AND created_at < $until \
+                 ORDER BY created_at",
+            )
+            .bind(("table", JournalEntry::TABLE))
+            .bind(("account", account.to_string()))
+            .bind(("from", Datetime::from(from)))
+            .bind(("until", Datetime::from(until)))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?)
+    }
+
+    /// Balances of `account` at `from` and the entries posting to it that were created
+    /// in `[from, until)`, oldest first. The balances are summed in the database from
+    /// the entries before `from`, so neither later entries nor the postings themselves
+    /// are read, and both are read in one transaction.
+    pub async fn period(
+        &self,
+        account: Account,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<(Vec<AccountBalance>, Vec<JournalEntry>), LedgerError> {
+        let mut response = self
+            .db
+            .query(
+                "BEGIN TRANSACTION;\n\
+                 SELECT asset_id, side, math::sum(amount) AS amount FROM array::flatten(( \
+                 SELECT VALUE postings[WHERE account = $account] FROM type::table($entries) \
+                 WHERE $account INSIDE accounts AND created_at < $from)) GROUP BY asset_id, side;\n\
+                 SELECT *, meta::id(id) AS id FROM type::table($entries) \
+                 WHERE $account INSIDE accounts AND created_at >= $from AND created_at < $until \
+                 ORDER BY created_at;\n\
+                 COMMIT TRANSACTION;",
+            )
+            .bind(("entries", JournalEntry::TABLE))
+            .bind(("account", account.to_string()))
+            .bind(("from", Datetime::from(from)))
+            .bind(("until", Datetime::from(until)))
+            .await?
+            .check()?;
+        let totals: Vec<SideTotal> = from_rows(response.take(0)?)?;
+        let mut balances: BTreeMap<Uuid, i64> = BTreeMap::new();
+        for total in totals {
+            let posting = Posting {
+                account,
+                asset_id: total.asset_id,
+                side: total.side,
+                amount: total.amount,
+            };
+            let balance = balances.entry(total.asset_id).or_default();
+            *balance = balance
+                .checked_add(posting.delta())
+                .ok_or(LedgerError::Overflow(account))?;
+        }
+        let balances = balances
+            .into_iter()
+            .map(|(asset_id, balance)| AccountBalance {
+                account,
+                asset_id,
+                balance,
+            })
+            .collect();
+        Ok((balances, from_rows(response.take(1)?)?))
+    }
+
+    pub async fn balance(&self, account: Account, asset_id: Uuid) -> Result<i64, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query("SELECT account, asset_id, balance FROM type::thing($table, [$account, $asset])")
+            .bind(("table", LEDGER_BALANCES_TABLE))
+            .bind(("account", account.to_string()))
+            .bind(("asset", asset_id.to_string()))
+            .await?
+            .take(0)?;
+        Ok(from_rows::<AccountBalance>(rows)?
+            .first()
+            .map_or(0, |row| row.balance))
+    }
+
+    pub async fn balances(&self, account: Account) -> Result<Vec<AccountBalance>, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT account, asset_id, balance FROM type::table($table) \
+                 WHERE account = $account ORDER BY asset_id",
+            )
+            .bind(("table", LEDGER_BALANCES_TABLE))
+            .bind(("account", account.to_string()))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?)
+    }
+
+    /// Balance of every account in every asset, ordered by account.
+    pub async fn trial_balance(&self) -> Result<Vec<AccountBalance>, LedgerError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT account, asset_id, balance FROM type::table($table) \
+                 ORDER BY account, asset_id",
+            )
+            .bind(("table", LEDGER_BALANCES_TABLE))
+            .await?
+            .take(0)?;
+        Ok(from_rows(rows)?)
+    }
+
+    /// Recomputes every balance from the journal and reports those that differ from
+    /// the recorded balance or, for users, from their `BalanceRaw`.
+    ///
+    /// The balances are read in one transaction together with the time the run starts,
+    /// and only entries created until then are summed, in pages that seek past the last
+    /// entry of the previous one. An entry that was still being committed at that time
+    /// can show up as a discrepancy that the next run no longer reports.
+    pub async fn verify(&self) -> Result<Vec<Discrepancy>, LedgerError> {
+        let mut response = self
+            .db
+            .query(
+                "BEGIN TRANSACTION;\n\
+                 RETURN time::now();\n\
+                 SELECT account, asset_id, balance FROM type::table($ledger);\n\
+                 SELECT user_id, asset_id, value FROM type::table($balances);\n\
+                 COMMIT TRANSACTION;",
+            )
+            .bind(("ledger", LEDGER_BALANCES_TABLE))
+            .bind(("balances", BalanceRaw::TABLE))
+            .await?
+            .check()?;
+        let started: Option<DateTime<Utc>> = response.take(0)?;
+        let started = Datetime::from(started.unwrap_or_else(Utc::now));
+        let recorded: BTreeMap<(Account, Uuid), i64> =
+            from_rows::<AccountBalance>(response.take(1)?)?
+                .into_iter()
+                .map(|row| ((row.account, row.asset_id), row.balance))
+                .collect();
+        let projected: BTreeMap<(Account, Uuid), i64> = from_rows::<Projection>(response.take(2)?)?
+            .into_iter()
+            .map(|row| ((Account::User(row.user_id), row.asset_id), row.value))
+            .collect();
+
+        let mut expected: BTreeMap<(Account, Uuid), i128> = BTreeMap::new();
+        let mut after: Option<(DateTime<Utc>, Uuid)> = None;
+        loop {
+            let condition = match after {
+                Some(_) => {
+                    " AND (created_at > <datetime> $after_time \
+                     OR (created_at = <datetime> $after_time AND meta::id(id) > $after_id))"
+                }
+                None => "",
+            };
+            let mut query = self
+                .db
+                .query(format!(
+                    "SELECT meta::id(id) AS id, postings, created_at FROM type::table($table) \
+                     WHERE created_at <= $started{condition} \
+                     ORDER BY created_at, id LIMIT {VERIFY_BATCH_SIZE}"
+                ))
+                .bind(("table", JournalEntry::TABLE))
+                .bind(("started", started.clone()));
+            if let Some((time, id)) = after {
+                query = query
+                    .bind(("after_time", Datetime::from(time)))
+                    .bind(("after_id", id.to_string()));
+            }
+            let rows: Vec<Value> = query.await?.take(0)?;
+            let batch: Vec<Postings> = from_rows(rows)?;
+            for posting in batch.iter().flat_map(|entry| &entry.postings) {
+                *expected
+                    .entry((posting.account, posting.asset_id))
+                    .or_default() += i128::from(posting.delta());
+            }
+            if batch.len() < VERIFY_BATCH_SIZE {
+                break;
+            }
+            after = batch.last().map(|entry| (entry.created_at, entry.id));
+        }
+
+        let keys: BTreeSet<_> = expected
+            .keys()
+            .chain(recorded.keys())
+            .chain(projected.keys())
+            .copied()
+            .collect();
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| {
+                let expected = expected.get(&key).copied().unwrap_or_default();
+                let recorded = recorded.get(&key).copied().unwrap_or_default();
+                let projected = key
+                    .0
+                    .user_id()
+                    .map(|_| projected.get(&key).copied().unwrap_or_default());
+                let matches = i128::from(recorded) == expected
+                    && projected.is_none_or(|projected| i128::from(projected) == expected);
+                (!matches).then(|| Discrepancy {
+                    account: key.0,
+                    asset_id: key.1,
+                    expected: i64::try_from(expected).unwrap_or(i64::MAX),
+                    recorded,
+                    projected,
+                })
+            })
+            .collect())
+    }
+}
+
+fn render(entry: &JournalEntry) -> Result<(String, Vec<(String, Value)>), LedgerError> {
+    let mut content = to_content(entry)?;
+    if let Some(object) = content.as_object_mut() {
+        let accounts: BTreeSet<Account> = entry.postings.iter().map(|p| p.account).collect();
+        object.insert("accounts".to_string(), serde_json::to_value(accounts)?);
+    }
+    let mut query = String::from("CREATE type::thing($entries, $id) CONTENT $entry RETURN NONE;\n");
+    let mut bindings = vec![
+        ("entries".to_string(), Value::from(JournalEntry::TABLE)),
+        ("ledger".to_string(), Value::from(LEDGER_BALANCES_TABLE)),
+        ("balances".to_string(), Value::from(BalanceRaw::TABLE)),
+        ("id".to_string(), Value::from(entry.id.to_string())),
+        ("entry".to_string(), content),
+    ];
+    for (index, ((account, asset_id), delta)) in entry.deltas()?.into_iter().enumerate() {
+        if delta == 0 {
+            continue;
+        }
+        bindings.push((format!("account_{index}"), Value::from(account.to_string())));
+        bindings.push((format!("asset_{index}"), Value::from(asset_id.to_string())));
+        bindings.push((format!("delta_{index}"), Value::from(delta)));
+        query.push_str(&format!(
+            "LET $ledger_{index} = (UPDATE type::thing($ledger, [$account_{index}, $asset_{index}]) \
+             SET account = $account_{index}, asset_id = $asset_{index}, balance += $delta_{index} RETURN AFTER);\n"
+        ));
+        if !account.allows_overdraft() {
+            query.push_str(&format!(
+                "IF $ledger_{index}[0].balance < 0 {{ THROW '{INSUFFICIENT_BALANCE}' }};\n"
+            ));
+        }
+        if let Some(user_id) = account.user_id() {
+            bindings.push((format!("user_{index}"), Value::from(user_id.to_string())));
+            bindings.push((
+                format!("projection_{index}"),
+                Value::from(Uuid::new_v4().to_string()),
+            ));
+            query.push_str(&format!(
+                "LET $balance_{index} = (UPDATE type::table($balances) SET value += $delta_{index}, version += 1 \
+                 WHERE user_id = $user_{index} AND asset_id = $asset_{index} RETURN AFTER);\n\
+                 IF array::len($balance_{index}) = 0 {{ CREATE type::thing($balances, $projection_{index}) \
+                 SET user_id = $user_{index}, asset_id = $asset_{index}, value = $delta_{index}, version = 0 }};\n"
+            ));
+        }
+    }
+    Ok((query, bindings))
+}
@@ -0,0 +1,6 @@
+pub mod errors;
+pub mod journal;
+pub mod models;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,252 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use database::Record;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::errors::LedgerError;
+
+/// An account of the ledger, stored as `user:<uuid>`, `fees`, `hot_wallet`, `withdrawals`
+/// or `suspense`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Account {
+    /// Funds owed to a user.
+    User(Uuid),
+    /// Fees earned by the exchange.
+    Fees,
+    /// Funds held on chain by the exchange's hot wallets.
+    HotWallet,
+    /// Funds held for withdrawals that have not been confirmed on chain yet.
+    Withdrawals,
+    /// Movements waiting to be explained, e.g. unmatched deposits.
+    Suspense,
+}
+impl Account {
+    /// Side on which postings increase the balance of the account.
+    pub fn normal_side(&self) -> Side {
+        match self {
+            Account::HotWallet => Side::Debit,
+            Account::User(_) | Account::Fees | Account::Withdrawals | Account::Suspense => {
+                Side::Credit
+            }
+        }
+    }
+
+    /// Whether the balance may become negative; only the suspense account may.
+    pub fn allows_overdraft(&self) -> bool {
+        matches!(self, Account::Suspense)
+    }
+
+    pub fn user_id(&self) -> Option<Uuid> {
+        match self {
+            Account::User(user_id) => Some(*user_id),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::User(user_id) => write!(f, "user:{user_id}"),
+            Account::Fees => f.write_str("fees"),
+            Account::HotWallet => f.write_str("hot_wallet"),
+            Account::Withdrawals => f.write_str("withdrawals"),
+            Account::Suspense => f.write_str("suspense"),
+        }
+    }
+}
+impl FromStr for Account {
+    type Err = LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fees" => Ok(Account::Fees),
+            "hot_wallet" => Ok(Account::HotWallet),
+            "withdrawals" => Ok(Account::Withdrawals),
+            "suspense" => Ok(Account::Suspense),
+            _ => s
+                .strip_prefix("user:")
+                .and_then(|user_id| Uuid::parse_str(user_id).ok())
+                .map(Account::User)
+                .ok_or_else(|| LedgerError::InvalidAccount(s.to_string())),
+        }
+    }
+}
+impl From<Account> for String {
+    fn from(account: Account) -> Self {
+        account.to_string()
+    }
+}
+impl TryFrom<String> for Account {
+    type Error = LedgerError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Debit,
+    Credit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Posting {
+    pub account: Account,
+    pub asset_id: Uuid,
+    pub side: Side,
+    pub amount: i64,
+}
+impl Posting {
+    /// Change of the account balance caused by this posting.
+    pub fn delta(&self) -> i64 {
+        if self.side == self.account.normal_side() {
+            self.amount
+        } else {
+            -self.amount
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Deposit,
+    Withdrawal,
+    Transfer,
+    Trade,
+    Fee,
+    Adjustment,
+}
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EntryKind::Deposit => "deposit",
+            EntryKind::Withdrawal => "withdrawal",
+            EntryKind::Transfer => "transfer",
+            EntryKind::Trade => "trade",
+            EntryKind::Fee => "fee",
+            EntryKind::Adjustment => "adjustment",
+        })
+    }
+}
+
+/// A movement of funds recorded as postings whose debits and credits balance per asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub kind: EntryKind,
+    /// Unique key of the movement, e.g. a transaction hash, so it is recorded only once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub postings: Vec<Posting>,
+    pub created_at: DateTime<Utc>,
+}
+impl JournalEntry {
+    pub fn new(kind: EntryKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            reference: None,
+            memo: None,
+            postings: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn debit(self, account: Account, asset_id: Uuid, amount: i64) -> Self {
+        self.post(account, asset_id, Side::Debit, amount)
+    }
+
+    pub fn credit(self, account: Account, asset_id: Uuid, amount: i64) -> Self {
+        self.post(account, asset_id, Side::Credit, amount)
+    }
+
+    fn post(mut self, account: Account, asset_id: Uuid, side: Side, amount: i64) -> Self {
+        self.postings.push(Posting {
+            account,
+            asset_id,
+            side,
+            amount,
+        });
+        self
+    }
+
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Checks that every amount is positive and that debits equal credits per asset.
+    pub fn validate(&self) -> Result<(), LedgerError> {
+        if self.postings.is_empty() {
+            return Err(LedgerError::EmptyEntry);
+        }
+        let mut totals: BTreeMap<Uuid, i128> = BTreeMap::new();
+        for posting in &self.postings {
+            if posting.amount <= 0 {
+                return Err(LedgerError::InvalidAmount);
+            }
+            let amount = i128::from(posting.amount);
+            *totals.entry(posting.asset_id).or_default() += match posting.side {
+                Side::Debit => amount,
+                Side::Credit => -amount,
+            };
+        }
+        match totals.into_iter().find(|(_, total)| *total != 0) {
+            Some((asset_id, _)) => Err(LedgerError::Unbalanced(asset_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Net change of every account balance touched by the entry.
+    pub fn deltas(&self) -> Result<BTreeMap<(Account, Uuid), i64>, LedgerError> {
+        let mut deltas: BTreeMap<(Account, Uuid), i64> = BTreeMap::new();
+        for posting in &self.postings {
+            let delta = deltas
+                .entry((posting.account, posting.asset_id))
+                .or_default();
+            *delta = delta
+                .checked_add(posting.delta())
+                .ok_or(LedgerError::Overflow(posting.account))?;
+        }
+        Ok(deltas)
+    }
+}
+impl Record for JournalEntry {
+    const TABLE: &'static str = "journal_entries";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Balance of an account in one asset as maintained by the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub account: Account,
+    pub asset_id: Uuid,
+    pub balance: i64,
+}
+
+/// A balance that does not match the sum of its postings.
+///
+/// `projected` is the user's `BalanceRaw` value, which must match as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub account: Account,
+    pub asset_id: Uuid,
+    pub expected: i64,
+    pub recorded: i64,
+    pub projected: Option<i64>,
+}
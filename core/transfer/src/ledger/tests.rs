@@ -0,0 +1,441 @@
+use chrono::Utc;
+use database::{
+    AuditCategory, AuditLog, BalanceRepository, Filter, Migrator, OutboxRelay, Record,
+    TradeRepository, TRADES_CHANNEL,
+};
+use models::{BalanceRaw, TradeRaw};
+use surrealdb::engine::local::Db;
+use uuid::Uuid;
+
+use super::{
+    errors::LedgerError,
+    journal::{Ledger, Statement},
+    models::{Account, EntryKind, JournalEntry},
+};
+use crate::tests::{deposit_entry, memory, migrated};
+
+async fn ledger() -> Ledger<Db> {
+    Ledger::new(migrated().await)
+}
+
+async fn projection(ledger: &Ledger<Db>, user_id: Uuid) -> Vec<BalanceRaw> {
+    BalanceRepository::new(ledger.db().clone())
+        .list(&Filter::new().eq("user_id", user_id))
+        .await
+        .unwrap()
+}
+
+#[test]
+fn accounts_round_trip_as_strings() {
+    let user_id = Uuid::new_v4();
+    for account in [
+        Account::User(user_id),
+        Account::Fees,
+        Account::HotWallet,
+        Account::Withdrawals,
+        Account::Suspense,
+    ] {
+        assert_eq!(account.to_string().parse::<Account>().unwrap(), account);
+    }
+    assert_eq!(
+        Account::User(user_id).to_string(),
+        format!("user:{user_id}")
+    );
+    assert!(matches!(
+        "user:42".parse::<Account>(),
+        Err(LedgerError::InvalidAccount(_))
+    ));
+}
+
+#[test]
+fn entries_must_balance_per_asset() {
+    let (user_id, eth, usdc) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    assert!(matches!(
+        JournalEntry::new(EntryKind::Adjustment).validate(),
+        Err(LedgerError::EmptyEntry)
+    ));
+    assert!(matches!(
+        JournalEntry::new(EntryKind::Deposit)
+            .debit(Account::HotWallet, eth, 0)
+            .credit(Account::User(user_id), eth, 0)
+            .validate(),
+        Err(LedgerError::InvalidAmount)
+    ));
+    assert!(matches!(
+        JournalEntry::new(EntryKind::Deposit)
+            .debit(Account::HotWallet, eth, 10)
+            .credit(Account::User(user_id), usdc, 10)
+            .validate(),
+        Err(LedgerError::Unbalanced(_))
+    ));
+    assert!(deposit_entry(Account::User(user_id), eth, 10)
+        .validate()
+        .is_ok());
+}
+
+#[tokio::test]
+async fn postings_move_balances_and_their_projection() {
+    let ledger = ledger().await;
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    ledger
+        .post(&deposit_entry(Account::User(user_id), asset_id, 100))
+        .await
+        .unwrap();
+    let withdrawal = JournalEntry::new(EntryKind::Withdrawal)
+        .debit(Account::User(user_id), asset_id, 30)
+        .credit(Account::HotWallet, asset_id, 28)
+        .credit(Account::Fees, asset_id, 2);
+    ledger.post(&withdrawal).await.unwrap();
+
+    assert_eq!(
+        ledger
+            .balance(Account::User(user_id), asset_id)
+            .await
+            .unwrap(),
+        70
+    );
+    assert_eq!(
+        ledger.balance(Account::HotWallet, asset_id).await.unwrap(),
+        72
+    );
+    assert_eq!(ledger.balance(Account::Fees, asset_id).await.unwrap(), 2);
+    assert_eq!(
+        ledger.balance(Account::Suspense, asset_id).await.unwrap(),
+        0
+    );
+    let projection = projection(&ledger, user_id).await;
+    assert_eq!(projection.len(), 1);
+    assert_eq!((projection[0].value, projection[0].version), (70, 1));
+
+    assert_eq!(
+        ledger.entry(&withdrawal.id).await.unwrap(),
+        Some(withdrawal)
+    );
+    assert!(ledger.verify().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn overdrafts_are_rejected_without_side_effects() {
+    let ledger = ledger().await;
+    let (sender, recipient, asset_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    ledger
+        .post(&deposit_entry(Account::User(sender), asset_id, 10))
+        .await
+        .unwrap();
+
+    let transfer = JournalEntry::new(EntryKind::Transfer)
+        .debit(Account::User(sender), asset_id, 11)
+        .credit(Account::User(recipient), asset_id, 11);
+    assert!(matches!(
+        ledger.post(&transfer).await,
+        Err(LedgerError::InsufficientBalance)
+    ));
+
+    assert_eq!(ledger.entry(&transfer.id).await.unwrap(), None);
+    assert_eq!(
+        ledger
+            .balance(Account::User(sender), asset_id)
+            .await
+            .unwrap(),
+        10
+    );
+    assert!(projection(&ledger, recipient).await.is_empty());
+
+    // The suspense account may go negative until the movement is explained.
+    ledger
+        .post(
+            &JournalEntry::new(EntryKind::Adjustment)
+                .debit(Account::Suspense, asset_id, 5)
+                .credit(Account::User(recipient), asset_id, 5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        ledger.balance(Account::Suspense, asset_id).await.unwrap(),
+        -5
+    );
+}
+
+#[tokio::test]
+async fn references_are_recorded_once() {
+    let ledger = ledger().await;
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let entry = deposit_entry(Account::User(user_id), asset_id, 10).reference("0xabc");
+    ledger.post(&entry).await.unwrap();
+
+    assert!(matches!(
+        ledger
+            .post(&deposit_entry(Account::User(user_id), asset_id, 10).reference("0xabc"))
+            .await,
+        Err(LedgerError::DuplicateReference(reference)) if reference == "0xabc"
+    ));
+    assert_eq!(
+        ledger.entry_by_reference("0xabc").await.unwrap(),
+        Some(entry)
+    );
+    assert_eq!(
+        ledger
+            .balance(Account::User(user_id), asset_id)
+            .await
+            .unwrap(),
+        10
+    );
+}
+
+#[tokio::test]
+async fn verification_reports_balances_changed_outside_the_ledger() {
+    let ledger = ledger().await;
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    ledger
+        .post(&deposit_entry(Account::User(user_id), asset_id, 100))
+        .await
+        .unwrap();
+
+    ledger
+        .db()
+        .query("UPDATE type::table($table) SET value = 1000 WHERE user_id = $user_id")
+        .bind(("table", BalanceRaw::TABLE))
+        .bind(("user_id", user_id.to_string()))
+        .await
+        .unwrap();
+
+    let discrepancies = ledger.verify().await.unwrap();
+    assert_eq!(discrepancies.len(), 1);
+    assert_eq!(discrepancies[0].account, Account::User(user_id));
+    assert_eq!(
+        (
+            discrepancies[0].expected,
+            discrepancies[0].recorded,
+            discrepancies[0].projected
+        ),
+        (100, 100, Some(1000))
+    );
+}
+
+#[tokio::test]
+async fn existing_balances_are_opened_against_suspense() {
+    let db = memory().await;
+    let embedded = Migrator::embedded();
+    let (before, after): (Vec<_>, Vec<_>) = embedded
+        .migrations()
+        .iter()
+        .cloned()
+        .partition(|migration| migration.version < 7);
+    Migrator::new(before)
+        .unwrap()
+        .run(&db, false)
+        .await
+        .unwrap();
+
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    db.query("CREATE type::table($table) SET user_id = $user_id, asset_id = $asset_id, value = 40, version = 0")
+        .bind(("table", BalanceRaw::TABLE))
+        .bind(("user_id", user_id.to_string()))
+        .bind(("asset_id", asset_id.to_string()))
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    Migrator::new(after).unwrap().run(&db, false).await.unwrap();
+
+    let ledger = Ledger::new(db);
+    assert_eq!(
+        ledger
+            .balance(Account::User(user_id), asset_id)
+            .await
+            .unwrap(),
+        40
+    );
+    assert_eq!(
+        ledger.balance(Account::Suspense, asset_id).await.unwrap(),
+        -40
+    );
+    assert!(ledger.verify().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn trades_are_settled_through_the_ledger() {
+    let ledger = ledger().await;
+    let (buyer, seller) = (Uuid::new_v4(), Uuid::new_v4());
+    let trade = TradeRaw {
+        id: Uuid::new_v4(),
+        base_asset_id: Uuid::new_v4(),
+        base_asset_volume: 10,
+        quote_asset_id: Uuid::new_v4(),
+        quote_asset_volume: 200,
+        created_at: Utc::now(),
+    };
+    ledger
+        .post(&deposit_entry(
+            Account::User(buyer),
+            trade.quote_asset_id,
+            500,
+        ))
+        .await
+        .unwrap();
+    ledger
+        .post(&deposit_entry(
+            Account::User(seller),
+            trade.base_asset_id,
+            10,
+        ))
+        .await
+        .unwrap();
+
+    let entry = ledger.post_trade(&trade, buyer, seller).await.unwrap();
+    assert_eq!(entry.kind, EntryKind::Trade);
+    for (account, asset_id, expected) in [
+        (buyer, trade.base_asset_id, 10),
+        (buyer, trade.quote_asset_id, 300),
+        (seller, trade.base_asset_id, 0),
+        (seller, trade.quote_asset_id, 200),
+    ] {
+        assert_eq!(
+            ledger
+                .balance(Account::User(account), asset_id)
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+    assert_eq!(
+        TradeRepository::new(ledger.db().clone())
+            .get(&trade.id)
+            .await
+            .unwrap(),
+        Some(trade.clone())
+    );
+    let pending = OutboxRelay::new(ledger.db().clone(), database::MemoryBroker::new())
+        .pending(10)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].channel, TRADES_CHANNEL);
+    assert!(ledger.verify().await.unwrap().is_empty());
+
+    assert!(matches!(
+        ledger.post_trade(&trade, buyer, seller).await,
+        Err(LedgerError::DuplicateReference(_))
+    ));
+    let mut unfunded = trade.clone();
+    unfunded.id = Uuid::new_v4();
+    assert!(matches!(
+        ledger.post_trade(&unfunded, buyer, seller).await,
+        Err(LedgerError::InsufficientBalance)
+    ));
+    assert_eq!(
+        TradeRepository::new(ledger.db().clone())
+            .get(&unfunded.id)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn one_transaction_publishes_several_messages() {
+    let ledger = ledger().await;
+    ledger
+        .commit(vec![
+            Statement::publish("first", &1).unwrap(),
+            Statement::publish("second", &2).unwrap(),
+        ])
+        .await
+        .unwrap();
+
+    let mut pending = OutboxRelay::new(ledger.db().clone(), database::MemoryBroker::new())
+        .pending(10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|message| (message.channel, message.payload))
+        .collect::<Vec<_>>();
+    pending.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        pending,
+        [
+            ("first".to_string(), serde_json::json!(1)),
+            ("second".to_string(), serde_json::json!(2)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn entries_are_looked_up_by_account_index() {
+    let ledger = ledger().await;
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    ledger
+        .post(&deposit_entry(Account::User(user_id), asset_id, 100))
+        .await
+        .unwrap();
+    ledger
+        .post(&deposit_entry(Account::User(Uuid::new_v4()), asset_id, 50))
+        .await
+        .unwrap();
+
+    let from = Utc::now() - chrono::Duration::hours(1);
+    let until = Utc::now() + chrono::Duration::hours(1);
+    let entries = ledger
+        .entries(Account::User(user_id), from, until)
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].postings[1].account, Account::User(user_id));
+
+    let plan: Vec<serde_json::Value> = ledger
+        .db()
+        .query("SELECT * FROM type::table($table) WHERE $account INSIDE accounts EXPLAIN")
+        .bind(("table", JournalEntry::TABLE))
+        .bind(("account", Account::User(user_id).to_string()))
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    assert!(plan
+        .iter()
+        .any(|step| { step["detail"]["plan"]["index"] == "journal_entries_accounts" }));
+}
+
+#[tokio::test]
+async fn postings_are_audited_in_the_same_transaction() {
+    let ledger = ledger().await;
+    let log = AuditLog::new(ledger.db().clone());
+    let (user_id, asset_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let entry = ledger
+        .post(&deposit_entry(Account::User(user_id), asset_id, 100))
+        .await
+        .unwrap();
+
+    let trail = log.list(0, 10).await.unwrap();
+    assert_eq!(trail.len(), 1);
+    assert_eq!(
+        (trail[0].event.category, trail[0].event.action.as_str()),
+        (AuditCategory::Balance, "ledger.deposit")
+    );
+    assert_eq!(
+        trail[0].event.target,
+        Some(format!("{}:{}", JournalEntry::TABLE, entry.id))
+    );
+
+    // An entry whose audit append fails is not posted either
+    ledger
+        .db()
+        .query("DEFINE FIELD action ON audit_log TYPE string ASSERT $value != 'ledger.deposit'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    assert!(ledger
+        .post(&deposit_entry(Account::User(user_id), asset_id, 50))
+        .await
+        .is_err());
+    assert_eq!(
+        ledger
+            .balance(Account::User(user_id), asset_id)
+            .await
+            .unwrap(),
+        100
+    );
+    assert_eq!(log.verify().await.unwrap().length, 1);
+}
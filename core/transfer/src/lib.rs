@@ -1,14 +1,11 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
-}
+pub mod approval;
+pub mod deposit;
+pub mod internal;
+pub mod ledger;
+pub mod limits;
+pub mod reconciliation;
+pub mod statement;
+pub mod withdrawal;
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+mod tests;
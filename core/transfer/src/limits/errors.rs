@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use database::DatabaseError;
+
+use super::models::LimitWindow;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LimitError {
+    #[error("withdrawals are blocked until {0} after a security change")]
+    CoolingOff(DateTime<Utc>),
+
+    #[error("{window} withdrawal limit exceeded, {remaining} remaining")]
+    Exceeded { window: LimitWindow, remaining: i64 },
+
+    #[error("at most {max} withdrawals are allowed per {window} window")]
+    TooFrequent { window: LimitWindow, max: usize },
+
+    #[error("no reference price for asset")]
+    PriceUnavailable,
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<surrealdb::Error> for LimitError {
+    fn from(err: surrealdb::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
+
+impl From<serde_json::Error> for LimitError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use database::Record;
+use models::Fraction;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Hours withdrawals stay blocked after a security change unless configured otherwise.
+pub const DEFAULT_COOLING_OFF_HOURS: i64 = 24;
+
+/// A change to a user's credentials or withdrawal addresses, after which withdrawals
+/// are blocked for the cooling-off period so a compromised account cannot be drained
+/// right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    PasswordChanged,
+    TwoFactorChanged,
+    AddressChanged,
+}
+impl fmt::Display for SecurityEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SecurityEventKind::PasswordChanged => "password_changed",
+            SecurityEventKind::TwoFactorChanged => "two_factor_changed",
+            SecurityEventKind::AddressChanged => "address_changed",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: SecurityEventKind,
+    /// Withdrawal address the user changed to, for `AddressChanged`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+impl Record for SecurityEvent {
+    const TABLE: &'static str = "security_events";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Rolling period over which withdrawals are added up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitWindow {
+    /// The last 24 hours.
+    Daily,
+    /// The last 30 days.
+    Monthly,
+}
+impl LimitWindow {
+    pub fn duration(&self) -> Duration {
+        match self {
+            LimitWindow::Daily => Duration::days(1),
+            LimitWindow::Monthly => Duration::days(30),
+        }
+    }
+}
+impl fmt::Display for LimitWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LimitWindow::Daily => "daily",
+            LimitWindow::Monthly => "monthly",
+        })
+    }
+}
+
+/// Maximum value withdrawn per window, in the smallest unit of the reference currency.
+/// A missing window is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limit {
+    pub daily: Option<i64>,
+    pub monthly: Option<i64>,
+}
+impl Limit {
+    pub fn new(daily: i64, monthly: i64) -> Self {
+        Self {
+            daily: Some(daily),
+            monthly: Some(monthly),
+        }
+    }
+
+    pub fn get(&self, window: LimitWindow) -> Option<i64> {
+        match window {
+            LimitWindow::Daily => self.daily,
+            LimitWindow::Monthly => self.monthly,
+        }
+    }
+}
+
+/// Limits of a single user, stored under the user's id. The user's tier replaces the
+/// default one, and a window set here overrides the tier's limit for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserLimits {
+    pub id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<i64>,
+    pub updated_at: DateTime<Utc>,
+}
+impl UserLimits {
+    pub fn new(user_id: Uuid) -> Self {
+        Self {
+            id: user_id,
+            tier: None,
+            daily: None,
+            monthly: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn tier(mut self, tier: impl Into<String>) -> Self {
+        self.tier = Some(tier.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: Limit) -> Self {
+        self.daily = limit.daily;
+        self.monthly = limit.monthly;
+        self
+    }
+}
+impl Record for UserLimits {
+    const TABLE: &'static str = "withdrawal_limits";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Withdrawal limits of the exchange.
+///
+/// Every user belongs to a tier whose limit caps the value of all their withdrawals
+/// together, while asset limits cap the value each user withdraws of a single asset.
+/// Unknown tiers are unlimited, so the default tier should always be configured.
+/// Velocity rules cap the number of withdrawals a user makes per window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitPolicy {
+    default_tier: String,
+    tiers: HashMap<String, Limit>,
+    assets: HashMap<Uuid, Limit>,
+    counts: HashMap<LimitWindow, usize>,
+    cooling_off: Duration,
+}
+impl LimitPolicy {
+    pub fn new(default_tier: impl Into<String>) -> Self {
+        Self {
+            default_tier: default_tier.into(),
+            tiers: HashMap::new(),
+            assets: HashMap::new(),
+            counts: HashMap::new(),
+            cooling_off: Duration::hours(DEFAULT_COOLING_OFF_HOURS),
+        }
+    }
+
+    pub fn tier(mut self, name: impl Into<String>, limit: Limit) -> Self {
+        self.tiers.insert(name.into(), limit);
+        self
+    }
+
+    pub fn asset(mut self, asset_id: Uuid, limit: Limit) -> Self {
+        self.assets.insert(asset_id, limit);
+        self
+    }
+
+    /// Allows at most `count` withdrawals per `window`.
+    pub fn max_withdrawals(mut self, window: LimitWindow, count: usize) -> Self {
+        self.counts.insert(window, count);
+        self
+    }
+
+    pub fn cooling_off(mut self, cooling_off: Duration) -> Self {
+        self.cooling_off = cooling_off;
+        self
+    }
+
+    pub fn cooling_off_period(&self) -> Duration {
+        self.cooling_off
+    }
+
+    /// Limit on the total value a user may withdraw, given their stored limits if any.
+    pub fn user_limit(&self, user: Option<&UserLimits>) -> Limit {
+        let tier = user
+            .and_then(|user| user.tier.as_deref())
+            .unwrap_or(&self.default_tier);
+        let limit = self.tiers.get(tier).copied().unwrap_or_default();
+        Limit {
+            daily: user.and_then(|user| user.daily).or(limit.daily),
+            monthly: user.and_then(|user| user.monthly).or(limit.monthly),
+        }
+    }
+
+    pub fn asset_limit(&self, asset_id: &Uuid) -> Limit {
+        self.assets.get(asset_id).copied().unwrap_or_default()
+    }
+
+    /// Number of withdrawals allowed per `window`, or `None` if it is not capped.
+    pub fn count_limit(&self, window: LimitWindow) -> Option<usize> {
+        self.counts.get(&window).copied()
+    }
+}
+
+/// Prices of assets in the reference currency, shared with whatever keeps them current.
+///
+/// A price is the value of one smallest unit of the asset in smallest units of the
+/// reference currency, e.g. cents per wei.
+#[derive(Debug, Clone, Default)]
+pub struct ReferencePrices {
+    prices: Arc<RwLock<HashMap<Uuid, Fraction>>>,
+}
+impl ReferencePrices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, asset_id: Uuid, price: Fraction) {
+        self.prices
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(asset_id, price);
+    }
+
+    pub fn get(&self, asset_id: &Uuid) -> Option<Fraction> {
+        self.prices
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(asset_id)
+            .cloned()
+    }
+
+    /// Value of `amount` of an asset, rounded up, or `None` if the asset has no price
+    /// or the value does not fit.
+    pub fn value(&self, asset_id: &Uuid, amount: i64) -> Option<i64> {
+        let value = Fraction::from(amount) * self.get(asset_id)?;
+        i64::try_from(value.ceil().to_integer()).ok()
+    }
+}
@@ -0,0 +1,284 @@
+use chrono::{DateTime, Utc};
+use database::{DatabaseError, Direction, Filter, Record, Repository};
+use serde_json::Value;
+use surrealdb::{sql::Datetime, Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::LimitError,
+    models::{
+        Limit, LimitPolicy, LimitWindow, ReferencePrices, SecurityEvent, SecurityEventKind,
+        UserLimits,
+    },
+};
+use crate::withdrawal::models::{Withdrawal, WithdrawalRequest, WithdrawalStatus};
+
+const WINDOWS: [LimitWindow; 2] = [LimitWindow::Daily, LimitWindow::Monthly];
+
+/// Enforces withdrawal limits, velocity rules and the cooling-off period after security
+/// changes.
+///
+/// Withdrawals count towards the limits from the moment they are requested until they
+/// fail or are cancelled, valued at current reference prices. The checks do not lock
+/// anything, so concurrent requests of one user may overshoot a limit by the last one.
+#[derive(Debug)]
+pub struct WithdrawalLimits<C: Connection> {
+    db: Surreal<C>,
+    events: Repository<C, SecurityEvent>,
+    users: Repository<C, UserLimits>,
+    policy: LimitPolicy,
+    prices: ReferencePrices,
+}
+impl<C: Connection> Clone for WithdrawalLimits<C> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            events: self.events.clone(),
+            users: self.users.clone(),
+            policy: self.policy.clone(),
+            prices: self.prices.clone(),
+        }
+    }
+}
+impl<C: Connection> WithdrawalLimits<C> {
+    pub fn new(db: Surreal<C>, policy: LimitPolicy, prices: ReferencePrices) -> Self {
+        Self {
+            events: Repository::new(db.clone()),
+            users: Repository::new(db.clone()),
+            db,
+            policy,
+            prices,
+        }
+    }
+
+    /// Records a security change of a user, starting their cooling-off period. To be
+    /// called whenever a user changes their password or second factor.
+    pub async fn record(
+        &self,
+        user_id: Uuid,
+        kind: SecurityEventKind,
+    ) -> Result<SecurityEvent, LimitError> {
+        self.create_event(user_id, kind, None).await
+    }
+
+    /// Records that a user changed their withdrawal address to `address`, starting their
+    /// cooling-off period. From then on `address` is known and withdrawing to it does not
+    /// count as another change.
+    pub async fn record_address(
+        &self,
+        user_id: Uuid,
+        address: &str,
+    ) -> Result<SecurityEvent, LimitError> {
+        self.create_event(
+            user_id,
+            SecurityEventKind::AddressChanged,
+            Some(address.trim().to_string()),
+        )
+        .await
+    }
+
+    async fn create_event(
+        &self,
+        user_id: Uuid,
+        kind: SecurityEventKind,
+        address: Option<String>,
+    ) -> Result<SecurityEvent, LimitError> {
+        Ok(self
+            .events
+            .create(&SecurityEvent {
+                id: Uuid::new_v4(),
+                user_id,
+                kind,
+                address,
+                created_at: Utc::now(),
+            })
+            .await?)
+    }
+
+    /// End of the user's cooling-off period, or `None` if they may withdraw.
+    pub async fn cooling_off_until(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<DateTime<Utc>>, LimitError> {
+        let latest = self
+            .events
+            .list(
+                &Filter::new()
+                    .eq("user_id", user_id)
+                    .order_by("created_at", Direction::Desc)
+                    .limit(1),
+            )
+            .await?
+            .pop();
+        Ok(latest
+            .map(|event| event.created_at + self.policy.cooling_off_period())
+            .filter(|until| *until > Utc::now()))
+    }
+
+    pub async fn user_limits(&self, user_id: Uuid) -> Result<Option<UserLimits>, LimitError> {
+        Ok(self.users.get(&user_id).await?)
+    }
+
+    /// Stores the tier and overrides of a user, replacing earlier ones.
+    pub async fn set_user_limits(&self, limits: &UserLimits) -> Result<UserLimits, LimitError> {
+        let limits = UserLimits {
+            updated_at: Utc::now(),
+            ..limits.clone()
+        };
+        match self.users.update(&limits).await {
+            Err(DatabaseError::NotFound) => Ok(self.users.create(&limits).await?),
+            result => Ok(result?),
+        }
+    }
+
+    /// Limit on the total value the user may withdraw.
+    pub async fn limit(&self, user_id: Uuid) -> Result<Limit, LimitError> {
+        let user = self.user_limits(user_id).await?;
+        Ok(self.policy.user_limit(user.as_ref()))
+    }
+
+    /// Value of the user's withdrawals within `window`.
+    pub async fn usage(&self, user_id: Uuid, window: LimitWindow) -> Result<i64, LimitError> {
+        let since = Utc::now() - window.duration();
+        self.value_of(&self.recent(user_id, since).await?, |_| true)
+    }
+
+    /// Fails with `CoolingOff`, `TooFrequent` or `Exceeded` if the user may not make
+    /// the withdrawal.
+    ///
+    /// A withdrawal to an address the user has neither withdrawn to before nor recorded
+    /// with [`Self::record_address`] is recorded as an address change and so starts the
+    /// cooling-off period. That includes the first withdrawal of a user, whose address
+    /// should therefore be recorded ahead of it.
+    ///
+    /// The check reads the user's recent withdrawals without locking them, so it must
+    /// not be relied on to be atomic with recording the withdrawal: concurrent requests
+    /// of one user can each pass it, and together overshoot a limit by the last one.
+    pub async fn check(&self, request: &WithdrawalRequest) -> Result<(), LimitError> {
+        if self.is_new_address(request).await? {
+            self.record_address(request.user_id, &request.address)
+                .await?;
+        }
+        if let Some(until) = self.cooling_off_until(request.user_id).await? {
+            return Err(LimitError::CoolingOff(until));
+        }
+
+        let now = Utc::now();
+        let recent = self
+            .recent(request.user_id, now - LimitWindow::Monthly.duration())
+            .await?;
+        for window in WINDOWS {
+            if let Some(max) = self.policy.count_limit(window) {
+                let since = now - window.duration();
+                let count = recent
+                    .iter()
+                    .filter(|withdrawal| withdrawal.created_at >= since)
+                    .count();
+                if count >= max {
+                    return Err(LimitError::TooFrequent { window, max });
+                }
+            }
+        }
+
+        let user_limit = self.limit(request.user_id).await?;
+        let asset_limit = self.policy.asset_limit(&request.asset_id);
+        if user_limit == Limit::default() && asset_limit == Limit::default() {
+            return Ok(());
+        }
+        let value = self
+            .prices
+            .value(&request.asset_id, request.amount)
+            .ok_or(LimitError::PriceUnavailable)?;
+        for window in WINDOWS {
+            let since = now - window.duration();
+            let in_window = |withdrawal: &Withdrawal| withdrawal.created_at >= since;
+            if let Some(limit) = user_limit.get(window) {
+                let used = self.value_of(&recent, in_window)?;
+                ensure_within(window, limit, used, value)?;
+            }
+            if let Some(limit) = asset_limit.get(window) {
+                let used = self.value_of(&recent, |withdrawal| {
+                    in_window(withdrawal) && withdrawal.asset_id == request.asset_id
+                })?;
+                ensure_within(window, limit, used, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the request goes to an address that is neither the destination of an
+    /// earlier withdrawal of the user nor recorded as changed to.
+    async fn is_new_address(&self, request: &WithdrawalRequest) -> Result<bool, LimitError> {
+        let mut response = self
+            .db
+            .query("SELECT VALUE address FROM type::table($withdrawals) WHERE user_id = $user")
+            .query(
+                "SELECT VALUE address FROM type::table($events) \
+                 WHERE user_id = $user AND kind = $kind",
+            )
+            .bind(("withdrawals", Withdrawal::TABLE))
+            .bind(("events", SecurityEvent::TABLE))
+            .bind(("user", request.user_id.to_string()))
+            .bind(("kind", SecurityEventKind::AddressChanged))
+            .await?;
+        let used: Vec<String> = response.take(0)?;
+        let changed: Vec<Option<String>> = response.take(1)?;
+        let address = request.address.trim();
+        Ok(!used.iter().any(|used| used == address)
+            && !changed.iter().flatten().any(|changed| changed == address))
+    }
+
+    /// Withdrawals of the user requested since `since` that did not fail and were not
+    /// cancelled.
+    async fn recent(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Withdrawal>, LimitError> {
+        let rows: Vec<Value> = self
+            .db
+            .query(
+                "SELECT *, meta::id(id) AS id FROM type::table($table) \
+                 WHERE user_id = $user AND created_at >= $since AND status NOTINSIDE $released",
+            )
+            .bind(("table", Withdrawal::TABLE))
+            .bind(("user", user_id.to_string()))
+            .bind(("since", Datetime::from(since)))
+            .bind((
+                "released",
+                [WithdrawalStatus::Failed, WithdrawalStatus::Cancelled],
+            ))
+            .await?
+            .take(0)?;
+        Ok(rows
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn value_of(
+        &self,
+        withdrawals: &[Withdrawal],
+        include: impl Fn(&Withdrawal) -> bool,
+    ) -> Result<i64, LimitError> {
+        withdrawals
+            .iter()
+            .filter(|withdrawal| include(withdrawal))
+            .try_fold(0i64, |total, withdrawal| {
+                self.prices
+                    .value(&withdrawal.asset_id, withdrawal.amount)
+                    .map(|value| total.saturating_add(value))
+                    .ok_or(LimitError::PriceUnavailable)
+            })
+    }
+}
+
+fn ensure_within(window: LimitWindow, limit: i64, used: i64, value: i64) -> Result<(), LimitError> {
+    if used.saturating_add(value) > limit {
+        return Err(LimitError::Exceeded {
+            window,
+            remaining: (limit - used).max(0),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use chrono::Duration;
+use database::Actor;
+use models::Fraction;
+use surrealdb::{engine::local::Db, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::LimitError,
+    models::{Limit, LimitPolicy, LimitWindow, ReferencePrices, SecurityEventKind, UserLimits},
+    service::WithdrawalLimits,
+};
+use crate::{
+    approval::models::ApprovalPolicy,
+    ledger::{journal::Ledger, models::Account},
+    tests::{add_asset, asset, deposit, migrated, registered, withdrawal_request, ADDRESS},
+    withdrawal::{
+        errors::WithdrawalError,
+        models::{WithdrawalFees, WithdrawalRequest},
+        service::Withdrawals,
+    },
+};
+
+struct Fixture {
+    db: Surreal<Db>,
+    limits: WithdrawalLimits<Db>,
+    withdrawals: Withdrawals<Db>,
+    prices: ReferencePrices,
+    user_id: Uuid,
+    ether: Uuid,
+    token: Uuid,
+}
+impl Fixture {
+    async fn new(policy: impl FnOnce(Uuid, Uuid) -> LimitPolicy) -> Self {
+        let db = migrated().await;
+
+        let (user_id, ether, token) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let ledger = Ledger::new(db.clone());
+        for (id, symbol) in [(ether, "ETH"), (token, "TKN")] {
+            add_asset(&db, &asset(id, symbol)).await;
+            deposit(&ledger, Account::User(user_id), id, 1_000).await;
+        }
+        registered(&db, user_id).await;
+
+        let prices = ReferencePrices::new();
+        prices.set(ether, Fraction::from(2i64));
+        prices.set(token, "1/3".parse().unwrap());
+        let limits = WithdrawalLimits::new(db.clone(), policy(ether, token), prices.clone());
+        Self {
+            withdrawals: Withdrawals::new(
+                db.clone(),
+                WithdrawalFees::new(),
+                limits.clone(),
+                ApprovalPolicy::new(),
+            ),
+            db,
+            limits,
+            prices,
+            user_id,
+            ether,
+            token,
+        }
+    }
+
+    async fn withdraw(&self, asset_id: Uuid, amount: i64) -> Result<(), WithdrawalError> {
+        self.withdrawals
+            .request(&withdrawal_request(self.user_id, asset_id, amount))
+            .await
+            .map(drop)
+    }
+}
+
+#[test]
+fn user_limits_override_their_tier() {
+    let policy = LimitPolicy::new("basic")
+        .tier("basic", Limit::new(100, 1_000))
+        .tier("verified", Limit::new(1_000, 10_000));
+    let user_id = Uuid::new_v4();
+
+    assert_eq!(policy.user_limit(None), Limit::new(100, 1_000));
+    assert_eq!(
+        policy.user_limit(Some(&UserLimits::new(user_id).tier("verified"))),
+        Limit::new(1_000, 10_000)
+    );
+    let user = UserLimits::new(user_id).tier("verified").limit(Limit {
+        daily: Some(50),
+        monthly: None,
+    });
+    assert_eq!(policy.user_limit(Some(&user)), Limit::new(50, 10_000));
+    assert_eq!(
+        policy.user_limit(Some(&UserLimits::new(user_id).tier("unknown"))),
+        Limit::default()
+    );
+}
+
+#[test]
+fn values_are_rounded_up() {
+    let prices = ReferencePrices::new();
+    let asset_id = Uuid::new_v4();
+    assert_eq!(prices.value(&asset_id, 1), None);
+    prices.set(asset_id, "1/3".parse().unwrap());
+    assert_eq!(prices.value(&asset_id, 3), Some(1));
+    assert_eq!(prices.value(&asset_id, 4), Some(2));
+}
+
+#[tokio::test]
+async fn tier_limits_cap_the_value_of_all_withdrawals() {
+    let fixture =
+        Fixture::new(|_, _| LimitPolicy::new("basic").tier("basic", Limit::new(300, 500))).await;
+
+    fixture.withdraw(fixture.ether, 100).await.unwrap();
+    fixture.withdraw(fixture.token, 150).await.unwrap();
+    assert_eq!(
+        fixture
+            .limits
+            .usage(fixture.user_id, LimitWindow::Daily)
+            .await
+            .unwrap(),
+        250
+    );
+    assert!(matches!(
+        fixture.withdraw(fixture.ether, 26).await,
+        Err(WithdrawalError::Limit(LimitError::Exceeded {
+            window: LimitWindow::Daily,
+            remaining: 50
+        }))
+    ));
+    fixture.withdraw(fixture.ether, 25).await.unwrap();
+
+    fixture
+        .limits
+        .set_user_limits(&UserLimits::new(fixture.user_id).limit(Limit {
+            daily: Some(1_000),
+            monthly: None,
+        }))
+        .await
+        .unwrap();
+    assert!(matches!(
+        fixture.withdraw(fixture.ether, 101).await,
+        Err(WithdrawalError::Limit(LimitError::Exceeded {
+            window: LimitWindow::Monthly,
+            remaining: 200
+        }))
+    ));
+}
+
+#[tokio::test]
+async fn asset_limits_apply_per_asset_and_released_withdrawals_do_not_count() {
+    let fixture = Fixture::new(|ether, _| {
+        LimitPolicy::new("basic").asset(
+            ether,
+            Limit {
+                daily: Some(100),
+                monthly: None,
+            },
+        )
+    })
+    .await;
+
+    fixture.withdraw(fixture.ether, 50).await.unwrap();
+    fixture.withdraw(fixture.token, 900).await.unwrap();
+    assert!(matches!(
+        fixture.withdraw(fixture.ether, 1).await,
+        Err(WithdrawalError::Limit(LimitError::Exceeded {
+            remaining: 0,
+            ..
+        }))
+    ));
+
+    let held = fixture.withdrawals.list(fixture.user_id).await.unwrap();
+    let ether = held
+        .iter()
+        .find(|withdrawal| withdrawal.asset_id == fixture.ether)
+        .unwrap();
+    fixture
+        .withdrawals
+        .cancel(Actor::User(fixture.user_id), &ether.id)
+        .await
+        .unwrap();
+    fixture.withdraw(fixture.ether, 50).await.unwrap();
+}
+
+#[tokio::test]
+async fn security_changes_start_a_cooling_off_period() {
+    let fixture = Fixture::new(|_, _| LimitPolicy::new("basic")).await;
+    fixture.withdraw(fixture.ether, 10).await.unwrap();
+
+    let event = fixture
+        .limits
+        .record(fixture.user_id, SecurityEventKind::TwoFactorChanged)
+        .await
+        .unwrap();
+    let until = event.created_at + Duration::hours(24);
+    assert!(matches!(
+        fixture.withdraw(fixture.ether, 10).await,
+        Err(WithdrawalError::Limit(LimitError::CoolingOff(blocked))) if blocked == until
+    ));
+    assert_eq!(
+        fixture
+            .withdrawals
+            .list(fixture.user_id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let limits = WithdrawalLimits::new(
+        fixture.db.clone(),
+        LimitPolicy::new("basic").cooling_off(Duration::zero()),
+        fixture.prices.clone(),
+    );
+    assert_eq!(
+        limits.cooling_off_until(fixture.user_id).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn prices_are_only_required_when_a_limit_applies() {
+    let fixture = Fixture::new(|_, _| LimitPolicy::new("basic")).await;
+    let request = withdrawal_request(fixture.user_id, fixture.ether, 10);
+
+    let unlimited = WithdrawalLimits::new(
+        fixture.db.clone(),
+        LimitPolicy::new("basic"),
+        ReferencePrices::new(),
+    );
+    unlimited.check(&request).await.unwrap();
+
+    let limited = WithdrawalLimits::new(
+        fixture.db.clone(),
+        LimitPolicy::new("basic").tier("basic", Limit::new(1_000, 1_000)),
+        ReferencePrices::new(),
+    );
+    assert!(matches!(
+        limited.check(&request).await,
+        Err(LimitError::PriceUnavailable)
+    ));
+}
+
+#[tokio::test]
+async fn velocity_rules_cap_the_number_of_withdrawals() {
+    let fixture =
+        Fixture::new(|_, _| LimitPolicy::new("basic").max_withdrawals(LimitWindow::Daily, 2)).await;
+
+    fixture.withdraw(fixture.ether, 10).await.unwrap();
+    fixture.withdraw(fixture.token, 10).await.unwrap();
+    assert!(matches!(
+        fixture.withdraw(fixture.ether, 10).await,
+        Err(WithdrawalError::Limit(LimitError::TooFrequent {
+            window: LimitWindow::Daily,
+            max: 2
+        }))
+    ));
+
+    let held = fixture.withdrawals.list(fixture.user_id).await.unwrap();
+    fixture
+        .withdrawals
+        .cancel(Actor::User(fixture.user_id), &held[0].id)
+        .await
+        .unwrap();
+    fixture.withdraw(fixture.ether, 10).await.unwrap();
+}
+
+#[tokio::test]
+async fn withdrawing_to_a_new_address_starts_a_cooling_off_period() {
+    let fixture = Fixture::new(|_, _| LimitPolicy::new("basic")).await;
+    let elsewhere = WithdrawalRequest {
+        address: "0x1111111111111111111111111111111111111111".to_string(),
+        ..withdrawal_request(fixture.user_id, fixture.ether, 10)
+    };
+
+    // A recorded address is not a change
+    fixture.withdraw(fixture.ether, 10).await.unwrap();
+    assert!(matches!(
+        fixture.withdrawals.request(&elsewhere).await,
+        Err(WithdrawalError::Limit(LimitError::CoolingOff(_)))
+    ));
+    let events: Vec<SecurityEventKind> = fixture
+        .db
+        .query("SELECT VALUE kind FROM security_events WHERE address = $address")
+        .bind(("address", elsewhere.address.clone()))
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    assert_eq!(events, [SecurityEventKind::AddressChanged]);
+
+    // Once the period is over the address is known
+    let limits = WithdrawalLimits::new(
+        fixture.db.clone(),
+        LimitPolicy::new("basic").cooling_off(Duration::zero()),
+        fixture.prices.clone(),
+    );
+    limits.check(&elsewhere).await.unwrap();
+    limits
+        .check(&withdrawal_request(fixture.user_id, fixture.ether, 10))
+        .await
+        .unwrap();
+    let mut changes: Vec<String> = fixture
+        .db
+        .query("SELECT VALUE address FROM security_events")
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    changes.sort();
+    assert_eq!(changes, [ADDRESS.to_string(), elsewhere.address.clone()]);
+
+    // So is the first address of a user who did not record it
+    let newcomer = Uuid::new_v4();
+    assert!(matches!(
+        fixture
+            .limits
+            .check(&withdrawal_request(newcomer, fixture.ether, 10))
+            .await,
+        Err(LimitError::CoolingOff(_))
+    ));
+    assert!(fixture
+        .limits
+        .cooling_off_until(newcomer)
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -0,0 +1,30 @@
+use blockchain::EvmNetworkError;
+use database::DatabaseError;
+use models::Network;
+use uuid::Uuid;
+
+use crate::ledger::errors::LedgerError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReconciliationError {
+    #[error("no client for network {0:?}")]
+    NetworkUnavailable(Network),
+
+    #[error("invalid contract address of asset {0}")]
+    InvalidContract(Uuid),
+
+    #[error("precision of asset {0} does not match its decimals on chain")]
+    InvalidPrecision(Uuid),
+
+    #[error("holdings of asset {0} overflow")]
+    Overflow(Uuid),
+
+    #[error("on-chain query failed")]
+    Chain(#[from] EvmNetworkError),
+
+    #[error("ledger error")]
+    Ledger(#[from] LedgerError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
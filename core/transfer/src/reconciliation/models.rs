@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use database::Record;
+use ethers::types::Address;
+use models::{Fraction, Network};
+use num_traits::Signed;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Wallets whose on-chain holdings back the ledger, and the difference tolerated per
+/// asset before a reconciliation raises an alert.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconciliationConfig {
+    wallets: HashMap<Network, Vec<Address>>,
+    tolerances: HashMap<Uuid, i64>,
+    default_tolerance: i64,
+}
+impl ReconciliationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn wallet(mut self, network: Network, address: Address) -> Self {
+        self.wallets.entry(network).or_default().push(address);
+        self
+    }
+
+    /// Tolerance of assets without one of their own, in their smallest unit.
+    pub fn default_tolerance(mut self, tolerance: i64) -> Self {
+        self.default_tolerance = tolerance.max(0);
+        self
+    }
+
+    pub fn tolerance(mut self, asset_id: Uuid, tolerance: i64) -> Self {
+        self.tolerances.insert(asset_id, tolerance.max(0));
+        self
+    }
+
+    pub fn wallets(&self, network: Network) -> &[Address] {
+        self.wallets.get(&network).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn tolerance_of(&self, asset_id: &Uuid) -> i64 {
+        self.tolerances
+            .get(asset_id)
+            .copied()
+            .unwrap_or(self.default_tolerance)
+    }
+}
+
+/// Ledger and on-chain holdings of one asset, in its smallest unit on the ledger.
+///
+/// `ledger` is the hot wallet's ledger balance, which equals what the exchange owes:
+/// user balances, collected fees and withdrawals held until they are confirmed, less
+/// any shortfall booked on suspense. `on_chain` is scaled from the decimals of the
+/// chain to the asset's precision, so it may hold more than fits the ledger and, for
+/// an asset with fewer decimals on the ledger than on chain, fractions of a unit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetReconciliation {
+    pub asset_id: Uuid,
+    pub symbol: String,
+    pub users: i64,
+    pub fees: i64,
+    pub withdrawals: i64,
+    pub suspense: i64,
+    pub ledger: i64,
+    pub on_chain: Fraction,
+    /// `on_chain - ledger`; negative when the wallets hold less than the ledger says.
+    pub difference: Fraction,
+    pub tolerance: i64,
+}
+impl AssetReconciliation {
+    pub fn is_within_tolerance(&self) -> bool {
+        self.difference.abs() <= Fraction::from(self.tolerance)
+    }
+}
+
+/// An asset that could not be reconciled, e.g. because its chain was unreachable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetFailure {
+    pub asset_id: Uuid,
+    pub symbol: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub id: Uuid,
+    pub assets: Vec<AssetReconciliation>,
+    #[serde(default)]
+    pub failures: Vec<AssetFailure>,
+    pub created_at: DateTime<Utc>,
+}
+impl ReconciliationReport {
+    /// Assets whose difference exceeds their tolerance.
+    pub fn discrepancies(&self) -> Vec<&AssetReconciliation> {
+        self.assets
+            .iter()
+            .filter(|asset| !asset.is_within_tolerance())
+            .collect()
+    }
+
+    /// Whether every asset was reconciled and is within its tolerance.
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty() && self.discrepancies().is_empty()
+    }
+}
+impl Record for ReconciliationReport {
+    const TABLE: &'static str = "reconciliation_reports";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use blockchain::EvmNetworkApi;
+use chrono::Utc;
+use database::{AssetRepository, Direction, Filter, Repository};
+use ethers::types::{Address, U256};
+use models::{AssetRaw, Fraction, Network};
+use num_bigint::{BigInt, Sign};
+use surrealdb::{Connection, Surreal};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{
+    errors::ReconciliationError,
+    models::{AssetFailure, AssetReconciliation, ReconciliationConfig, ReconciliationReport},
+};
+use crate::ledger::{
+    journal::{Ledger, Statement},
+    models::Account,
+};
+
+/// Channel that reports with discrepancies above tolerance or failed assets are
+/// published on.
+pub const RECONCILIATION_ALERTS_CHANNEL: &str = "reconciliation_alerts";
+
+/// Decimals of the native coin of every EVM network.
+const NATIVE_DECIMALS: u32 = 18;
+
+/// Compares what the ledger says the exchange holds of each asset with the balances of
+/// its wallets on chain.
+///
+/// Every report is stored, and one with discrepancies above tolerance is published on
+/// [`RECONCILIATION_ALERTS_CHANNEL`] in the same transaction. An asset that cannot be
+/// reconciled, e.g. because its chain is unreachable, is listed among the report's
+/// failures and raises the alert too, without keeping the others from being
+/// reconciled. Withdrawals stay on the
+/// ledger until they are confirmed, so those broadcast but not yet confirmed show up
+/// as a shortfall until then.
+#[derive(Debug)]
+pub struct Reconciliation<C: Connection, N> {
+    ledger: Ledger<C>,
+    assets: AssetRepository<C>,
+    reports: Repository<C, ReconciliationReport>,
+    networks: HashMap<Network, N>,
+    config: ReconciliationConfig,
+}
+impl<C: Connection, N: Clone> Clone for Reconciliation<C, N> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            assets: self.assets.clone(),
+            reports: self.reports.clone(),
+            networks: self.networks.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+impl<C, N> Reconciliation<C, N>
+where
+    C: Connection,
+    N: EvmNetworkApi + Clone + Send + Sync,
+{
+    pub fn new(db: Surreal<C>, config: ReconciliationConfig) -> Self {
+        Self {
+            ledger: Ledger::new(db.clone()),
+            assets: AssetRepository::new(db.clone()),
+            reports: Repository::new(db),
+            networks: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Client used to read the balances of the wallets on `network`.
+    pub fn network(mut self, network: Network, client: N) -> Self {
+        self.networks.insert(network, client);
+        self
+    }
+
+    /// Reconciles every asset, stores the report and raises an alert if any asset is
+    /// off by more than its tolerance or could not be reconciled.
+    pub async fn run(&self) -> Result<ReconciliationReport, ReconciliationError> {
+        let mut totals: HashMap<Uuid, LedgerTotals> = HashMap::new();
+        let mut overflowed = HashSet::new();
+        for row in self.ledger.trial_balance().await? {
+            let total = totals.entry(row.asset_id).or_default();
+            if total.add(row.account, row.balance).is_none() {
+                overflowed.insert(row.asset_id);
+            }
+        }
+
+        let mut assets = Vec::new();
+        let mut failures = Vec::new();
+        for asset in self
+            .assets
+            .list(&Filter::new().order_by("symbol", Direction::Asc))
+            .await?
+        {
+            let totals = totals.remove(&asset.id).unwrap_or_default();
+            let reconciled = if overflowed.contains(&asset.id) {
+                Err(ReconciliationError::Overflow(asset.id))
+            } else {
+                self.reconcile(&asset, totals).await
+            };
+            match reconciled {
+                Ok(reconciled) => assets.push(reconciled),
+                Err(err) => {
+                    tracing::error!("{} could not be reconciled: {:?}", asset.symbol, err);
+                    failures.push(AssetFailure {
+                        asset_id: asset.id,
+                        symbol: asset.symbol,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+
+        let report = ReconciliationReport {
+            id: Uuid::new_v4(),
+            assets,
+            failures,
+            created_at: Utc::now(),
+        };
+        let mut statements = vec![Statement::create(&report)?];
+        for asset in report.discrepancies() {
+            tracing::error!(
+                "{} is off by {} on chain, tolerance {}",
+                asset.symbol,
+                asset.difference,
+                asset.tolerance
+            );
+        }
+        if !report.is_clean() {
+            statements.push(Statement::publish(RECONCILIATION_ALERTS_CHANNEL, &report)?);
+        }
+        self.ledger.commit(statements).await?;
+        Ok(report)
+    }
+
+    /// Most recent reports, newest first.
+    pub async fn reports(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<ReconciliationReport>, ReconciliationError> {
+        Ok(self
+            .reports
+            .list(
+                &Filter::new()
+                    .order_by("created_at", Direction::Desc)
+                    .limit(limit),
+            )
+            .await?)
+    }
+
+    async fn reconcile(
+        &self,
+        asset: &AssetRaw,
+        totals: LedgerTotals,
+    ) -> Result<AssetReconciliation, ReconciliationError> {
+        let on_chain = self.holdings(asset).await?;
+        Ok(AssetReconciliation {
+            asset_id: asset.id,
+            symbol: asset.symbol.clone(),
+            users: totals.users,
+            fees: totals.fees,
+            withdrawals: totals.withdrawals,
+            suspense: totals.suspense,
+            ledger: totals.hot_wallet,
+            difference: on_chain.clone() - Fraction::from(totals.hot_wallet),
+            on_chain,
+            tolerance: self.config.tolerance_of(&asset.id),
+        })
+    }
+
+    /// Sum of the balances of the asset in every wallet on its network, scaled from
+    /// the decimals on chain to the precision of the asset on the ledger.
+    async fn holdings(&self, asset: &AssetRaw) -> Result<Fraction, ReconciliationError> {
+        let wallets = self.config.wallets(asset.network);
+        if wallets.is_empty() {
+            return Ok(Fraction::from(0i64));
+        }
+        let client = self
+            .networks
+            .get(&asset.network)
+            .ok_or(ReconciliationError::NetworkUnavailable(asset.network))?;
+        let token = asset
+            .contract_address
+            .as_deref()
+            .map(|contract| contract.parse::<Address>())
+            .transpose()
+            .map_err(|_| ReconciliationError::InvalidContract(asset.id))?;
+
+        let mut holdings = U256::zero();
+        for wallet in wallets {
+            let balance = match token {
+                Some(token) => client.clone().get_token_balance(token, *wallet).await?,
+                None => client.clone().get_balance(*wallet).await?,
+            };
+            holdings = holdings
+                .checked_add(balance)
+                .ok_or(ReconciliationError::Overflow(asset.id))?;
+        }
+
+        let decimals = match token {
+            Some(token) => client.clone().get_token_decimals(token).await?,
+            None => NATIVE_DECIMALS,
+        };
+        let exponent = i32::try_from(asset.precision)
+            .ok()
+            .zip(i32::try_from(decimals).ok())
+            .and_then(|(precision, decimals)| precision.checked_sub(decimals))
+            .ok_or(ReconciliationError::InvalidPrecision(asset.id))?;
+        let mut bytes = [0; 32];
+        holdings.to_big_endian(&mut bytes);
+        let holdings = Fraction::from(BigInt::from_bytes_be(Sign::Plus, &bytes));
+        Ok(holdings * Fraction(Fraction::from(10i64).pow(exponent)))
+    }
+}
+impl<C, N> Reconciliation<C, N>
+where
+    C: Connection,
+    N: EvmNetworkApi + Clone + Send + Sync + 'static,
+{
+    /// Runs a reconciliation every `interval` for as long as the task runs.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.run().await {
+                    tracing::error!("reconciliation failed: {:?}", err);
+                }
+            }
+        })
+    }
+}
+
+/// Ledger balances of one asset, summed per kind of account.
+#[derive(Debug, Default)]
+struct LedgerTotals {
+    users: i64,
+    fees: i64,
+    withdrawals: i64,
+    suspense: i64,
+    hot_wallet: i64,
+}
+impl LedgerTotals {
+    fn add(&mut self, account: Account, balance: i64) -> Option<()> {
+        let total = match account {
+            Account::User(_) => &mut self.users,
+            Account::Fees => &mut self.fees,
+            Account::Withdrawals => &mut self.withdrawals,
+            Account::Suspense => &mut self.suspense,
+            Account::HotWallet => &mut self.hot_wallet,
+        };
+        *total = total.checked_add(balance)?;
+        Some(())
+    }
+}
@@ -0,0 +1,267 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use blockchain::{EvmNetworkApi, EvmNetworkError};
+use database::{MemoryBroker, OutboxRelay};
+use ethers::types::{Address, Transaction, TxHash, U256};
+use models::{AssetRaw, Fraction, Network};
+use surrealdb::{engine::local::Db, Surreal};
+use url::Url;
+use uuid::Uuid;
+
+use super::{
+    errors::ReconciliationError,
+    models::ReconciliationConfig,
+    service::{Reconciliation, RECONCILIATION_ALERTS_CHANNEL},
+};
+use crate::{
+    ledger::{
+        journal::Ledger,
+        models::{Account, EntryKind, JournalEntry},
+    },
+    tests::{add_asset, asset, deposit, migrated},
+};
+
+const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+/// Decimals of the token on chain, against its precision of 2 on the ledger.
+const TOKEN_DECIMALS: u32 = 6;
+
+/// Balances of a chain, keyed by token contract (`None` for ether) and holder.
+#[derive(Debug, Clone, Default)]
+struct Chain {
+    balances: Arc<HashMap<(Option<Address>, Address), u64>>,
+}
+#[async_trait]
+impl EvmNetworkApi for Chain {
+    fn get_name(self) -> String {
+        "test".to_string()
+    }
+    fn get_rpc_url(self) -> Url {
+        "http://localhost:8545".parse().unwrap()
+    }
+    async fn get_chain_id(self) -> Result<U256, EvmNetworkError> {
+        Ok(U256::one())
+    }
+    async fn get_transaction(self, _: TxHash) -> Result<Transaction, EvmNetworkError> {
+        Err(EvmNetworkError::Transaction)
+    }
+    async fn get_balance(self, address: Address) -> Result<U256, EvmNetworkError> {
+        Ok(self.balance(None, address))
+    }
+    async fn get_token_balance(
+        self,
+        token: Address,
+        holder: Address,
+    ) -> Result<U256, EvmNetworkError> {
+        Ok(self.balance(Some(token), holder))
+    }
+    async fn get_token_decimals(self, _: Address) -> Result<u32, EvmNetworkError> {
+        Ok(TOKEN_DECIMALS)
+    }
+}
+impl Chain {
+    fn balance(&self, token: Option<Address>, holder: Address) -> U256 {
+        self.balances
+            .get(&(token, holder))
+            .copied()
+            .unwrap_or_default()
+            .into()
+    }
+}
+
+struct Fixture {
+    db: Surreal<Db>,
+    ether: Uuid,
+    token: Uuid,
+    wallets: [Address; 2],
+}
+impl Fixture {
+    async fn new() -> Self {
+        let db = migrated().await;
+
+        let (ether, token) = (Uuid::new_v4(), Uuid::new_v4());
+        let ledger = Ledger::new(db.clone());
+        let user = Account::User(Uuid::new_v4());
+        for (id, symbol, contract_address, precision, amount) in [
+            (ether, "ETH", None, 18, 100),
+            (token, "USDC", Some(TOKEN.to_string()), 2, 30),
+        ] {
+            add_asset(
+                &db,
+                &AssetRaw {
+                    contract_address,
+                    precision,
+                    ..asset(id, symbol)
+                },
+            )
+            .await;
+            deposit(&ledger, user, id, amount).await;
+        }
+        ledger
+            .post(
+                &JournalEntry::new(EntryKind::Fee)
+                    .debit(user, ether, 5)
+                    .credit(Account::Fees, ether, 5),
+            )
+            .await
+            .unwrap();
+        Self {
+            db,
+            ether,
+            token,
+            wallets: [Address::random(), Address::random()],
+        }
+    }
+
+    fn reconciliation(&self, ether: [u64; 2], token: u64) -> Reconciliation<Db, Chain> {
+        let token_contract = TOKEN.parse::<Address>().unwrap();
+        let chain = Chain {
+            balances: Arc::new(HashMap::from([
+                ((None, self.wallets[0]), ether[0]),
+                ((None, self.wallets[1]), ether[1]),
+                ((Some(token_contract), self.wallets[1]), token),
+            ])),
+        };
+        let config = ReconciliationConfig::new()
+            .wallet(Network::Ethereum, self.wallets[0])
+            .wallet(Network::Ethereum, self.wallets[1])
+            .tolerance(self.token, 2);
+        Reconciliation::new(self.db.clone(), config).network(Network::Ethereum, chain)
+    }
+
+    async fn alerts(&self) -> usize {
+        OutboxRelay::new(self.db.clone(), MemoryBroker::new())
+            .pending(10)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|message| message.channel == RECONCILIATION_ALERTS_CHANNEL)
+            .count()
+    }
+}
+
+#[tokio::test]
+async fn matching_holdings_produce_a_clean_report() {
+    let fixture = Fixture::new().await;
+    let reconciliation = fixture.reconciliation([60, 40], 300_000);
+
+    let report = reconciliation.run().await.unwrap();
+    assert!(report.discrepancies().is_empty());
+    let ether = &report.assets[0];
+    assert_eq!(ether.asset_id, fixture.ether);
+    assert_eq!(
+        (
+            ether.users,
+            ether.fees,
+            ether.ledger,
+            &ether.on_chain,
+            &ether.difference
+        ),
+        (95, 5, 100, &Fraction::from(100i64), &Fraction::from(0i64))
+    );
+    assert_eq!(
+        (report.assets[1].asset_id, &report.assets[1].on_chain),
+        (fixture.token, &Fraction::from(30i64))
+    );
+
+    assert_eq!(reconciliation.reports(10).await.unwrap(), [report]);
+    assert_eq!(fixture.alerts().await, 0);
+}
+
+#[tokio::test]
+async fn differences_above_tolerance_raise_an_alert() {
+    let fixture = Fixture::new().await;
+
+    let report = fixture
+        .reconciliation([60, 30], 280_001)
+        .run()
+        .await
+        .unwrap();
+    let discrepancies = report.discrepancies();
+    assert_eq!(discrepancies.len(), 1);
+    assert_eq!(
+        (discrepancies[0].asset_id, &discrepancies[0].difference),
+        (fixture.ether, &Fraction::from(-10i64))
+    );
+    // Dust below the ledger's precision is kept rather than rounded away
+    assert_eq!(
+        report.assets[1].difference,
+        "-19999/10000".parse::<Fraction>().unwrap()
+    );
+    assert!(report.assets[1].is_within_tolerance());
+    assert_eq!(fixture.alerts().await, 1);
+}
+
+#[tokio::test]
+async fn wallets_need_a_client_for_their_network() {
+    let fixture = Fixture::new().await;
+    let config = ReconciliationConfig::new().wallet(Network::Ethereum, fixture.wallets[0]);
+    let reconciliation = Reconciliation::<Db, Chain>::new(fixture.db.clone(), config);
+
+    let report = reconciliation.run().await.unwrap();
+    assert!(report.assets.is_empty());
+    assert_eq!(
+        report
+            .failures
+            .iter()
+            .map(|failure| (failure.asset_id, failure.error.clone()))
+            .collect::<Vec<_>>(),
+        [fixture.ether, fixture.token].map(|asset_id| (
+            asset_id,
+            ReconciliationError::NetworkUnavailable(Network::Ethereum).to_string()
+        ))
+    );
+    assert_eq!(reconciliation.reports(10).await.unwrap(), [report]);
+    assert_eq!(fixture.alerts().await, 1);
+}
+
+#[tokio::test]
+async fn a_failing_asset_does_not_keep_the_others_from_being_reconciled() {
+    let fixture = Fixture::new().await;
+    let broken = Uuid::new_v4();
+    add_asset(
+        &fixture.db,
+        &AssetRaw {
+            contract_address: Some("not an address".to_string()),
+            ..asset(broken, "BRK")
+        },
+    )
+    .await;
+
+    let report = fixture
+        .reconciliation([60, 40], 300_000)
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(
+        report
+            .assets
+            .iter()
+            .map(|asset| asset.asset_id)
+            .collect::<Vec<_>>(),
+        [fixture.ether, fixture.token]
+    );
+    assert!(report.discrepancies().is_empty());
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].asset_id, broken);
+    assert!(!report.is_clean());
+    assert_eq!(fixture.alerts().await, 1);
+}
+
+#[tokio::test]
+async fn holdings_beyond_the_ledger_range_are_reported() {
+    let fixture = Fixture::new().await;
+
+    let reconciliation = fixture.reconciliation([u64::MAX, u64::MAX], 300_000);
+
+    let report = reconciliation.run().await.unwrap();
+    let on_chain = Fraction::from(i64::MAX) * Fraction::from(4i64) + Fraction::from(2i64);
+    assert_eq!(report.assets[0].on_chain, on_chain);
+    assert_eq!(
+        report.assets[0].difference,
+        on_chain - Fraction::from(100i64)
+    );
+    assert_eq!(report.discrepancies().len(), 1);
+    assert_eq!(reconciliation.reports(10).await.unwrap(), [report]);
+}
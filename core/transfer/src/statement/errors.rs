@@ -0,0 +1,23 @@
+use database::DatabaseError;
+use uuid::Uuid;
+
+use super::service::MAX_PERIOD;
+use crate::ledger::errors::LedgerError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StatementError {
+    #[error("statement period must end after it starts")]
+    InvalidPeriod,
+
+    #[error("statement period must not exceed {} days", MAX_PERIOD.num_days())]
+    PeriodTooLong,
+
+    #[error("balance of asset {0} overflows")]
+    Overflow(Uuid),
+
+    #[error("ledger error")]
+    Ledger(#[from] LedgerError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
@@ -0,0 +1,6 @@
+pub mod errors;
+pub mod models;
+pub mod service;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ledger::models::EntryKind;
+
+const CSV_HEADER: &str = "asset_id,symbol,created_at,kind,entry_id,reference,memo,amount,balance";
+
+/// A movement of one asset, signed from the user's point of view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementLine {
+    pub entry_id: Uuid,
+    pub kind: EntryKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub amount: i64,
+    /// Balance after the movement.
+    pub balance: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetStatement {
+    pub asset_id: Uuid,
+    pub symbol: String,
+    pub opening_balance: i64,
+    pub lines: Vec<StatementLine>,
+    pub closing_balance: i64,
+}
+
+/// Movements of a user's balances in `[from, until)`, per asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountStatement {
+    pub user_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub assets: Vec<AssetStatement>,
+}
+impl AccountStatement {
+    /// One row per movement, framed by an `opening_balance` and a `closing_balance` row
+    /// per asset.
+    ///
+    /// Text that a spreadsheet would evaluate as a formula is prefixed with `'`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{CSV_HEADER}\n");
+        for asset in &self.assets {
+            let symbol = escape(&asset.symbol);
+            let row = |csv: &mut String, created_at: &DateTime<Utc>, rest: &str| {
+                let _ = writeln!(
+                    csv,
+                    "{},{symbol},{},{rest}",
+                    asset.asset_id,
+                    created_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+                );
+            };
+            row(
+                &mut csv,
+                &self.from,
+                &format!("opening_balance,,,,,{}", asset.opening_balance),
+            );
+            for line in &asset.lines {
+                row(
+                    &mut csv,
+                    &line.created_at,
+                    &format!(
+                        "{},{},{},{},{},{}",
+                        line.kind,
+                        line.entry_id,
+                        line.reference.as_deref().map(escape).unwrap_or_default(),
+                        line.memo.as_deref().map(escape).unwrap_or_default(),
+                        line.amount,
+                        line.balance
+                    ),
+                );
+            }
+            row(
+                &mut csv,
+                &self.until,
+                &format!("closing_balance,,,,,{}", asset.closing_balance),
+            );
+        }
+        csv
+    }
+}
+
+fn escape(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
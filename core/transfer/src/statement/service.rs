@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use database::AssetRepository;
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::StatementError,
+    models::{AccountStatement, AssetStatement, StatementLine},
+};
+use crate::ledger::{journal::Ledger, models::Account};
+
+/// Longest period a statement may cover.
+pub const MAX_PERIOD: Duration = Duration::days(366);
+
+/// Builds period statements of user balances from the ledger.
+#[derive(Debug)]
+pub struct Statements<C: Connection> {
+    ledger: Ledger<C>,
+    assets: AssetRepository<C>,
+}
+impl<C: Connection> Clone for Statements<C> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            assets: self.assets.clone(),
+        }
+    }
+}
+impl<C: Connection> Statements<C> {
+    pub fn new(db: Surreal<C>) -> Self {
+        Self {
+            ledger: Ledger::new(db.clone()),
+            assets: AssetRepository::new(db),
+        }
+    }
+
+    /// Statement of every asset the user held or moved in `[from, until)`, ordered by
+    /// symbol, failing with `PeriodTooLong` for periods longer than [`MAX_PERIOD`].
+    ///
+    /// Opening balances are summed by the database from the entries before `from`, and
+    /// only the entries of the period itself are read, so the statement does not
+    /// change as later entries are posted.
+    pub async fn generate(
+        &self,
+        user_id: Uuid,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<AccountStatement, StatementError> {
+        if from >= until {
+            return Err(StatementError::InvalidPeriod);
+        }
+        if until - from > MAX_PERIOD {
+            return Err(StatementError::PeriodTooLong);
+        }
+        let account = Account::User(user_id);
+        let (balances, entries) = self.ledger.period(account, from, until).await?;
+
+        let mut statements: BTreeMap<Uuid, AssetStatement> = balances
+            .into_iter()
+            .map(|balance| (balance.asset_id, opened(balance.asset_id, balance.balance)))
+            .collect();
+        for entry in &entries {
+            for ((posted_to, asset_id), amount) in entry.deltas()? {
+                if posted_to != account || amount == 0 {
+                    continue;
+                }
+                let statement = statements
+                    .entry(asset_id)
+                    .or_insert_with(|| opened(asset_id, 0));
+                let balance = statement
+                    .closing_balance
+                    .checked_add(amount)
+                    .ok_or(StatementError::Overflow(asset_id))?;
+                statement.closing_balance = balance;
+                statement.lines.push(StatementLine {
+                    entry_id: entry.id,
+                    kind: entry.kind,
+                    reference: entry.reference.clone(),
+                    memo: entry.memo.clone(),
+                    amount,
+                    balance,
+                    created_at: entry.created_at,
+                });
+            }
+        }
+
+        let mut assets = Vec::new();
+        for mut statement in statements.into_values() {
+            if statement.opening_balance == 0 && statement.lines.is_empty() {
+                continue;
+            }
+            if let Some(asset) = self.assets.get(&statement.asset_id).await? {
+                statement.symbol = asset.symbol;
+            }
+            assets.push(statement);
+        }
+        assets.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        Ok(AccountStatement {
+            user_id,
+            from,
+            until,
+            assets,
+        })
+    }
+}
+
+/// Statement of an asset opening at `balance`, before any line is added.
+fn opened(asset_id: Uuid, balance: i64) -> AssetStatement {
+    AssetStatement {
+        asset_id,
+        symbol: String::new(),
+        opening_balance: balance,
+        lines: Vec::new(),
+        closing_balance: balance,
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Duration, Utc};
+use surrealdb::engine::local::Db;
+use uuid::Uuid;
+
+use super::{
+    errors::StatementError,
+    service::{Statements, MAX_PERIOD},
+};
+use crate::{
+    ledger::{
+        journal::Ledger,
+        models::{Account, EntryKind, JournalEntry},
+    },
+    tests::{add_asset, asset, migrated},
+};
+
+struct Fixture {
+    ledger: Ledger<Db>,
+    statements: Statements<Db>,
+    user_id: Uuid,
+    ether: Uuid,
+    token: Uuid,
+}
+impl Fixture {
+    async fn new() -> Self {
+        let db = migrated().await;
+
+        let (ether, token) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, symbol) in [(ether, "ETH"), (token, "USDC")] {
+            add_asset(&db, &asset(id, symbol)).await;
+        }
+        Self {
+            ledger: Ledger::new(db.clone()),
+            statements: Statements::new(db),
+            user_id: Uuid::new_v4(),
+            ether,
+            token,
+        }
+    }
+
+    async fn post(&self, entry: JournalEntry, created_at: DateTime<Utc>) {
+        self.ledger
+            .post(&JournalEntry {
+                created_at,
+                ..entry
+            })
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn statements_frame_the_movements_of_each_asset_in_the_period() {
+    let fixture = Fixture::new().await;
+    let user = Account::User(fixture.user_id);
+    let (ether, token) = (fixture.ether, fixture.token);
+    let now = Utc::now();
+    let (from, until) = (now - Duration::days(5), now - Duration::days(1));
+
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Deposit)
+                .debit(Account::HotWallet, ether, 100)
+                .credit(user, ether, 100)
+                .reference("0xdeposit"),
+            now - Duration::days(10),
+        )
+        .await;
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Transfer)
+                .debit(user, ether, 30)
+                .credit(Account::User(Uuid::new_v4()), ether, 30)
+                .memo("rent"),
+            now - Duration::days(4),
+        )
+        .await;
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Trade)
+                .debit(user, ether, 20)
+                .credit(Account::Suspense, ether, 20)
+                .debit(Account::Suspense, token, 50)
+                .credit(user, token, 50),
+            now - Duration::days(3),
+        )
+        .await;
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Fee)
+                .debit(user, ether, 2)
+                .credit(Account::Fees, ether, 2),
+            now - Duration::days(2),
+        )
+        .await;
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Fee)
+                .debit(user, ether, 1)
+                .credit(Account::Fees, ether, 1),
+            now,
+        )
+        .await;
+
+    let statement = fixture
+        .statements
+        .generate(fixture.user_id, from, until)
+        .await
+        .unwrap();
+    assert_eq!(
+        statement
+            .assets
+            .iter()
+            .map(|asset| (
+                asset.symbol.as_str(),
+                asset.opening_balance,
+                asset.closing_balance
+            ))
+            .collect::<Vec<_>>(),
+        [("ETH", 100, 48), ("USDC", 0, 50)]
+    );
+    assert_eq!(
+        statement.assets[0]
+            .lines
+            .iter()
+            .map(|line| (line.kind, line.amount, line.balance))
+            .collect::<Vec<_>>(),
+        [
+            (EntryKind::Transfer, -30, 70),
+            (EntryKind::Trade, -20, 50),
+            (EntryKind::Fee, -2, 48),
+        ]
+    );
+    assert_eq!(statement.assets[0].lines[0].memo.as_deref(), Some("rent"));
+    assert_eq!(statement.assets[1].lines[0].kind, EntryKind::Trade);
+
+    let csv = statement.to_csv();
+    let rows = csv.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1 + 5 + 3);
+    assert!(rows[0].starts_with("asset_id,symbol,created_at,kind"));
+    assert!(rows[1].ends_with("opening_balance,,,,,100"));
+    assert!(rows[2].ends_with(",rent,-30,70"));
+    assert!(rows[5].ends_with("closing_balance,,,,,48"));
+}
+
+#[tokio::test]
+async fn balances_without_movements_carry_over() {
+    let fixture = Fixture::new().await;
+    let user = Account::User(fixture.user_id);
+    let now = Utc::now();
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Deposit)
+                .debit(Account::HotWallet, fixture.ether, 7)
+                .credit(user, fixture.ether, 7),
+            now - Duration::days(10),
+        )
+        .await;
+    // Earlier debits are netted into the opening balance, later entries are left out
+    for (amount, created_at) in [(3, now - Duration::days(5)), (1, now + Duration::hours(1))] {
+        fixture
+            .post(
+                JournalEntry::new(EntryKind::Fee)
+                    .debit(user, fixture.ether, amount)
+                    .credit(Account::Fees, fixture.ether, amount),
+                created_at,
+            )
+            .await;
+    }
+
+    let statement = fixture
+        .statements
+        .generate(fixture.user_id, now - Duration::days(1), now)
+        .await
+        .unwrap();
+    assert_eq!(statement.assets.len(), 1);
+    let ether = &statement.assets[0];
+    assert_eq!(
+        (
+            ether.opening_balance,
+            ether.lines.len(),
+            ether.closing_balance
+        ),
+        (4, 0, 4)
+    );
+
+    let empty = fixture
+        .statements
+        .generate(Uuid::new_v4(), now - Duration::days(1), now)
+        .await
+        .unwrap();
+    assert!(empty.assets.is_empty());
+}
+
+#[tokio::test]
+async fn csv_fields_are_quoted_and_formulas_neutralized() {
+    let fixture = Fixture::new().await;
+    let user = Account::User(fixture.user_id);
+    let now = Utc::now();
+    fixture
+        .post(
+            JournalEntry::new(EntryKind::Transfer)
+                .debit(Account::Suspense, fixture.ether, 5)
+                .credit(user, fixture.ether, 5)
+                .memo("=HYPERLINK(\"x\"), thanks"),
+            now - Duration::hours(1),
+        )
+        .await;
+
+    let csv = fixture
+        .statements
+        .generate(fixture.user_id, now - Duration::days(1), now)
+        .await
+        .unwrap()
+        .to_csv();
+    assert!(csv.contains(",\"'=HYPERLINK(\"\"x\"\"), thanks\",5,5\n"));
+}
+
+#[tokio::test]
+async fn periods_must_end_after_they_start() {
+    let fixture = Fixture::new().await;
+    let now = Utc::now();
+    assert!(matches!(
+        fixture.statements.generate(fixture.user_id, now, now).await,
+        Err(StatementError::InvalidPeriod)
+    ));
+}
+
+#[tokio::test]
+async fn periods_are_capped() {
+    let fixture = Fixture::new().await;
+    let now = Utc::now();
+    assert!(fixture
+        .statements
+        .generate(fixture.user_id, now - MAX_PERIOD, now)
+        .await
+        .is_ok());
+    assert!(matches!(
+        fixture
+            .statements
+            .generate(fixture.user_id, DateTime::UNIX_EPOCH, now)
+            .await,
+        Err(StatementError::PeriodTooLong)
+    ));
+}
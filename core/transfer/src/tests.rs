@@ -0,0 +1,138 @@
+//! Fixtures shared by the tests of every module.
+
+use chrono::{Duration, Utc};
+use database::{AssetRepository, Migrator, Repository};
+use models::{AssetRaw, Network};
+use surrealdb::{
+    engine::local::{Db, Mem},
+    Surreal,
+};
+use uuid::Uuid;
+
+use crate::{
+    ledger::{
+        journal::Ledger,
+        models::{Account, EntryKind, JournalEntry},
+    },
+    limits::{
+        models::{LimitPolicy, ReferencePrices, SecurityEvent, SecurityEventKind},
+        service::WithdrawalLimits,
+    },
+    withdrawal::models::WithdrawalRequest,
+};
+
+/// Destination of the withdrawals requested in tests.
+pub const ADDRESS: &str = "0x00000000219ab540356cbb839cbe05303d7705fa";
+
+/// Empty in-memory database.
+pub async fn memory() -> Surreal<Db> {
+    let db = Surreal::new::<Mem>(()).await.unwrap();
+    db.use_ns("test").use_db("test").await.unwrap();
+    db
+}
+
+/// In-memory database with the embedded schema applied.
+pub async fn migrated() -> Surreal<Db> {
+    let db = memory().await;
+    Migrator::embedded().run(&db, false).await.unwrap();
+    db
+}
+
+/// An enabled Ethereum asset named after its symbol.
+pub fn asset(id: Uuid, symbol: &str) -> AssetRaw {
+    AssetRaw {
+        id,
+        name: symbol.to_string(),
+        symbol: symbol.to_string(),
+        precision: 18,
+        network: Network::Ethereum,
+        contract_address: None,
+        deposits_enabled: true,
+        withdrawals_enabled: true,
+        trading_enabled: true,
+    }
+}
+
+pub fn ether(id: Uuid) -> AssetRaw {
+    AssetRaw {
+        name: "Ether".to_string(),
+        ..asset(id, "ETH")
+    }
+}
+
+pub async fn add_asset(db: &Surreal<Db>, asset: &AssetRaw) {
+    AssetRepository::new(db.clone())
+        .create(asset)
+        .await
+        .unwrap();
+}
+
+/// Deposit of `amount` from the hot wallet to `account`.
+pub fn deposit_entry(account: Account, asset_id: Uuid, amount: i64) -> JournalEntry {
+    JournalEntry::new(EntryKind::Deposit)
+        .debit(Account::HotWallet, asset_id, amount)
+        .credit(account, asset_id, amount)
+}
+
+/// Posts a deposit of `amount` from the hot wallet to `account`.
+pub async fn deposit(ledger: &Ledger<Db>, account: Account, asset_id: Uuid, amount: i64) {
+    ledger
+        .post(&deposit_entry(account, asset_id, amount))
+        .await
+        .unwrap();
+}
+
+pub async fn balance(ledger: &Ledger<Db>, account: Account, asset_id: Uuid) -> i64 {
+    ledger.balance(account, asset_id).await.unwrap()
+}
+
+/// Withdrawal of `amount` to [`ADDRESS`].
+pub fn withdrawal_request(user_id: Uuid, asset_id: Uuid, amount: i64) -> WithdrawalRequest {
+    WithdrawalRequest {
+        user_id,
+        asset_id,
+        address: ADDRESS.to_string(),
+        amount,
+    }
+}
+
+/// Withdrawal limits without any limit or velocity rule.
+pub fn unlimited(db: &Surreal<Db>) -> WithdrawalLimits<Db> {
+    WithdrawalLimits::new(
+        db.clone(),
+        LimitPolicy::new("basic"),
+        ReferencePrices::new(),
+    )
+}
+
+/// Records [`ADDRESS`] as the withdrawal address of `user_id`, long enough ago for the
+/// cooling-off period to be over.
+pub async fn registered(db: &Surreal<Db>, user_id: Uuid) {
+    Repository::new(db.clone())
+        .create(&SecurityEvent {
+            id: Uuid::new_v4(),
+            user_id,
+            kind: SecurityEventKind::AddressChanged,
+            address: Some(ADDRESS.to_string()),
+            created_at: Utc::now() - Duration::days(30),
+        })
+        .await
+        .unwrap();
+}
+
+/// Migrated database listing Ether, returned with its id, of which `user_id` deposited
+/// `amount` and registered [`ADDRESS`].
+pub async fn funded(user_id: Uuid, amount: i64) -> (Surreal<Db>, Uuid) {
+    let db = migrated().await;
+    let asset_id = Uuid::new_v4();
+    add_asset(&db, &ether(asset_id)).await;
+    deposit(
+        &Ledger::new(db.clone()),
+        Account::User(user_id),
+        asset_id,
+        amount,
+    )
+    .await;
+    registered(&db, user_id).await;
+    (db, asset_id)
+}
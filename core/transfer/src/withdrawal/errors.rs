@@ -0,0 +1,68 @@
+use database::DatabaseError;
+
+use super::models::WithdrawalStatus;
+use crate::{ledger::errors::LedgerError, limits::errors::LimitError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WithdrawalError {
+    #[error("withdrawal amount must exceed the fee of {0}")]
+    InvalidAmount(i64),
+
+    #[error("destination address is empty")]
+    InvalidAddress,
+
+    #[error("asset not found")]
+    AssetNotFound,
+
+    #[error("withdrawals of this asset are disabled")]
+    Disabled,
+
+    #[error("withdrawal not found")]
+    NotFound,
+
+    #[error("withdrawal cannot go from {from} to {to}")]
+    IllegalTransition {
+        from: WithdrawalStatus,
+        to: WithdrawalStatus,
+    },
+
+    #[error("withdrawal needs approval by a quorum of approvers")]
+    ApprovalRequired,
+
+    #[error("withdrawal was modified concurrently")]
+    Conflict,
+
+    #[error("insufficient balance")]
+    InsufficientBalance,
+
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+
+    #[error("ledger error")]
+    Ledger(#[source] LedgerError),
+
+    #[error("database error")]
+    Database(#[from] DatabaseError),
+}
+
+impl From<LedgerError> for WithdrawalError {
+    fn from(err: LedgerError) -> Self {
+        match err {
+            LedgerError::InsufficientBalance => Self::InsufficientBalance,
+            LedgerError::Database(DatabaseError::Conflict) => Self::Conflict,
+            err => Self::Ledger(err),
+        }
+    }
+}
+
+impl From<surrealdb::Error> for WithdrawalError {
+    fn from(err: surrealdb::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
+
+impl From<serde_json::Error> for WithdrawalError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Database(err.into())
+    }
+}
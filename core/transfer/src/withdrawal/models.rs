@@ -0,0 +1,152 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Utc};
+use database::Record;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::errors::WithdrawalError;
+
+/// Stage of a withdrawal. Funds are held from `Requested` on and either leave the
+/// exchange on `Confirmed` or are released back to the user on `Failed` or `Cancelled`.
+///
+/// A signed transaction may still land on chain, so a signed or broadcast withdrawal
+/// that goes wrong is `Stalled` instead of failed, holding the funds until an operator
+/// knows whether the transaction was confirmed or dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalStatus {
+    Requested,
+    PendingReview,
+    Approved,
+    Signed,
+    Broadcast,
+    Stalled,
+    Confirmed,
+    Failed,
+    Cancelled,
+}
+impl WithdrawalStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            WithdrawalStatus::Confirmed | WithdrawalStatus::Failed | WithdrawalStatus::Cancelled
+        )
+    }
+
+    /// Whether a withdrawal in this status may move to `next`.
+    ///
+    /// Withdrawals can be cancelled or fail until they are signed. Afterwards they can
+    /// only stall, and a stalled withdrawal fails once its transaction is known to be
+    /// dropped.
+    pub fn can_transition_to(&self, next: WithdrawalStatus) -> bool {
+        use WithdrawalStatus::*;
+        matches!(
+            (self, next),
+            (Requested, PendingReview | Approved)
+                | (PendingReview, Approved)
+                | (Approved, Signed)
+                | (Signed, Broadcast)
+                | (Broadcast, Confirmed)
+                | (Signed | Broadcast, Stalled)
+                | (Stalled, Confirmed | Failed)
+                | (Requested | PendingReview | Approved, Cancelled | Failed)
+        )
+    }
+}
+impl fmt::Display for WithdrawalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WithdrawalStatus::Requested => "requested",
+            WithdrawalStatus::PendingReview => "pending_review",
+            WithdrawalStatus::Approved => "approved",
+            WithdrawalStatus::Signed => "signed",
+            WithdrawalStatus::Broadcast => "broadcast",
+            WithdrawalStatus::Stalled => "stalled",
+            WithdrawalStatus::Confirmed => "confirmed",
+            WithdrawalStatus::Failed => "failed",
+            WithdrawalStatus::Cancelled => "cancelled",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawalRequest {
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub address: String,
+    /// Amount taken from the user's balance, including the fee.
+    pub amount: i64,
+}
+
+/// A withdrawal of `amount` of which `amount - fee` is sent on chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub asset_id: Uuid,
+    pub address: String,
+    pub amount: i64,
+    pub fee: i64,
+    pub status: WithdrawalStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: u64,
+}
+impl Withdrawal {
+    /// Amount sent to the destination address.
+    pub fn net_amount(&self) -> i64 {
+        self.amount - self.fee
+    }
+
+    /// Returns the withdrawal moved to `next`, failing with `IllegalTransition` if the
+    /// state machine does not allow it.
+    pub fn transition(&self, next: WithdrawalStatus) -> Result<Self, WithdrawalError> {
+        if !self.status.can_transition_to(next) {
+            return Err(WithdrawalError::IllegalTransition {
+                from: self.status,
+                to: next,
+            });
+        }
+        Ok(Self {
+            status: next,
+            updated_at: Utc::now(),
+            ..self.clone()
+        })
+    }
+}
+impl Record for Withdrawal {
+    const TABLE: &'static str = "withdrawals";
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
+
+/// Flat fee charged per withdrawal of an asset, in its smallest unit; assets without a
+/// fee are withdrawn for free.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WithdrawalFees {
+    assets: HashMap<Uuid, i64>,
+}
+impl WithdrawalFees {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn asset(mut self, asset_id: Uuid, fee: i64) -> Self {
+        self.assets.insert(asset_id, fee);
+        self
+    }
+
+    pub fn get(&self, asset_id: &Uuid) -> i64 {
+        self.assets.get(asset_id).copied().unwrap_or_default()
+    }
+}
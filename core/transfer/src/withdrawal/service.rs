@@ -0,0 +1,340 @@
+use chrono::Utc;
+use database::{Actor, AssetRepository, AuditCategory, AuditEvent, Direction, Filter, Repository};
+use surrealdb::{Connection, Surreal};
+use uuid::Uuid;
+
+use super::{
+    errors::WithdrawalError,
+    models::{Withdrawal, WithdrawalFees, WithdrawalRequest, WithdrawalStatus},
+};
+use crate::{
+    approval::models::{ApprovalPolicy, ApprovalRequest, ApprovalStatus},
+    ledger::{
+        errors::LedgerError,
+        journal::{Ledger, Statement},
+        models::{Account, EntryKind, JournalEntry},
+    },
+    limits::service::WithdrawalLimits,
+};
+
+/// Moves withdrawals through their lifecycle and keeps the ledger in step with it.
+///
+/// Requesting a withdrawal holds its amount on the `withdrawals` account. Confirming it
+/// pays the held funds out of the hot wallet and books the fee, while failing or
+/// cancelling it releases them to the user. Each of these ledger entries is committed
+/// in the same transaction as the status change that causes it, and so is the entry in
+/// the audit log recording the change.
+///
+/// Requests are checked against the user's withdrawal limits, velocity rules and
+/// cooling-off period before anything is held. The check and the hold are separate
+/// transactions, so concurrent requests of one user may overshoot a limit by the last
+/// one; see [`WithdrawalLimits::check`]. Every transition takes the [`Actor`] that
+/// caused it, e.g. the user cancelling or the operator signing, for the audit log.
+/// Withdrawals needing a quorum under the approval policy can only be approved through
+/// `approval::service::Approvals`, and only be signed once their approval request is
+/// approved.
+#[derive(Debug)]
+pub struct Withdrawals<C: Connection> {
+    ledger: Ledger<C>,
+    withdrawals: Repository<C, Withdrawal>,
+    assets: AssetRepository<C>,
+    fees: WithdrawalFees,
+    limits: WithdrawalLimits<C>,
+    approvals: ApprovalPolicy,
+    requests: Repository<C, ApprovalRequest>,
+}
+impl<C: Connection> Clone for Withdrawals<C> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: self.ledger.clone(),
+            withdrawals: self.withdrawals.clone(),
+            assets: self.assets.clone(),
+            fees: self.fees.clone(),
+            limits: self.limits.clone(),
+            approvals: self.approvals.clone(),
+            requests: self.requests.clone(),
+        }
+    }
+}
+impl<C: Connection> Withdrawals<C> {
+    pub fn new(
+        db: Surreal<C>,
+        fees: WithdrawalFees,
+        limits: WithdrawalLimits<C>,
+        approvals: ApprovalPolicy,
+    ) -> Self {
+        Self {
+            ledger: Ledger::new(db.clone()),
+            withdrawals: Repository::new(db.clone()),
+            assets: AssetRepository::new(db.clone()),
+            requests: Repository::new(db),
+            fees,
+            limits,
+            approvals,
+        }
+    }
+
+    pub fn db(&self) -> &Surreal<C> {
+        self.ledger.db()
+    }
+
+    pub fn approval_policy(&self) -> &ApprovalPolicy {
+        &self.approvals
+    }
+
+    /// Records a withdrawal in `Requested` and holds its amount, failing with
+    /// `InsufficientBalance` if the user cannot cover it.
+    pub async fn request(
+        &self,
+        request: &WithdrawalRequest,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let asset = self
+            .assets
+            .get(&request.asset_id)
+            .await?
+            .ok_or(WithdrawalError::AssetNotFound)?;
+        if !asset.withdrawals_enabled {
+            return Err(WithdrawalError::Disabled);
+        }
+        let fee = self.fees.get(&asset.id);
+        if request.amount <= fee {
+            return Err(WithdrawalError::InvalidAmount(fee));
+        }
+        if request.address.trim().is_empty() {
+            return Err(WithdrawalError::InvalidAddress);
+        }
+        self.limits.check(request).await?;
+
+        let now = Utc::now();
+        let withdrawal = Withdrawal {
+            id: Uuid::new_v4(),
+            user_id: request.user_id,
+            asset_id: request.asset_id,
+            address: request.address.trim().to_string(),
+            amount: request.amount,
+            fee,
+            status: WithdrawalStatus::Requested,
+            tx_hash: None,
+            failure_reason: None,
+            created_at: now,
+            updated_at: now,
+            version: 0,
+        };
+        let hold = entry(&withdrawal, "hold")
+            .debit(
+                Account::User(withdrawal.user_id),
+                withdrawal.asset_id,
+                withdrawal.amount,
+            )
+            .credit(Account::Withdrawals, withdrawal.asset_id, withdrawal.amount);
+        let event = AuditEvent::new(
+            Actor::User(withdrawal.user_id),
+            AuditCategory::Withdrawal,
+            "withdrawal.request",
+        )
+        .record(None, Some(&withdrawal))?;
+        self.ledger
+            .post_with(
+                &hold,
+                vec![Statement::create(&withdrawal)?, Statement::audit(event)],
+            )
+            .await?;
+        Ok(withdrawal)
+    }
+
+    pub async fn get(&self, id: &Uuid) -> Result<Option<Withdrawal>, WithdrawalError> {
+        Ok(self.withdrawals.get(id).await?)
+    }
+
+    /// Withdrawals of a user, newest first.
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        Ok(self
+            .withdrawals
+            .list(
+                &Filter::new()
+                    .eq("user_id", user_id)
+                    .order_by("created_at", Direction::Desc),
+            )
+            .await?)
+    }
+
+    /// Withdrawals in `status`, oldest first, e.g. to find the ones awaiting review.
+    pub async fn with_status(
+        &self,
+        status: WithdrawalStatus,
+    ) -> Result<Vec<Withdrawal>, WithdrawalError> {
+        Ok(self
+            .withdrawals
+            .list(
+                &Filter::new()
+                    .eq("status", status)
+                    .order_by("created_at", Direction::Asc),
+            )
+            .await?)
+    }
+
+    pub async fn submit_for_review(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::PendingReview, |_| ())
+            .await
+    }
+
+    /// Approves a withdrawal that does not need a quorum of approvers.
+    pub async fn approve(&self, actor: Actor, id: &Uuid) -> Result<Withdrawal, WithdrawalError> {
+        let withdrawal = self.get(id).await?.ok_or(WithdrawalError::NotFound)?;
+        if self.approvals.requires_quorum(&withdrawal) {
+            return Err(WithdrawalError::ApprovalRequired);
+        }
+        self.advance(actor, id, WithdrawalStatus::Approved, |_| ())
+            .await
+    }
+
+    /// Signs an approved withdrawal, failing with `ApprovalRequired` if it needs a
+    /// quorum its approval request did not reach.
+    pub async fn sign(&self, actor: Actor, id: &Uuid) -> Result<Withdrawal, WithdrawalError> {
+        let withdrawal = self.get(id).await?.ok_or(WithdrawalError::NotFound)?;
+        if self.approvals.requires_quorum(&withdrawal) {
+            let status = self.requests.get(id).await?.map(|request| request.status);
+            if status != Some(ApprovalStatus::Approved) {
+                return Err(WithdrawalError::ApprovalRequired);
+            }
+        }
+        self.advance(actor, id, WithdrawalStatus::Signed, |_| ())
+            .await
+    }
+
+    pub async fn broadcast(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+        tx_hash: &str,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::Broadcast, |withdrawal| {
+            withdrawal.tx_hash = Some(tx_hash.to_string())
+        })
+        .await
+    }
+
+    /// Pays the held amount out of the hot wallet and books the fee.
+    pub async fn confirm(&self, actor: Actor, id: &Uuid) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::Confirmed, |_| ())
+            .await
+    }
+
+    /// Holds a signed or broadcast withdrawal whose transaction went wrong until it is
+    /// known to be confirmed or dropped.
+    pub async fn stall(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+        reason: &str,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::Stalled, |withdrawal| {
+            withdrawal.failure_reason = Some(reason.to_string())
+        })
+        .await
+    }
+
+    /// Releases the held amount to the user. A stalled withdrawal must only fail once
+    /// its transaction is known to be dropped, or it is paid out twice.
+    pub async fn fail(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+        reason: &str,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::Failed, |withdrawal| {
+            withdrawal.failure_reason = Some(reason.to_string())
+        })
+        .await
+    }
+
+    /// Releases the held amount to the user.
+    pub async fn cancel(&self, actor: Actor, id: &Uuid) -> Result<Withdrawal, WithdrawalError> {
+        self.advance(actor, id, WithdrawalStatus::Cancelled, |_| ())
+            .await
+    }
+
+    async fn advance(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+        status: WithdrawalStatus,
+        update: impl FnOnce(&mut Withdrawal),
+    ) -> Result<Withdrawal, WithdrawalError> {
+        self.advance_with(actor, id, status, update, Vec::new())
+            .await
+    }
+
+    /// Moves a withdrawal to `status`, recording the change by `actor` in the audit log,
+    /// and runs `statements` in the same transaction.
+    pub(crate) async fn advance_with(
+        &self,
+        actor: Actor,
+        id: &Uuid,
+        status: WithdrawalStatus,
+        update: impl FnOnce(&mut Withdrawal),
+        statements: Vec<Statement>,
+    ) -> Result<Withdrawal, WithdrawalError> {
+        let current = self
+            .withdrawals
+            .get(id)
+            .await?
+            .ok_or(WithdrawalError::NotFound)?;
+        let mut next = current.transition(status)?;
+        update(&mut next);
+
+        let entry = match status {
+            WithdrawalStatus::Confirmed => {
+                let mut settle = entry(&next, "settle")
+                    .debit(Account::Withdrawals, next.asset_id, next.amount)
+                    .credit(Account::HotWallet, next.asset_id, next.net_amount());
+                if next.fee > 0 {
+                    settle = settle.credit(Account::Fees, next.asset_id, next.fee);
+                }
+                Some(settle)
+            }
+            WithdrawalStatus::Failed | WithdrawalStatus::Cancelled => Some(
+                entry(&next, "release")
+                    .debit(Account::Withdrawals, next.asset_id, next.amount)
+                    .credit(Account::User(next.user_id), next.asset_id, next.amount),
+            ),
+            _ => None,
+        };
+
+        let event = AuditEvent::new(
+            actor,
+            AuditCategory::Withdrawal,
+            format!("withdrawal.{status}"),
+        )
+        .record(Some(&current), Some(&next))?;
+        let statements = [
+            vec![Statement::replace(&next)?],
+            statements,
+            vec![Statement::audit(event)],
+        ]
+        .concat();
+        let result = match &entry {
+            Some(entry) => self.ledger.post_with(entry, statements).await.map(drop),
+            None => self.ledger.commit(statements).await,
+        };
+        match result {
+            Ok(()) => Ok(Withdrawal {
+                version: current.version + 1,
+                ..next
+            }),
+            // The entry was posted by an earlier transition of the same withdrawal
+            Err(LedgerError::DuplicateReference(_)) => Err(WithdrawalError::Conflict),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn entry(withdrawal: &Withdrawal, step: &str) -> JournalEntry {
+    JournalEntry::new(EntryKind::Withdrawal)
+        .reference(format!("withdrawal:{}:{step}", withdrawal.id))
+        .memo(step)
+}
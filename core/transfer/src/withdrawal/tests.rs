@@ -0,0 +1,408 @@
+use database::{Actor, AuditLog};
+use surrealdb::engine::local::Db;
+use uuid::Uuid;
+
+use super::{
+    errors::WithdrawalError,
+    models::{WithdrawalFees, WithdrawalStatus},
+    service::Withdrawals,
+};
+use crate::{
+    approval::models::ApprovalPolicy,
+    ledger::{journal::Ledger, models::Account},
+    tests::{balance, funded, unlimited, withdrawal_request},
+};
+
+const ALL: [WithdrawalStatus; 9] = [
+    WithdrawalStatus::Requested,
+    WithdrawalStatus::PendingReview,
+    WithdrawalStatus::Approved,
+    WithdrawalStatus::Signed,
+    WithdrawalStatus::Broadcast,
+    WithdrawalStatus::Stalled,
+    WithdrawalStatus::Confirmed,
+    WithdrawalStatus::Failed,
+    WithdrawalStatus::Cancelled,
+];
+
+struct Fixture {
+    ledger: Ledger<Db>,
+    withdrawals: Withdrawals<Db>,
+    user_id: Uuid,
+    operator: Actor,
+    asset_id: Uuid,
+}
+impl Fixture {
+    async fn new() -> Self {
+        let user_id = Uuid::new_v4();
+        let (db, asset_id) = funded(user_id, 100).await;
+        Self {
+            ledger: Ledger::new(db.clone()),
+            withdrawals: Withdrawals::new(
+                db.clone(),
+                WithdrawalFees::new().asset(asset_id, 2),
+                unlimited(&db),
+                ApprovalPolicy::new(),
+            ),
+            user_id,
+            asset_id,
+            operator: Actor::Admin(Uuid::new_v4()),
+        }
+    }
+}
+
+#[test]
+fn state_machine_allows_only_forward_transitions() {
+    use WithdrawalStatus::*;
+    let allowed = [
+        (Requested, PendingReview),
+        (Requested, Approved),
+        (Requested, Failed),
+        (Requested, Cancelled),
+        (PendingReview, Approved),
+        (PendingReview, Failed),
+        (PendingReview, Cancelled),
+        (Approved, Signed),
+        (Approved, Failed),
+        (Approved, Cancelled),
+        (Signed, Broadcast),
+        (Signed, Stalled),
+        (Broadcast, Confirmed),
+        (Broadcast, Stalled),
+        (Stalled, Confirmed),
+        (Stalled, Failed),
+    ];
+    for from in ALL {
+        for to in ALL {
+            assert_eq!(
+                from.can_transition_to(to),
+                allowed.contains(&(from, to)),
+                "{from} -> {to}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn confirmed_withdrawals_pay_out_and_book_the_fee() {
+    let fixture = Fixture::new().await;
+    let withdrawals = &fixture.withdrawals;
+
+    let withdrawal = withdrawals
+        .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 50))
+        .await
+        .unwrap();
+    assert_eq!(
+        (withdrawal.status, withdrawal.fee, withdrawal.net_amount()),
+        (WithdrawalStatus::Requested, 2, 48)
+    );
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        50
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Withdrawals, fixture.asset_id).await,
+        50
+    );
+
+    withdrawals
+        .submit_for_review(fixture.operator, &withdrawal.id)
+        .await
+        .unwrap();
+    withdrawals
+        .approve(fixture.operator, &withdrawal.id)
+        .await
+        .unwrap();
+    withdrawals
+        .sign(fixture.operator, &withdrawal.id)
+        .await
+        .unwrap();
+    withdrawals
+        .broadcast(fixture.operator, &withdrawal.id, "0xfeed")
+        .await
+        .unwrap();
+    let confirmed = withdrawals
+        .confirm(fixture.operator, &withdrawal.id)
+        .await
+        .unwrap();
+    assert_eq!(confirmed.status, WithdrawalStatus::Confirmed);
+    assert_eq!(confirmed.tx_hash.as_deref(), Some("0xfeed"));
+    assert_eq!(
+        withdrawals.get(&withdrawal.id).await.unwrap(),
+        Some(confirmed)
+    );
+
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        50
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Withdrawals, fixture.asset_id).await,
+        0
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::HotWallet, fixture.asset_id).await,
+        52
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Fees, fixture.asset_id).await,
+        2
+    );
+    assert!(fixture.ledger.verify().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_and_cancelled_withdrawals_release_the_hold() {
+    let fixture = Fixture::new().await;
+    let withdrawals = &fixture.withdrawals;
+
+    let cancelled = withdrawals
+        .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 30))
+        .await
+        .unwrap();
+    let failed = withdrawals
+        .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 40))
+        .await
+        .unwrap();
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        30
+    );
+
+    withdrawals
+        .cancel(Actor::User(fixture.user_id), &cancelled.id)
+        .await
+        .unwrap();
+    withdrawals
+        .approve(fixture.operator, &failed.id)
+        .await
+        .unwrap();
+    withdrawals
+        .sign(fixture.operator, &failed.id)
+        .await
+        .unwrap();
+    withdrawals
+        .broadcast(fixture.operator, &failed.id, "0xdead")
+        .await
+        .unwrap();
+    assert!(matches!(
+        withdrawals
+            .fail(fixture.operator, &failed.id, "reverted")
+            .await,
+        Err(WithdrawalError::IllegalTransition {
+            from: WithdrawalStatus::Broadcast,
+            to: WithdrawalStatus::Failed
+        })
+    ));
+    let stalled = withdrawals
+        .stall(fixture.operator, &failed.id, "not mined")
+        .await
+        .unwrap();
+    assert_eq!(stalled.status, WithdrawalStatus::Stalled);
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        60
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Withdrawals, fixture.asset_id).await,
+        40
+    );
+    let failed = withdrawals
+        .fail(fixture.operator, &failed.id, "dropped")
+        .await
+        .unwrap();
+    assert_eq!(failed.failure_reason.as_deref(), Some("dropped"));
+
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Withdrawals, fixture.asset_id).await,
+        0
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Fees, fixture.asset_id).await,
+        0
+    );
+    assert_eq!(
+        withdrawals
+            .list(fixture.user_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|withdrawal| withdrawal.status)
+            .collect::<Vec<_>>(),
+        [WithdrawalStatus::Failed, WithdrawalStatus::Cancelled]
+    );
+    assert!(fixture.ledger.verify().await.unwrap().is_empty());
+
+    let actors = AuditLog::new(fixture.withdrawals.db().clone())
+        .list(0, 100)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| (entry.event.action, entry.event.actor))
+        .collect::<Vec<_>>();
+    for (status, actor) in [
+        (WithdrawalStatus::Cancelled, Actor::User(fixture.user_id)),
+        (WithdrawalStatus::Signed, fixture.operator),
+        (WithdrawalStatus::Failed, fixture.operator),
+    ] {
+        assert!(actors.contains(&(format!("withdrawal.{status}"), actor)));
+    }
+}
+
+#[tokio::test]
+async fn illegal_transitions_change_nothing() {
+    let fixture = Fixture::new().await;
+    let withdrawals = &fixture.withdrawals;
+    let withdrawal = withdrawals
+        .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 30))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        withdrawals.sign(fixture.operator, &withdrawal.id).await,
+        Err(WithdrawalError::IllegalTransition {
+            from: WithdrawalStatus::Requested,
+            to: WithdrawalStatus::Signed
+        })
+    ));
+    withdrawals
+        .cancel(Actor::User(fixture.user_id), &withdrawal.id)
+        .await
+        .unwrap();
+    for result in [
+        withdrawals
+            .cancel(Actor::User(fixture.user_id), &withdrawal.id)
+            .await,
+        withdrawals
+            .fail(fixture.operator, &withdrawal.id, "late")
+            .await,
+        withdrawals.approve(fixture.operator, &withdrawal.id).await,
+    ] {
+        assert!(matches!(
+            result,
+            Err(WithdrawalError::IllegalTransition {
+                from: WithdrawalStatus::Cancelled,
+                ..
+            })
+        ));
+    }
+    assert!(matches!(
+        withdrawals.approve(fixture.operator, &Uuid::new_v4()).await,
+        Err(WithdrawalError::NotFound)
+    ));
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+}
+
+#[tokio::test]
+async fn requests_are_validated_before_funds_are_held() {
+    let fixture = Fixture::new().await;
+    let withdrawals = &fixture.withdrawals;
+
+    assert!(matches!(
+        withdrawals
+            .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 101))
+            .await,
+        Err(WithdrawalError::InsufficientBalance)
+    ));
+    assert!(matches!(
+        withdrawals
+            .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 2))
+            .await,
+        Err(WithdrawalError::InvalidAmount(2))
+    ));
+    let mut request = withdrawal_request(fixture.user_id, fixture.asset_id, 10);
+    request.address = " ".to_string();
+    assert!(matches!(
+        withdrawals.request(&request).await,
+        Err(WithdrawalError::InvalidAddress)
+    ));
+    let mut request = withdrawal_request(fixture.user_id, fixture.asset_id, 10);
+    request.asset_id = Uuid::new_v4();
+    assert!(matches!(
+        withdrawals.request(&request).await,
+        Err(WithdrawalError::AssetNotFound)
+    ));
+
+    assert!(withdrawals.list(fixture.user_id).await.unwrap().is_empty());
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+}
+
+#[tokio::test]
+async fn concurrent_transitions_release_funds_once() {
+    let fixture = Fixture::new().await;
+    let withdrawals = &fixture.withdrawals;
+    let withdrawal = withdrawals
+        .request(&withdrawal_request(fixture.user_id, fixture.asset_id, 30))
+        .await
+        .unwrap();
+
+    let (cancelled, failed) = tokio::join!(
+        withdrawals.cancel(Actor::User(fixture.user_id), &withdrawal.id),
+        withdrawals.fail(fixture.operator, &withdrawal.id, "rejected")
+    );
+    assert_eq!(cancelled.is_ok() as u8 + failed.is_ok() as u8, 1);
+    for result in [cancelled, failed] {
+        assert!(matches!(
+            result,
+            Ok(_) | Err(WithdrawalError::Conflict | WithdrawalError::IllegalTransition { .. })
+        ));
+    }
+    assert_eq!(
+        balance(
+            &fixture.ledger,
+            Account::User(fixture.user_id),
+            fixture.asset_id
+        )
+        .await,
+        100
+    );
+    assert_eq!(
+        balance(&fixture.ledger, Account::Withdrawals, fixture.asset_id).await,
+        0
+    );
+}